@@ -1,14 +1,18 @@
 mod helpers;
 
+use ::qubed::CoordinateTypes;
 use ::qubed::Coordinates;
 use ::qubed::Datacube;
+use ::qubed::MappedQube;
 use ::qubed::Qube;
+use ::qubed::iter::{Identifier, IdentifierCursor, LeafCursor};
 use ::qubed::metadata::MetadataValues;
-use ::qubed::select::SelectMode;
+use ::qubed::select::{SelectMode, Selector};
+use pyo3::exceptions::PyOverflowError;
 use pyo3::exceptions::PyTypeError;
 use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
-use pyo3::types::{PyDict, PyFloat, PyInt, PyList, PyModule, PySet, PyString};
+use pyo3::types::{PyBytes, PyDict, PyFloat, PyInt, PyList, PyModule, PySet, PyString};
 use serde_json::Value as JsonValue;
 use std::collections::HashMap;
 
@@ -51,22 +55,63 @@ impl PyQube {
         let py_list = PyList::empty(py);
 
         for datacube in &datacubes {
-            let dict = PyDict::new(py);
-            for (dimension, coordinates) in datacube.coordinates() {
-                dict.set_item(dimension, coordinates_to_value(py, coordinates)?)?;
-            }
-            py_list.append(dict)?;
+            py_list.append(datacube_to_pydict(py, datacube)?)?;
         }
 
         // Return an owned Python object so the list outlives this Rust call frame.
         Ok(py_list.into_any().unbind())
     }
 
+    /// Yield the datacubes of `to_datacubes()` one at a time, so that only one
+    /// of them is in memory.
+    pub fn iter_datacubes(slf: PyRef<'_, Self>) -> DatacubeIterator {
+        DatacubeIterator { qube: slf.into(), cursor: LeafCursor::new() }
+    }
+
+    /// Yield every identifier as a `{dimension: value}` dict, expanding each
+    /// datacube into one identifier per combination of its values.
+    pub fn iter_identifiers(slf: PyRef<'_, Self>) -> IdentifierIterator {
+        IdentifierIterator { qube: slf.into(), cursor: IdentifierCursor::new() }
+    }
+
+    /// Up to `limit` identifiers starting at `offset`, in the order of
+    /// `iter_identifiers()`.
+    pub fn page(&self, py: Python<'_>, offset: u128, limit: usize) -> PyResult<Py<PyAny>> {
+        let result = PyList::empty(py);
+        for identifier in self.inner.page(offset, limit) {
+            result.append(identifier_to_pydict(py, identifier)?)?;
+        }
+        Ok(result.into_any().unbind())
+    }
+
+    /// `n` distinct identifiers drawn uniformly at random; the same `seed`
+    /// gives the same sample.
+    #[pyo3(signature = (n, seed=0))]
+    pub fn sample(&self, py: Python<'_>, n: usize, seed: u64) -> PyResult<Py<PyAny>> {
+        let result = PyList::empty(py);
+        for identifier in self.inner.sample(n, seed) {
+            result.append(identifier_to_pydict(py, identifier)?)?;
+        }
+        Ok(result.into_any().unbind())
+    }
+
     pub fn to_arena_json(&self) -> PyResult<String> {
         let v = self.inner.to_arena_json();
         serde_json::to_string(&v).map_err(|e| PyTypeError::new_err(e.to_string()))
     }
 
+    /// Stream the arena JSON layout to a file without building it in memory.
+    pub fn to_arena_json_file(&self, path: &str) -> PyResult<()> {
+        let file = std::fs::File::create(path)
+            .map_err(|e| PyTypeError::new_err(format!("Failed to create {path}: {e}")))?;
+        self.inner.to_arena_json_writer(file).map_err(PyTypeError::new_err)
+    }
+
+    /// The compact binary serialisation, see `from_bytes`.
+    pub fn to_bytes<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.inner.to_bytes())
+    }
+
     pub fn to_json(&self) -> PyResult<String> {
         let v = self.inner.to_json();
         serde_json::to_string(&v).map_err(|e| PyTypeError::new_err(e.to_string()))
@@ -95,14 +140,60 @@ impl PyQube {
         }
     }
 
+    pub fn to_yaml(&self) -> String {
+        self.inner.to_yaml()
+    }
+
+    pub fn to_yaml_datacubes(&self) -> String {
+        self.inner.to_yaml_datacubes()
+    }
+
+    #[staticmethod]
+    pub fn from_yaml(input: &str) -> PyResult<Self> {
+        Qube::from_yaml(input).map(|qube| PyQube { inner: qube }).map_err(PyTypeError::new_err)
+    }
+
     #[pyo3(name = "__str__")]
     pub fn py_str(&self) -> PyResult<String> {
         self.to_ascii()
     }
 
+    /// The number of identifiers, see `identifier_count()`.
     #[pyo3(name = "__len__")]
     pub fn py_len(&self) -> PyResult<usize> {
-        Ok(self.inner.datacube_count())
+        usize::try_from(self.inner.identifier_count()).map_err(|_| {
+            PyOverflowError::new_err("too many identifiers for len(), use identifier_count()")
+        })
+    }
+
+    /// The number of fully expanded identifiers, counting every combination
+    /// of values along each leaf path.
+    pub fn identifier_count(&self) -> u128 {
+        self.inner.identifier_count()
+    }
+
+    /// The number of leaf paths, i.e. of datacubes in `to_datacubes()`.
+    pub fn datacube_count(&self) -> usize {
+        self.inner.datacube_count()
+    }
+
+    /// The number of nodes below the root.
+    pub fn node_count(&self) -> usize {
+        self.inner.node_count()
+    }
+
+    /// For each value of `dimension`, the number of identifiers that have it.
+    pub fn value_histogram(&self, py: Python<'_>, dimension: &str) -> PyResult<Py<PyAny>> {
+        let result = PyDict::new(py);
+        for (value, count) in self.inner.value_histogram(dimension) {
+            result.set_item(coordinate_to_py(py, &value)?, count)?;
+        }
+        Ok(result.into_any().unbind())
+    }
+
+    #[staticmethod]
+    pub fn from_bytes(data: &[u8]) -> PyResult<Self> {
+        Qube::from_bytes(data).map(|qube| PyQube { inner: qube }).map_err(PyTypeError::new_err)
     }
 
     #[staticmethod]
@@ -114,6 +205,16 @@ impl PyQube {
         }
     }
 
+    /// Read a file written by `to_arena_json_file`, one node at a time.
+    #[staticmethod]
+    pub fn from_arena_json_file(path: &str) -> PyResult<Self> {
+        let file = std::fs::File::open(path)
+            .map_err(|e| PyTypeError::new_err(format!("Failed to open {path}: {e}")))?;
+        Qube::from_arena_json_reader(file)
+            .map(|qube| PyQube { inner: qube })
+            .map_err(PyTypeError::new_err)
+    }
+
     #[staticmethod]
     #[pyo3(signature = (datacube, order=None))]
     pub fn from_datacube(
@@ -146,35 +247,16 @@ impl PyQube {
         mode: Option<String>,
         _consume: Option<bool>,
     ) -> PyResult<PyQube> {
-        // Collect selection data with owned Strings and Coordinates
-        let mut selection_data: Vec<(String, Coordinates)> = Vec::new();
-
-        for (k, v) in request.iter() {
-            let key: String =
-                k.extract().map_err(|_| PyTypeError::new_err("select keys must be strings"))?;
-
-            let coords = if v.is_instance_of::<PyList>() {
-                let lst =
-                    v.downcast::<PyList>().map_err(|e| PyTypeError::new_err(e.to_string()))?;
-                let joined = join_pylist_as_path(lst)?;
-                Coordinates::from_string(&joined)
-            } else {
-                // Convert any value to string representation (handles int, float, str)
-                let py_str = v.str()?;
-                let s: String = py_str.extract()?;
-                Coordinates::from_string(&s)
-            };
-
-            selection_data.push((key, coords));
-        }
+        let selection_data = pydict_to_selection(&request)?;
 
         let select_mode = match mode.as_deref() {
             Some(m) if m.eq_ignore_ascii_case("prune") => SelectMode::Prune,
+            Some(m) if m.eq_ignore_ascii_case("strict") => SelectMode::Strict,
             _ => SelectMode::Default,
         };
 
         // Convert to references for the select call
-        let pairs: Vec<(&str, Coordinates)> =
+        let pairs: Vec<(&str, Selector)> =
             selection_data.iter().map(|(k, c)| (k.as_str(), c.clone())).collect();
 
         match self.inner.select(&pairs, select_mode) {
@@ -183,6 +265,56 @@ impl PyQube {
         }
     }
 
+    /// Explain which values of each selected dimension were present and
+    /// matched, and where an empty selection was cut.  The `message` entry is
+    /// a readable summary.
+    pub fn explain_select(
+        &self,
+        py: Python<'_>,
+        request: Bound<'_, PyDict>,
+    ) -> PyResult<Py<PyAny>> {
+        let selection_data = pydict_to_selection(&request)?;
+        let pairs: Vec<(&str, Selector)> =
+            selection_data.iter().map(|(k, c)| (k.as_str(), c.clone())).collect();
+        let explanation = self.inner.explain_select(&pairs).map_err(PyTypeError::new_err)?;
+
+        let dimensions = PyList::empty(py);
+        for dim in &explanation.dimensions {
+            let entry = PyDict::new(py);
+            entry.set_item("dimension", &dim.dimension)?;
+            entry.set_item("requested", dim.requested.to_string())?;
+            entry.set_item("present", coordinates_to_list(py, &dim.present)?)?;
+            entry.set_item("matched", coordinates_to_list(py, &dim.matched)?)?;
+            entry.set_item("cut_branches", dim.cut_branches)?;
+            entry.set_item("known", dim.known)?;
+            entry.set_item("suggestions", &dim.suggestions)?;
+            dimensions.append(entry)?;
+        }
+
+        let result = PyDict::new(py);
+        result.set_item("dimensions", dimensions)?;
+        result.set_item("cut_depth", explanation.cut_depth)?;
+        result.set_item("cut_by", &explanation.cut_by)?;
+        result.set_item("message", explanation.to_string())?;
+        Ok(result.into_any().unbind())
+    }
+
+    /// Keep only the identifiers allowed by `mask`; dimensions missing from
+    /// the mask allow any value.
+    #[pyo3(signature = (mask, mode=None))]
+    pub fn select_qube(&self, mask: &PyQube, mode: Option<String>) -> PyResult<PyQube> {
+        let select_mode = match mode.as_deref() {
+            Some(m) if m.eq_ignore_ascii_case("prune") => SelectMode::Prune,
+            Some(m) if m.eq_ignore_ascii_case("strict") => SelectMode::Strict,
+            _ => SelectMode::Default,
+        };
+
+        match self.inner.select_qube(&mask.inner, select_mode) {
+            Ok(q) => Ok(PyQube { inner: q }),
+            Err(e) => Err(PyTypeError::new_err(e)),
+        }
+    }
+
     pub fn all_unique_dim_coords(&mut self, py: Python<'_>) -> PyResult<Py<PyAny>> {
         let dim_coords = self.inner.all_unique_dim_coords();
         let py_dict = PyDict::new(py);
@@ -233,6 +365,26 @@ impl PyQube {
         Ok(PyQube { inner: result })
     }
 
+    /// Returns a new Qube with the dimensions in `order` nested outermost,
+    /// in that order, and every other dimension below them.  Metadata is kept.
+    pub fn reorder(&self, order: Vec<String>) -> PyQube {
+        let order: Vec<&str> = order.iter().map(String::as_str).collect();
+        PyQube { inner: self.inner.reorder(&order) }
+    }
+
+    /// Searches for the dimension order giving the fewest nodes.  Returns a
+    /// dict with the reordered `qube`, its `order`, and `nodes_before` and
+    /// `nodes_after`.
+    pub fn optimize_order(&self, py: Python<'_>) -> PyResult<Py<PyAny>> {
+        let optimized = self.inner.optimize_order();
+        let result = PyDict::new(py);
+        result.set_item("qube", PyQube { inner: optimized.qube })?;
+        result.set_item("order", optimized.order)?;
+        result.set_item("nodes_before", optimized.nodes_before)?;
+        result.set_item("nodes_after", optimized.nodes_after)?;
+        Ok(result.into_any().unbind())
+    }
+
     /// Wrap this Qube under new parent node(s).
     ///
     /// Takes a datacube dict `{dim: value}` and optional order, mirroring
@@ -269,10 +421,7 @@ impl PyQube {
 
     #[pyo3(name = "__or__")]
     pub fn _or_wrapper(&self, other: &Bound<'_, PyQube>) -> PyResult<Self> {
-        let mut result = self.inner.clone();
-        let mut other_inner = other.borrow().inner.clone();
-        result.append(&mut other_inner);
-        Ok(PyQube { inner: result })
+        Ok(PyQube { inner: self.inner.union(&other.borrow().inner) })
     }
 
     /// Returns a new Qube containing every identifier in `self` that is not
@@ -287,6 +436,55 @@ impl PyQube {
         self.subtract(other)
     }
 
+    /// Returns a new Qube containing every identifier present in both `self`
+    /// and `other`.  Neither operand is modified.
+    pub fn intersect(&self, other: &Bound<'_, PyQube>) -> PyResult<PyQube> {
+        let other_ref = other.borrow();
+        Ok(PyQube { inner: self.inner.intersect(&other_ref.inner) })
+    }
+
+    /// Python operator sugar: `a & b` delegates to `a.intersect(b)`.
+    pub fn __and__(&self, other: &Bound<'_, PyQube>) -> PyResult<PyQube> {
+        self.intersect(other)
+    }
+
+    /// Returns a new Qube containing every identifier present in exactly one of
+    /// `self` and `other`.  If `provenance_key` is given, each identifier is tagged
+    /// with that metadata key set to "left" or "right".
+    #[pyo3(signature = (other, provenance_key=None))]
+    pub fn symmetric_difference(
+        &self,
+        other: &Bound<'_, PyQube>,
+        provenance_key: Option<&str>,
+    ) -> PyResult<PyQube> {
+        let other_ref = other.borrow();
+        let inner = match provenance_key {
+            Some(key) => self.inner.symmetric_difference_with_provenance(&other_ref.inner, key),
+            None => self.inner.symmetric_difference(&other_ref.inner),
+        };
+        Ok(PyQube { inner })
+    }
+
+    /// Python operator sugar: `a ^ b` delegates to `a.symmetric_difference(b)`.
+    pub fn __xor__(&self, other: &Bound<'_, PyQube>) -> PyResult<PyQube> {
+        self.symmetric_difference(other, None)
+    }
+
+    /// Returns ``True`` if every identifier in `self` is also in `other`.
+    pub fn is_subset_of(&self, other: &Bound<'_, PyQube>) -> bool {
+        self.inner.is_subset_of(&other.borrow().inner)
+    }
+
+    /// Returns ``True`` if every identifier in `other` is also in `self`.
+    pub fn is_superset_of(&self, other: &Bound<'_, PyQube>) -> bool {
+        self.inner.is_superset_of(&other.borrow().inner)
+    }
+
+    /// Returns ``True`` if `self` and `other` have no identifier in common.
+    pub fn is_disjoint(&self, other: &Bound<'_, PyQube>) -> bool {
+        self.inner.is_disjoint(&other.borrow().inner)
+    }
+
     pub fn append_many(&mut self, others: &Bound<'_, PyList>) -> PyResult<()> {
         // First validate all types so type errors happen before any mutation.
         let mut validated_qubes = Vec::with_capacity(others.len());
@@ -393,11 +591,11 @@ impl PyQube {
                 let lst =
                     v.downcast::<PyList>().map_err(|e| PyTypeError::new_err(e.to_string()))?;
                 let joined = join_pylist_as_path(lst)?;
-                Coordinates::from_string(&joined)
+                Coordinates::from_dimension_string(&key, &joined)
             } else {
                 let py_str = v.str()?;
                 let s: String = py_str.extract()?;
-                Coordinates::from_string(&s)
+                Coordinates::from_dimension_string(&key, &s)
             };
 
             self.inner.expand(&key, coords).map_err(PyTypeError::new_err)?;
@@ -496,8 +694,8 @@ impl PyQube {
     /// * **`dict[str, list]`** — returns ``True`` if every key in the dict is
     ///   a dimension that exists *and* every value listed for that key is
     ///   present in that dimension's coordinate set.
-    /// * **`Qube`** — checks that every dimension/value present in the other
-    ///   Qube also exists in this one (subset check).
+    /// * **`Qube`** — returns ``True`` if every identifier in the other Qube
+    ///   is also an identifier of this one (see ``is_subset_of``).
     ///
     /// ```python
     /// q = Qube.from_datacube({"param": "2t/tp", "time": "0/1/2"}, ["param", "time"])
@@ -510,16 +708,7 @@ impl PyQube {
     /// ```
     pub fn contains(&self, py: Python<'_>, item: Bound<'_, PyAny>) -> PyResult<bool> {
         // Helper: convert a Coordinates value to a Vec<String> of individual values.
-        let coords_to_strings = |coords: &::qubed::Coordinates| -> Vec<String> {
-            let s = coords.to_string();
-            if s.is_empty() {
-                vec![]
-            } else if s.contains('/') {
-                s.split('/').map(|v| v.to_owned()).collect()
-            } else {
-                vec![s]
-            }
-        };
+        let coords_to_strings = |coords: &::qubed::Coordinates| coords.to_value_strings();
 
         // Pre-compute current axes as Vec<String> per dimension.
         let current_axes: std::collections::BTreeMap<String, Vec<String>> = self
@@ -541,27 +730,7 @@ impl PyQube {
 
         // --- branch 3: item is a Qube ---
         if let Ok(other_cell) = item.downcast::<PyQube>() {
-            let other_ref = other_cell.borrow();
-            let other_axes: std::collections::BTreeMap<String, Vec<String>> = other_ref
-                .inner
-                .all_unique_dim_coords()
-                .into_iter()
-                .map(|(k, v)| (k, coords_to_strings(&v)))
-                .collect();
-
-            for (key, other_vals) in &other_axes {
-                match current_axes.get(key) {
-                    None => return Ok(false),
-                    Some(cur_vals) => {
-                        for oval in other_vals {
-                            if !cur_vals.contains(oval) {
-                                return Ok(false);
-                            }
-                        }
-                    }
-                }
-            }
-            return Ok(true);
+            return Ok(other_cell.borrow().inner.is_subset_of(&self.inner));
         }
 
         Err(PyTypeError::new_err("contains: item must be a str, dict[str, list], or Qube"))
@@ -689,6 +858,109 @@ impl PyQube {
     }
 }
 
+/// Generator returned by `Qube.iter_datacubes()`.  It keeps the Qube alive
+/// and reads it on each step.
+#[pyclass(unsendable, name = "DatacubeIterator")]
+pub struct DatacubeIterator {
+    qube: Py<PyQube>,
+    cursor: LeafCursor,
+}
+
+#[pymethods]
+impl DatacubeIterator {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&mut self, py: Python<'_>) -> PyResult<Option<Py<PyAny>>> {
+        let qube = self.qube.borrow(py);
+        match self.cursor.next_datacube(&qube.inner) {
+            Some(datacube) => Ok(Some(datacube_to_pydict(py, &datacube)?.into_any().unbind())),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Generator returned by `Qube.iter_identifiers()`.
+#[pyclass(unsendable, name = "IdentifierIterator")]
+pub struct IdentifierIterator {
+    qube: Py<PyQube>,
+    cursor: IdentifierCursor,
+}
+
+#[pymethods]
+impl IdentifierIterator {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&mut self, py: Python<'_>) -> PyResult<Option<Py<PyAny>>> {
+        let qube = self.qube.borrow(py);
+        match self.cursor.next_identifier(&qube.inner) {
+            Some(identifier) => Ok(Some(identifier_to_pydict(py, identifier)?.into_any().unbind())),
+            None => Ok(None),
+        }
+    }
+}
+
+/// A read-only Qube over a binary file written by `Qube.to_bytes()`, mapped
+/// into memory and decoded a node at a time.
+#[pyclass(unsendable, name = "MappedQube")]
+pub struct PyMappedQube {
+    inner: MappedQube,
+}
+
+#[pymethods]
+impl PyMappedQube {
+    /// Map the binary Qube at `path`.  The checksum is only checked by
+    /// `verify()`.
+    #[staticmethod]
+    pub fn open(path: &str) -> PyResult<Self> {
+        MappedQube::open(path).map(|inner| PyMappedQube { inner }).map_err(PyTypeError::new_err)
+    }
+
+    pub fn verify(&self) -> PyResult<()> {
+        self.inner.verify().map_err(PyTypeError::new_err)
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.inner.node_count()
+    }
+
+    pub fn to_qube(&self) -> PyResult<PyQube> {
+        self.inner.to_qube().map(|inner| PyQube { inner }).map_err(PyTypeError::new_err)
+    }
+
+    pub fn to_datacubes(&self, py: Python<'_>) -> PyResult<Py<PyAny>> {
+        let datacubes = self.inner.to_datacubes().map_err(PyTypeError::new_err)?;
+        let py_list = PyList::empty(py);
+        for datacube in &datacubes {
+            py_list.append(datacube_to_pydict(py, datacube)?)?;
+        }
+        Ok(py_list.into_any().unbind())
+    }
+
+    /// Select as `Qube.select` does, decoding only the matching subtrees.
+    #[pyo3(signature = (request, mode=None))]
+    pub fn select(&self, request: Bound<'_, PyDict>, mode: Option<String>) -> PyResult<PyQube> {
+        let selection_data = pydict_to_selection(&request)?;
+
+        let select_mode = match mode.as_deref() {
+            Some(m) if m.eq_ignore_ascii_case("prune") => SelectMode::Prune,
+            Some(m) if m.eq_ignore_ascii_case("strict") => SelectMode::Strict,
+            _ => SelectMode::Default,
+        };
+
+        let pairs: Vec<(&str, Selector)> =
+            selection_data.iter().map(|(k, c)| (k.as_str(), c.clone())).collect();
+
+        match self.inner.select(&pairs, select_mode) {
+            Ok(q) => Ok(PyQube { inner: q }),
+            Err(e) => Err(PyTypeError::new_err(e)),
+        }
+    }
+}
+
 // -------------------------
 //  Metadata helpers
 // -------------------------
@@ -740,12 +1012,11 @@ fn find_node_by_path(
                             let child_ref = qube
                                 .node(child_id)
                                 .ok_or_else(|| "Child node not found".to_string())?;
-                            let coord_str = child_ref.coordinates().to_string();
-                            let matches = if coord_str.contains('/') {
-                                coord_str.split('/').any(|p| p == want_val.as_str())
-                            } else {
-                                coord_str == *want_val
-                            };
+                            let matches = child_ref
+                                .coordinates()
+                                .to_value_strings()
+                                .iter()
+                                .any(|p| p == want_val);
                             if matches {
                                 found = Some((dim_str.clone(), child_id));
                                 break 'outer;
@@ -888,6 +1159,10 @@ fn json_scalar_to_py<'py>(
 
 /// Convert Coordinates to a Python list (always returns a list, even for single elements).
 fn coordinates_to_list(py: Python<'_>, coords: &Coordinates) -> PyResult<Py<PyAny>> {
+    // Integer ranges serialise to their compact string form, so list them directly.
+    if let Coordinates::Integers(ints) = coords {
+        return Ok(PyList::new(py, ints.iter().collect::<Vec<_>>())?.into_any().unbind());
+    }
     match coords.to_json_value() {
         serde_json::Value::Array(arr) if arr.is_empty() => {
             Ok(PyList::empty(py).into_any().unbind())
@@ -906,6 +1181,35 @@ fn coordinates_to_list(py: Python<'_>, coords: &Coordinates) -> PyResult<Py<PyAn
 
 /// Convert Coordinates to a Python value, unwrapping single-element arrays to scalars.
 /// Suitable for datacube entries where each dimension has exactly one value.
+/// A single value as a Python scalar; date-times and durations become strings.
+fn coordinate_to_py(py: Python<'_>, value: &CoordinateTypes) -> PyResult<Py<PyAny>> {
+    Ok(match value {
+        CoordinateTypes::Integer(v) => v.into_pyobject(py)?.into_any().unbind(),
+        CoordinateTypes::Float(v) => v.into_pyobject(py)?.into_any().unbind(),
+        CoordinateTypes::String(v) => v.into_pyobject(py)?.into_any().unbind(),
+        other => other.to_string().into_pyobject(py)?.into_any().unbind(),
+    })
+}
+
+fn identifier_to_pydict<'py>(
+    py: Python<'py>,
+    identifier: Identifier<'_>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new(py);
+    for (dimension, value) in identifier {
+        dict.set_item(dimension, coordinate_to_py(py, &value)?)?;
+    }
+    Ok(dict)
+}
+
+fn datacube_to_pydict<'py>(py: Python<'py>, datacube: &Datacube) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new(py);
+    for (dimension, coordinates) in datacube.coordinates() {
+        dict.set_item(dimension, coordinates_to_value(py, coordinates)?)?;
+    }
+    Ok(dict)
+}
+
 fn coordinates_to_value(py: Python<'_>, coords: &Coordinates) -> PyResult<Py<PyAny>> {
     match coords.to_json_value() {
         serde_json::Value::Array(arr) if arr.len() == 1 => {
@@ -934,7 +1238,7 @@ fn pydict_to_datacube(datacube: Bound<'_, PyDict>) -> PyResult<(Datacube, Vec<St
             Coordinates::from(val)
         } else {
             let s: String = v.extract().unwrap_or_else(|_| v.str().unwrap().extract().unwrap());
-            Coordinates::from_string(&s)
+            Coordinates::from_dimension_string(&key, &s)
         };
         dc.add_coordinate(&key, coords);
     }
@@ -943,6 +1247,55 @@ fn pydict_to_datacube(datacube: Bound<'_, PyDict>) -> PyResult<(Datacube, Vec<St
 
 /// Build Coordinates from a Python list, preserving the types of the elements.
 /// If the list contains ints, store as integers. If strings, store as strings.
+/// Converts a `select` request into selectors: a compiled `re` pattern or a
+/// `"glob:"` prefixed string matches by pattern, anything else by value.
+fn pydict_to_selection(request: &Bound<'_, PyDict>) -> PyResult<Vec<(String, Selector)>> {
+    let mut selection_data: Vec<(String, Selector)> = Vec::new();
+    let regex_type = PyModule::import(request.py(), "re")?.getattr("Pattern")?;
+
+    for (k, v) in request.iter() {
+        let key: String =
+            k.extract().map_err(|_| PyTypeError::new_err("select keys must be strings"))?;
+
+        // A compiled `re` pattern, matched against the whole value
+        if v.is_instance(&regex_type)? {
+            let pattern: String = v.getattr("pattern")?.extract().map_err(|_| {
+                PyTypeError::new_err("select regex patterns must be str, not bytes")
+            })?;
+            let flags: i64 = v.getattr("flags")?.extract()?;
+            let ignore_case = flags & 2 != 0; // re.IGNORECASE
+            let pattern = if ignore_case { format!("(?i){}", pattern) } else { pattern };
+            let selector = Selector::regex(&pattern).map_err(PyValueError::new_err)?;
+            selection_data.push((key, selector));
+            continue;
+        }
+
+        // A "glob:" prefixed string, e.g. "glob:0*"
+        if let Ok(text) = v.extract::<String>()
+            && let Some(glob) = text.strip_prefix("glob:")
+        {
+            let selector = Selector::glob(glob).map_err(PyValueError::new_err)?;
+            selection_data.push((key, selector));
+            continue;
+        }
+
+        let coords = if v.is_instance_of::<PyList>() {
+            let lst = v.downcast::<PyList>().map_err(|e| PyTypeError::new_err(e.to_string()))?;
+            let joined = join_pylist_as_path(lst)?;
+            Coordinates::from_dimension_string(&key, &joined)
+        } else {
+            // Convert any value to string representation (handles int, float, str)
+            let py_str = v.str()?;
+            let s: String = py_str.extract()?;
+            Coordinates::from_dimension_string(&key, &s)
+        };
+
+        selection_data.push((key, Selector::Values(coords)));
+    }
+
+    Ok(selection_data)
+}
+
 fn pylist_to_coords(lst: &Bound<'_, PyList>) -> PyResult<Coordinates> {
     let mut coords = Coordinates::new();
     for item in lst.iter() {
@@ -1034,5 +1387,8 @@ fn py_any_to_json(obj: &Bound<'_, PyAny>) -> PyResult<JsonValue> {
 #[pyo3(name = "qubed")]
 fn py_qubed_module(_py: Python<'_>, m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<PyQube>()?;
+    m.add_class::<DatacubeIterator>()?;
+    m.add_class::<IdentifierIterator>()?;
+    m.add_class::<PyMappedQube>()?;
     Ok(())
 }
@@ -0,0 +1,48 @@
+from qubed import Qube
+
+
+# ---------------------------------------------------------------------------
+# Helpers
+# ---------------------------------------------------------------------------
+
+def _simple(class_val: str, param_val: str = "1") -> Qube:
+    return Qube.from_ascii(f"root\n└── class={class_val}\n    └── param={param_val}\n")
+
+
+def _two_branches() -> Qube:
+    return Qube.from_ascii(
+        "root\n"
+        "├── class=od\n"
+        "│   └── param=1\n"
+        "└── class=rd\n"
+        "    └── param=2\n"
+    )
+
+
+# ---------------------------------------------------------------------------
+# contains(Qube)
+# ---------------------------------------------------------------------------
+
+def test_contains_qube_is_structural() -> None:
+    """Both axis values exist in A, but the combination class=od,param=2 does not."""
+    a = _two_branches()
+    assert a.contains(_simple("od", "2")) is False
+    assert a.contains(_simple("rd", "2")) is True
+
+
+# ---------------------------------------------------------------------------
+# Predicates
+# ---------------------------------------------------------------------------
+
+def test_subset_and_superset() -> None:
+    a = _two_branches()
+    small = _simple("od", "1")
+    assert small.is_subset_of(a)
+    assert a.is_superset_of(small)
+    assert not a.is_subset_of(small)
+
+
+def test_is_disjoint() -> None:
+    a = _two_branches()
+    assert a.is_disjoint(_simple("od", "2"))
+    assert not a.is_disjoint(_simple("od/rd", "1"))
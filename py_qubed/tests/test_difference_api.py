@@ -204,11 +204,10 @@ def test_subtract_result_is_independent() -> None:
 # ---------------------------------------------------------------------------
 
 def test_subtract_len_reflects_removed_identifiers() -> None:
-    """len() counts leaf-path nodes; subtracting one complete branch reduces the count.
+    """len() counts identifiers; subtracting one complete branch reduces the count.
 
-    Three distinct branches each with a unique param value means three leaf-path
-    nodes (they cannot be merged because their sub-trees differ).  Removing one
-    whole branch drops len() from 3 → 2.
+    Three distinct branches each with a unique param value means three
+    identifiers.  Removing one whole branch drops len() from 3 → 2.
     """
     a = Qube.from_ascii(
         "root\n"
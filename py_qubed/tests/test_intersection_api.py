@@ -0,0 +1,45 @@
+from qubed import Qube
+
+
+# ---------------------------------------------------------------------------
+# Helpers
+# ---------------------------------------------------------------------------
+
+def _simple(class_val: str, param_val: str = "1") -> Qube:
+    return Qube.from_ascii(f"root\n└── class={class_val}\n    └── param={param_val}\n")
+
+
+# ---------------------------------------------------------------------------
+# Intersection
+# ---------------------------------------------------------------------------
+
+def test_intersect_identical_returns_same() -> None:
+    """A ∩ A = A."""
+    a = _simple("od", "1/2")
+    result = a.intersect(a)
+    assert result.to_ascii() == a.to_ascii()
+
+
+def test_intersect_disjoint_returns_empty() -> None:
+    """Qubes sharing no identifiers have an empty intersection."""
+    a = _simple("od")
+    b = _simple("rd")
+    assert (a & b).all_unique_dim_coords() == {}
+
+
+def test_intersect_keeps_only_common_values() -> None:
+    a = _simple("od/rd", "1/2/3")
+    b = _simple("od", "2/3/4")
+    result = a & b
+    coords = result.all_unique_dim_coords()
+    assert coords["class"] == ["od"]
+    assert coords["param"] == [2, 3]
+
+
+def test_intersect_does_not_modify_operands() -> None:
+    a = _simple("od/rd", "1/2")
+    b = _simple("od", "2")
+    a_before, b_before = a.to_ascii(), b.to_ascii()
+    _ = a & b
+    assert a.to_ascii() == a_before
+    assert b.to_ascii() == b_before
@@ -1,6 +1,7 @@
-from qubed import Qube
+from qubed import MappedQube, Qube
 import pytest
 import copy
+import json
 
 ASCII_INPUT = """root
 └── class=3
@@ -66,6 +67,61 @@ def test_to_datacubes_shape() -> None:
     assert datacubes[0]["param"] == 42
 
 
+def test_iter_datacubes_and_identifiers() -> None:
+    qube = Qube.from_ascii("""root
+├── class=od
+│   └── param=1/2
+└── class=rd
+    └── param=3
+""")
+
+    datacubes = qube.iter_datacubes()
+    assert iter(datacubes) is datacubes
+    assert list(datacubes) == qube.to_datacubes()
+
+    identifiers = list(qube.iter_identifiers())
+    assert identifiers == [
+        {"class": "od", "param": 1},
+        {"class": "od", "param": 2},
+        {"class": "rd", "param": 3},
+    ]
+    assert list(Qube().iter_identifiers()) == []
+
+
+def test_identifier_count_and_value_histogram() -> None:
+    qube = Qube.from_ascii("""root
+├── class=od
+│   └── expver=0001/0002
+│       └── param=1/2/3
+└── class=rd
+    └── param=1/2
+""")
+    assert qube.datacube_count() == 2
+    assert qube.identifier_count() == 8
+    assert len(qube) == 8
+    assert qube.value_histogram("param") == {1: 3, 2: 3, 3: 2}
+    assert qube.value_histogram("expver") == {"0001": 3, "0002": 3}
+    assert qube.value_histogram("step") == {}
+
+
+def test_page_and_sample() -> None:
+    qube = Qube.from_ascii("""root
+├── class=od
+│   └── param=1/2/3
+└── class=rd
+    └── param=4/5
+""")
+    everything = list(qube.iter_identifiers())
+    assert qube.page(0, 2) + qube.page(2, 2) + qube.page(4, 2) == everything
+    assert qube.page(10, 2) == []
+
+    sample = qube.sample(3, seed=7)
+    assert len(sample) == 3
+    assert sample == qube.sample(3, seed=7)
+    assert all(identifier in everything for identifier in sample)
+    assert qube.sample(10) == everything
+
+
 def test_str_and_len_dunder_methods() -> None:
     qube = Qube.from_ascii("""root
 └── class=5
@@ -102,6 +158,70 @@ def test_to_from_arena_json_roundtrip() -> None:
     assert reconstructed.to_ascii() == qube.to_ascii()
 
 
+def test_to_from_bytes_roundtrip() -> None:
+    qube = Qube.from_ascii("""root
+├── class=od
+│   └── param=1/2/3
+└── class=rd
+    └── param=0/to/240/by/6
+""")
+
+    data = qube.to_bytes()
+    assert isinstance(data, bytes)
+    assert data[:4] == b"QUBE"
+    assert len(data) < len(qube.to_arena_json())
+    assert Qube.from_bytes(data).to_ascii() == qube.to_ascii()
+
+    corrupt = bytearray(data)
+    corrupt[len(data) // 2] ^= 0x40
+    with pytest.raises(TypeError, match="checksum"):
+        Qube.from_bytes(bytes(corrupt))
+
+
+def test_arena_json_file_roundtrip(tmp_path) -> None:
+    qube = Qube.from_ascii("""root
+├── class=od
+│   └── expver=0001/0002
+│       └── param=1/2/3
+└── class=rd
+    └── param=0/to/240/by/6
+""")
+    path = tmp_path / "climate.json"
+    qube.to_arena_json_file(str(path))
+    assert json.loads(path.read_text()) == json.loads(qube.to_arena_json())
+    assert Qube.from_arena_json_file(str(path)).to_ascii() == qube.to_ascii()
+
+    path.write_text('{"version": "1", "qube": [{"dim": "root", "coords": null, "parent": null, "children": [1]}]}')
+    with pytest.raises(TypeError, match="missing"):
+        Qube.from_arena_json_file(str(path))
+
+
+def test_mapped_qube_reads_binary_file(tmp_path) -> None:
+    qube = Qube.from_ascii("""root
+├── class=od
+│   ├── expver=0001
+│   │   └── param=1/2/3
+│   └── expver=0002
+│       └── param=1/2
+└── class=rd
+    └── expver=0001
+        └── param=0/to/240/by/6
+""")
+    path = tmp_path / "climate.qube"
+    path.write_bytes(qube.to_bytes())
+
+    mapped = MappedQube.open(str(path))
+    mapped.verify()
+    assert mapped.node_count() == qube.node_count()
+    assert mapped.to_qube().to_ascii() == qube.to_ascii()
+    assert mapped.to_datacubes() == qube.to_datacubes()
+
+    request = {"class": "od", "param": ["2", "3"]}
+    assert mapped.select(request).to_ascii() == qube.select(request).to_ascii()
+    with pytest.raises(TypeError, match="not found"):
+        mapped.select({"clas": "od"}, mode="strict")
+
+
 def test_from_datacube_basic() -> None:
     """Build a Qube from a single datacube dict and verify all dimensions appear."""
     dc = {"class": "od", "expver": "0001", "param": "1"}
@@ -260,6 +380,31 @@ def test_to_from_tree_json_roundtrip() -> None:
     assert reconstructed.to_ascii() == qube.to_ascii()
 
 
+def test_to_from_yaml_roundtrip() -> None:
+    qube = Qube.from_ascii("""root
+├── class=od
+│   └── param=1/2/3
+└── class=rd
+    └── step=0h/6h
+""")
+
+    assert "dtype: duration" in qube.to_yaml()
+    assert Qube.from_yaml(qube.to_yaml()).to_ascii() == qube.to_ascii()
+    assert Qube.from_yaml(qube.to_yaml_datacubes()).to_ascii() == qube.to_ascii()
+
+
+def test_from_yaml_datacube_list() -> None:
+    qube = Qube.from_yaml("""
+- {class: od, expver: "0001", param: [1, 2]}
+- {class: rd, expver: "0001", param: 0/to/12/by/6}
+""")
+    assert qube.select({"class": "rd"}).to_datacubes() == [
+        {"root": [], "class": "rd", "expver": "0001", "param": [0, 6, 12]}
+    ]
+    with pytest.raises(TypeError, match="mapping"):
+        Qube.from_yaml("- [1, 2]")
+
+
 def test_from_tree_json_invalid_input() -> None:
     """from_tree_json should raise TypeError on invalid JSON."""
     with pytest.raises(TypeError):
@@ -413,6 +558,54 @@ def test_squeeze_returns_new_qube() -> None:
     assert "param=1/2" in squeezed.to_ascii()
 
 
+def test_reorder_moves_dimension_to_top() -> None:
+    """reorder() nests the listed dimensions first and keeps every identifier."""
+    q = Qube.from_ascii("""root
+├── class=od
+│   └── expver=0001
+│       └── param=1/2
+└── class=rd
+    └── param=1/3
+""")
+
+    reordered = q.reorder(["param"])
+
+    assert reordered.to_ascii() == Qube.from_ascii("""root
+├── param=1
+│   ├── class=od
+│   │   └── expver=0001
+│   └── class=rd
+├── param=2
+│   └── class=od
+│       └── expver=0001
+└── param=3
+    └── class=rd
+""").to_ascii()
+    key = lambda d: sorted(d.items())
+    assert sorted(reordered.iter_identifiers(), key=key) == sorted(q.iter_identifiers(), key=key)
+    # Original unchanged
+    assert q.to_ascii().startswith("root\n├── class=od")
+
+
+def test_optimize_order_reduces_node_count() -> None:
+    """optimize_order() reports node counts and returns the smaller Qube."""
+    q = Qube.from_ascii("""root
+├── class=od
+│   └── expver=0001
+│       └── step=0/1
+└── class=rd
+    └── expver=0002
+        └── step=0/1
+""")
+
+    result = q.optimize_order()
+
+    assert result["nodes_before"] == q.node_count() == 6
+    assert result["nodes_after"] == result["qube"].node_count() == 5
+    assert result["order"] == ["step", "class", "expver"]
+    assert len(result["qube"]) == len(q)
+
+
 def test_repr() -> None:
     """__repr__ should return the ASCII tree (same as __str__)."""
     q = Qube.from_ascii("""root
@@ -1,3 +1,6 @@
+import re
+
+import pytest
 import qubed
 
 
@@ -376,3 +379,104 @@ def test_select_deep_key_multi_level_unselected_prefix():
     assert selected.to_ascii() == qubed.Qube.from_ascii(expected).to_ascii(), (
         "only class=1/expver=0001 contains param=1; all other branches must be pruned"
     )
+
+
+def test_select_glob_and_regex():
+    input_qube = r"""root
+├── class=od
+│   └── expver=0001/0002/1001
+│       └── param=1
+└── class=rd
+    └── expver=1002/prod/test
+        └── param=1"""
+
+    q = qubed.Qube.from_ascii(input_qube)
+
+    by_glob = q.select({"expver": "glob:0*"}, None, None)
+    expected = r"""root
+└── class=od
+    └── expver=0001/0002
+        └── param=1"""
+    assert by_glob.to_ascii() == qubed.Qube.from_ascii(expected).to_ascii()
+
+    by_regex = q.select({"expver": re.compile(r"PROD|t.st", re.IGNORECASE)}, None, None)
+    expected = r"""root
+└── class=rd
+    └── expver=prod/test
+        └── param=1"""
+    assert by_regex.to_ascii() == qubed.Qube.from_ascii(expected).to_ascii()
+
+
+def test_select_qube():
+    input_qube = r"""root
+├── class=od
+│   └── expver=0001/0002
+│       └── param=1/2/3
+└── class=rd
+    └── expver=0001
+        └── param=1/2/3"""
+    mask = r"""root
+├── class=od
+│   └── param=1/2
+└── class=rd
+    └── param=3"""
+
+    q = qubed.Qube.from_ascii(input_qube)
+    selected = q.select_qube(qubed.Qube.from_ascii(mask))
+
+    expected = r"""root
+├── class=od
+│   └── expver=0001/0002
+│       └── param=1/2
+└── class=rd
+    └── expver=0001
+        └── param=3"""
+    assert selected.to_ascii() == qubed.Qube.from_ascii(expected).to_ascii()
+
+
+def test_select_strict():
+    input_qube = r"""root
+├── class=od
+│   ├── levtype=sfc
+│   │   └── step=0
+│   └── param=1
+│       └── step=0
+└── class=rd
+    └── levtype=sfc
+        └── param=1/2"""
+    q = qubed.Qube.from_ascii(input_qube)
+
+    strict = q.select({"levtype": "sfc", "param": 1}, mode="strict")
+    expected = r"""root
+└── class=rd
+    └── levtype=sfc
+        └── param=1"""
+    assert strict.to_ascii() == qubed.Qube.from_ascii(expected).to_ascii()
+
+    with pytest.raises(TypeError, match="parm"):
+        q.select({"parm": 1}, mode="strict")
+
+
+def test_explain_select():
+    input_qube = r"""root
+├── class=od
+│   └── expver=0001/0002
+│       └── param=1/2
+└── class=rd
+    └── expver=0001
+        └── param=3"""
+    q = qubed.Qube.from_ascii(input_qube)
+
+    info = q.explain_select({"class": "od", "expver": "0003"})
+    assert info["cut_depth"] == 2
+    assert info["cut_by"] == ["expver"]
+    expver = info["dimensions"][1]
+    assert expver["present"] == ["0001", "0002"]
+    assert expver["matched"] == []
+    assert expver["suggestions"] == ["0001", "0002"]
+    assert "cut at depth 2" in info["message"]
+
+    info = q.explain_select({"parm": 1})
+    assert info["cut_depth"] is None
+    assert info["dimensions"][0]["known"] is False
+    assert info["dimensions"][0]["suggestions"] == ["param"]
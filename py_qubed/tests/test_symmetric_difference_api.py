@@ -0,0 +1,42 @@
+from qubed import Qube
+
+
+# ---------------------------------------------------------------------------
+# Helpers
+# ---------------------------------------------------------------------------
+
+def _simple(class_val: str, param_val: str = "1") -> Qube:
+    return Qube.from_ascii(f"root\n└── class={class_val}\n    └── param={param_val}\n")
+
+
+# ---------------------------------------------------------------------------
+# Symmetric difference
+# ---------------------------------------------------------------------------
+
+def test_symmetric_difference_identical_returns_empty() -> None:
+    a = _simple("od", "1/2")
+    assert (a ^ a).all_unique_dim_coords() == {}
+
+
+def test_symmetric_difference_keeps_one_sided_values() -> None:
+    a = _simple("od", "1/2/3")
+    b = _simple("od", "2/3/4")
+    coords = (a ^ b).all_unique_dim_coords()
+    assert coords["param"] == [1, 4]
+
+
+def test_symmetric_difference_provenance_tags() -> None:
+    a = _simple("od", "1/2/3")
+    b = _simple("od", "2/3/4")
+    result = a.symmetric_difference(b, provenance_key="side")
+    assert result.get_all_metadata({"class": "od", "param": "1"})["side"] == ["left"]
+    assert result.get_all_metadata({"class": "od", "param": "4"})["side"] == ["right"]
+
+
+def test_symmetric_difference_does_not_modify_operands() -> None:
+    a = _simple("od/rd", "1/2")
+    b = _simple("od", "2")
+    a_before, b_before = a.to_ascii(), b.to_ascii()
+    _ = a ^ b
+    assert a.to_ascii() == a_before
+    assert b.to_ascii() == b_before
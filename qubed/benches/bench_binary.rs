@@ -0,0 +1,96 @@
+//! Loading Qubes from their binary form against loading them from arena JSON.
+//! Run with `cargo bench -p qubed --bench bench_binary`.
+//!
+//! The shipped examples are timed as they are, then a larger catalogue-like
+//! Qube built here, since the examples in this tree are small.
+
+use std::time::{Duration, Instant};
+
+use qubed::{Coordinates, NodeIdx, Qube};
+
+const EXAMPLES: &[&str] = &["large_climate_eg.json", "large_extremes_eg.json", "oper_fdb.json"];
+
+/// A climate-like catalogue: every model of every experiment has a few
+/// streams, each with a year of dates and pressure-level and surface fields.
+/// Nodes hold several values each, as they do once datacubes are merged.
+fn catalogue_qube() -> Qube {
+    let mut qube = Qube::new();
+    let root = qube.root();
+    let add = |qube: &mut Qube, parent: NodeIdx, dim: &str, values: &str| {
+        qube.get_or_create_child(dim, parent, Some(Coordinates::from_string(values))).unwrap()
+    };
+
+    for activity in ["cmip6", "highresmip", "scenariomip", "story-nudging"] {
+        let activity = add(&mut qube, root, "activity", activity);
+        for experiment in 0..8 {
+            let experiment = add(&mut qube, activity, "experiment", &format!("exp{experiment}"));
+            for model in ["ifs-fesom", "ifs-nemo", "icon"] {
+                let model = add(&mut qube, experiment, "model", model);
+                for (year, stream) in
+                    (1990..2030).zip(["clte", "clmn", "clte", "clmn"].iter().cycle())
+                {
+                    let stream = add(&mut qube, model, "stream", stream);
+                    let dates = format!("{year}0101/to/{year}1231");
+                    let date = add(&mut qube, stream, "date", &dates);
+                    let pl = add(&mut qube, date, "levtype", "pl");
+                    let levels = add(&mut qube, pl, "levelist", "50/100/250/500/700/850/925/1000");
+                    add(&mut qube, levels, "param", "129/130/131/132/133/135/157");
+                    let sfc = add(&mut qube, date, "levtype", "sfc");
+                    add(&mut qube, sfc, "param", "134/151/165/166/167/168/228/8/9");
+                }
+            }
+        }
+    }
+    qube
+}
+
+/// The fastest time per call of `f`, called often enough to time reliably
+/// however small the Qube, not counting the time taken to drop its results.
+fn time<T>(reps: usize, mut f: impl FnMut() -> T) -> Duration {
+    (0..10)
+        .map(|_| {
+            let mut results = Vec::with_capacity(reps);
+            let start = Instant::now();
+            for _ in 0..reps {
+                results.push(f());
+            }
+            start.elapsed() / reps as u32
+        })
+        .min()
+        .unwrap()
+}
+
+fn report(name: &str, qube: &Qube) {
+    let bytes = qube.to_bytes();
+    let json = serde_json::to_string(&qube.to_arena_json()).unwrap();
+    let reps = (1 << 20) / json.len() + 1;
+
+    let binary = time(reps, || Qube::from_bytes(&bytes).unwrap());
+    let arena = time(reps, || Qube::from_arena_json(serde_json::from_str(&json).unwrap()).unwrap());
+
+    println!(
+        "{:<24} {:>8} nodes {:>10} B binary {:>10} B JSON  from_bytes {:>10.3?}  from_arena_json {:>10.3?}  {:>5.1}x",
+        name,
+        qube.node_count(),
+        bytes.len(),
+        json.len(),
+        binary,
+        arena,
+        arena.as_secs_f64() / binary.as_secs_f64()
+    );
+}
+
+fn main() {
+    let examples = concat!(env!("CARGO_MANIFEST_DIR"), "/../qubed_meteo/qube_examples");
+    for name in EXAMPLES {
+        let path = format!("{examples}/{name}");
+        match std::fs::read_to_string(&path) {
+            Ok(text) => {
+                let qube = Qube::from_arena_json(serde_json::from_str(&text).unwrap()).unwrap();
+                report(name, &qube);
+            }
+            Err(e) => println!("{:<24} skipped: {}", name, e),
+        }
+    }
+    report("catalogue (generated)", &catalogue_qube());
+}
@@ -0,0 +1,293 @@
+use crate::{Coordinates, Datacube, NodeIdx, Qube};
+
+impl Qube {
+    /// Returns `true` if every identifier in `self` is also in `other`.
+    ///
+    /// Identifiers are compared structurally: a path ending at a leaf in one Qube
+    /// does not match a deeper path in the other (the same convention as
+    /// [`Qube::subtract`] and [`Qube::intersect`]).  Neither the tree shape nor
+    /// the metadata of the two Qubes has to match.
+    pub fn is_subset_of(&self, other: &Qube) -> bool {
+        self.subset_counter_example(other).is_none()
+    }
+
+    /// Returns `true` if every identifier in `other` is also in `self`.
+    pub fn is_superset_of(&self, other: &Qube) -> bool {
+        other.is_subset_of(self)
+    }
+
+    /// Returns `true` if `self` and `other` have no identifier in common.
+    pub fn is_disjoint(&self, other: &Qube) -> bool {
+        self.disjoint_counter_example(other).is_none()
+    }
+
+    /// Returns a [`Datacube`] of identifiers that are in `self` but not in `other`,
+    /// or `None` if `self` is a subset of `other`.
+    ///
+    /// The walk stops at the first uncovered set of values it finds, so the
+    /// returned datacube is one witness, not the full difference (use
+    /// [`Qube::subtract`] for that).  Every identifier it spans is a counter-example.
+    pub fn subset_counter_example(&self, other: &Qube) -> Option<Datacube> {
+        let mut path = Vec::new();
+        if self.find_uncovered(other, self.root(), &[other.root()], &mut path) {
+            Some(path_to_datacube(path))
+        } else {
+            None
+        }
+    }
+
+    /// Returns a [`Datacube`] of identifiers present in both `self` and `other`,
+    /// or `None` if the two are disjoint.
+    ///
+    /// Like [`Qube::subset_counter_example`], the walk stops at the first shared
+    /// identifier found.
+    pub fn disjoint_counter_example(&self, other: &Qube) -> Option<Datacube> {
+        let mut path = Vec::new();
+        if self.find_common(other, self.root(), other.root(), &mut path) {
+            Some(path_to_datacube(path))
+        } else {
+            None
+        }
+    }
+
+    // ------------------------------------------------------------------
+    // Internal recursive helpers
+    // ------------------------------------------------------------------
+
+    /// Searches the A-subtree below `self_id` for identifiers not covered by the
+    /// union of the B-subtrees below `other_ids`.
+    ///
+    /// Each A-node's coordinates are split into pieces according to which B-nodes
+    /// contain them.  A piece contained by no B-node is a counter-example; the
+    /// others recurse with exactly the B-nodes that contain them.  On success the
+    /// counter-example is left in `path`.
+    fn find_uncovered(
+        &self,
+        other: &Qube,
+        self_id: NodeIdx,
+        other_ids: &[NodeIdx],
+        path: &mut Vec<(String, Coordinates)>,
+    ) -> bool {
+        let self_children = self.node_ref(self_id).unwrap().children();
+
+        for (dim, self_kids) in self_children {
+            let dim_str = match self.dimension_str(dim) {
+                Some(s) => s,
+                None => continue,
+            };
+
+            // All B-nodes of this dimension, keyed by string because the two
+            // Qubes may have independent interner tables.
+            let other_kids: Vec<NodeIdx> = other_ids
+                .iter()
+                .flat_map(|&id| other.node_ref(id).unwrap().children())
+                .filter(|(d, _)| other.dimension_str(d) == Some(dim_str))
+                .flat_map(|(_, kids)| kids.iter().copied())
+                .collect();
+
+            for &self_kid in self_kids {
+                let self_node = self.node_ref(self_kid).unwrap();
+                // A node with no values holds no identifiers.
+                if self_node.coords().is_empty() {
+                    continue;
+                }
+                let self_is_leaf = self_node.children().is_empty();
+
+                let mut pieces: Vec<(Coordinates, Vec<NodeIdx>)> =
+                    vec![(self_node.coords().clone(), Vec::new())];
+
+                for &other_kid in &other_kids {
+                    let other_node = other.node_ref(other_kid).unwrap();
+                    // Different schema depths never cover each other.
+                    if other_node.children().is_empty() != self_is_leaf {
+                        continue;
+                    }
+
+                    let mut next = Vec::with_capacity(pieces.len());
+                    for (coords, covering) in pieces {
+                        let res = coords.intersect_with_tolerance(
+                            other_node.coords(),
+                            self.float_tolerance(dim_str),
+                        );
+                        if !res.intersection.is_empty() {
+                            let mut with_kid = covering.clone();
+                            with_kid.push(other_kid);
+                            next.push((res.intersection, with_kid));
+                        }
+                        if !res.only_a.is_empty() {
+                            next.push((res.only_a, covering));
+                        }
+                    }
+                    pieces = next;
+                }
+
+                for (coords, covering) in pieces {
+                    path.push((dim_str.to_owned(), coords));
+
+                    if covering.is_empty() {
+                        self.extend_path_to_leaf(self_kid, path);
+                        return true;
+                    }
+                    if !self_is_leaf && self.find_uncovered(other, self_kid, &covering, path) {
+                        return true;
+                    }
+
+                    path.pop();
+                }
+            }
+        }
+
+        false
+    }
+
+    /// Searches for an identifier shared by the A-subtree below `self_id` and the
+    /// B-subtree below `other_id`, leaving it in `path` on success.
+    fn find_common(
+        &self,
+        other: &Qube,
+        self_id: NodeIdx,
+        other_id: NodeIdx,
+        path: &mut Vec<(String, Coordinates)>,
+    ) -> bool {
+        let self_children = self.node_ref(self_id).unwrap().children();
+        let other_children = other.node_ref(other_id).unwrap().children();
+
+        for (dim, self_kids) in self_children {
+            let dim_str = match self.dimension_str(dim) {
+                Some(s) => s,
+                None => continue,
+            };
+            let other_kids: Vec<NodeIdx> = other_children
+                .iter()
+                .filter(|(d, _)| other.dimension_str(d) == Some(dim_str))
+                .flat_map(|(_, kids)| kids.iter().copied())
+                .collect();
+
+            for &self_kid in self_kids {
+                let self_node = self.node_ref(self_kid).unwrap();
+                let self_is_leaf = self_node.children().is_empty();
+
+                for &other_kid in &other_kids {
+                    let other_node = other.node_ref(other_kid).unwrap();
+                    if other_node.children().is_empty() != self_is_leaf {
+                        continue;
+                    }
+
+                    let intersection = self_node
+                        .coords()
+                        .intersect_with_tolerance(
+                            other_node.coords(),
+                            self.float_tolerance(dim_str),
+                        )
+                        .intersection;
+                    if intersection.is_empty() {
+                        continue;
+                    }
+
+                    path.push((dim_str.to_owned(), intersection));
+                    if self_is_leaf || self.find_common(other, self_kid, other_kid, path) {
+                        return true;
+                    }
+                    path.pop();
+                }
+            }
+        }
+
+        false
+    }
+
+    /// Appends the first root-to-leaf continuation below `node_id` to `path`.
+    fn extend_path_to_leaf(&self, node_id: NodeIdx, path: &mut Vec<(String, Coordinates)>) {
+        let mut current = node_id;
+        while let Some(child) = self.node(current).and_then(|n| {
+            n.all_children().find(|&c| !self.node(c).unwrap().coordinates().is_empty())
+        }) {
+            let child_node = self.node(child).unwrap();
+            path.push((
+                child_node.dimension().unwrap_or_default().to_owned(),
+                child_node.coordinates().clone(),
+            ));
+            current = child;
+        }
+    }
+}
+
+fn path_to_datacube(path: Vec<(String, Coordinates)>) -> Datacube {
+    let mut datacube = Datacube::new();
+    for (dim, coords) in path {
+        datacube.add_coordinate(&dim, coords);
+    }
+    datacube
+}
+
+// ---------------------------------------------------------------------------
+// Trait impl: set equality
+// ---------------------------------------------------------------------------
+
+/// Two Qubes are equal when they contain the same identifiers, regardless of
+/// tree shape, compression state or metadata.
+impl PartialEq for Qube {
+    fn eq(&self, other: &Self) -> bool {
+        self.is_subset_of(other) && other.is_subset_of(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn q(ascii: &str) -> Qube {
+        Qube::from_ascii(ascii).unwrap()
+    }
+
+    #[test]
+    fn counter_example_is_not_in_other() {
+        let a = q(r#"root
+├── class=od
+│   └── param=1
+└── class=rd
+    └── param=2/3"#);
+        let b = q(r#"root
+├── class=od
+│   └── param=1
+└── class=rd
+    └── param=2"#);
+
+        let dc = a.subset_counter_example(&b).expect("a is not a subset of b");
+        assert_eq!(dc.coordinates()["class"].to_string(), "rd");
+        assert_eq!(dc.coordinates()["param"].to_string(), "3");
+
+        let witness = Qube::from_datacube(&dc, Some(&["class".into(), "param".into()]));
+        assert!(witness.is_subset_of(&a));
+        assert!(witness.is_disjoint(&b));
+    }
+
+    #[test]
+    fn counter_example_extends_to_a_leaf() {
+        let a = q(r#"root
+└── class=od
+    └── expver=0001
+        └── param=1"#);
+        let b = q(r#"root
+└── class=rd
+    └── expver=0001
+        └── param=1"#);
+
+        let dc = a.subset_counter_example(&b).unwrap();
+        assert_eq!(dc.len(), 3, "witness should be a full identifier: {:?}", dc);
+    }
+
+    #[test]
+    fn common_example_is_in_both() {
+        let a = q(r#"root
+└── class=od/rd
+    └── param=1/2"#);
+        let b = q(r#"root
+└── class=rd
+    └── param=2/3"#);
+
+        let dc = a.disjoint_counter_example(&b).expect("a and b overlap");
+        assert_eq!(dc.coordinates()["class"].to_string(), "rd");
+        assert_eq!(dc.coordinates()["param"].to_string(), "2");
+    }
+}
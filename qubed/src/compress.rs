@@ -112,7 +112,7 @@ impl Qube {
                 //   1. Every group member has this key (no None),
                 //   2. Every value is a `Strings` set (any number of values),
                 //   3. Both the merged coords and every original coords set are
-                //      fully enumerable (no RangeSet / Mixed).
+                //      fully enumerable (no Mixed).
                 let can_use_per_coord = merged_enumerable
                     && orig_enumerable
                     && values.iter().all(|v| {
@@ -1,52 +1,183 @@
 use std::hash::Hash;
 
-use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, NaiveTime, Utc};
 use tiny_vec::TinyVec;
 
+use crate::coordinates::ranges::{
+    self, Canonical, Grid, MIN_RANGE_LEN, RangeBuilder, Segment, Side, walk,
+};
 use crate::coordinates::{Coordinates, IntersectionResult};
 
+/// A set of date-times.
+///
+/// The representation is canonical, so two `DateTimeCoordinates` holding the
+/// same values always compare (and hash) equal: scanning the sorted values from
+/// the left, every evenly spaced run of at least [`MIN_RANGE_LEN`] values
+/// becomes a [`DateTimeRange`].  If there is no such run the values are kept in
+/// a sorted `List`; otherwise everything is stored as a `RangeSet`, with the
+/// values outside long runs held as single-value ranges.
+///
+/// An hourly axis spanning years is one range, and removing a month from it
+/// leaves two.
 #[derive(Debug, Clone, PartialEq)]
 pub enum DateTimeCoordinates {
     List(TinyVec<NaiveDateTime, 4>),
+    RangeSet(TinyVec<DateTimeRange, 2>),
+}
+
+/// The date-times `start, start + step, ..., end`, where `end` always lies on
+/// the grid.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DateTimeRange {
+    start: NaiveDateTime,
+    end: NaiveDateTime,
+    step: Duration,
+}
+
+impl DateTimeRange {
+    /// The date-times `start, start + step, ..., end`, or `None` if `step` is
+    /// not positive, or `end` is before `start` or not on the grid.
+    pub(crate) fn new(start: NaiveDateTime, end: NaiveDateTime, step: Duration) -> Option<Self> {
+        (step > Duration::zero() && end >= start && on_grid(start, step, end))
+            .then_some(DateTimeRange { start, end, step })
+    }
+
+    pub fn start(&self) -> NaiveDateTime {
+        self.start
+    }
+
+    pub fn end(&self) -> NaiveDateTime {
+        self.end
+    }
+
+    pub fn step(&self) -> Duration {
+        self.step
+    }
+
+    pub fn len(&self) -> usize {
+        (nanos(self.end - self.start) / nanos(self.step)) as usize + 1
+    }
+
+    pub fn contains(&self, value: NaiveDateTime) -> bool {
+        self.start <= value && value <= self.end && on_grid(self.start, self.step, value)
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = NaiveDateTime> + '_ {
+        (0..self.len() as i128).map(move |n| nth(self.start, self.step, n))
+    }
 }
 
 impl DateTimeCoordinates {
-    pub(crate) fn extend(&mut self, new_coords: &DateTimeCoordinates) {
-        match (self, new_coords) {
-            (DateTimeCoordinates::List(list), DateTimeCoordinates::List(new_list)) => {
-                for v in new_list.iter() {
-                    list.push(*v);
-                }
-            }
+    /// The date-times `start, start + step, ...` up to and including `end`, or
+    /// `None` if `step` is not positive or `end` is before `start`.
+    pub fn from_range(start: NaiveDateTime, end: NaiveDateTime, step: Duration) -> Option<Self> {
+        if step <= Duration::zero() || end < start {
+            return None;
+        }
+        let count = nanos(end - start) / nanos(step) + 1;
+        let mut builder = RangeBuilder::default();
+        builder.push_segment(Segment { start, step, count });
+        Some(Self::from_builder(builder))
+    }
+
+    /// The canonical form of sorted, deduplicated `values`.
+    fn from_sorted(values: impl IntoIterator<Item = NaiveDateTime>) -> Self {
+        let mut builder = RangeBuilder::default();
+        for v in values {
+            builder.push(v);
         }
+        Self::from_builder(builder)
+    }
+
+    fn from_builder(builder: RangeBuilder<NaiveDateTime>) -> Self {
+        match builder.finish() {
+            Canonical::Values(values) => DateTimeCoordinates::List(values.into_iter().collect()),
+            Canonical::Runs(runs) => DateTimeCoordinates::RangeSet(
+                runs.into_iter()
+                    .map(|r| DateTimeRange { start: r.start, end: r.end, step: r.step })
+                    .collect(),
+            ),
+        }
+    }
+
+    pub(crate) fn extend(&mut self, new_coords: &DateTimeCoordinates) {
+        let mut builder = RangeBuilder::default();
+        walk(self.segments(), new_coords.segments(), |_, segment| builder.push_segment(segment));
+        *self = Self::from_builder(builder);
     }
 
     pub(crate) fn append(&mut self, new_coord: NaiveDateTime) {
         match self {
-            DateTimeCoordinates::List(list) => list.push(new_coord),
+            DateTimeCoordinates::List(list) => {
+                let Err(pos) = list.binary_search(&new_coord) else {
+                    return;
+                };
+                // `pos` is at most `list.len()`, so the insert cannot fail.
+                let _ = list.insert(pos, new_coord);
+                if list.len() >= MIN_RANGE_LEN && has_long_run_through(list, pos) {
+                    *self = Self::from_sorted(list.to_vec());
+                }
+            }
+            DateTimeCoordinates::RangeSet(_) => {
+                if !self.contains(new_coord) {
+                    let mut single = TinyVec::new();
+                    single.push(new_coord);
+                    self.extend(&DateTimeCoordinates::List(single));
+                }
+            }
         }
     }
 
     pub(crate) fn len(&self) -> usize {
         match self {
             DateTimeCoordinates::List(list) => list.len(),
+            DateTimeCoordinates::RangeSet(ranges) => ranges.iter().map(DateTimeRange::len).sum(),
+        }
+    }
+
+    /// Iterates over the values in ascending order.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = NaiveDateTime> + '_ {
+        match self {
+            DateTimeCoordinates::List(list) => itertools::Either::Left(list.iter().copied()),
+            DateTimeCoordinates::RangeSet(ranges) => {
+                itertools::Either::Right(ranges.iter().flat_map(DateTimeRange::iter))
+            }
         }
     }
 
     pub(crate) fn contains(&self, value: NaiveDateTime) -> bool {
         match self {
-            DateTimeCoordinates::List(list) => list.iter().any(|&v| v == value),
+            DateTimeCoordinates::List(list) => list.binary_search(&value).is_ok(),
+            DateTimeCoordinates::RangeSet(ranges) => {
+                let i = ranges.partition_point(|r| r.end < value);
+                ranges.get(i).is_some_and(|r| r.contains(value))
+            }
         }
     }
 
+    /// Formats the values in ISO 8601, `/`-separated and in ascending order.
+    /// Three or more values that are evenly spaced are written as a range,
+    /// `start/to/end[/by/step]`, with date-only end points when every value is
+    /// at midnight and the step is a whole number of days.
     pub(crate) fn to_string(&self) -> String {
+        let mut parts = Vec::new();
         match self {
-            DateTimeCoordinates::List(list) => list
-                .iter()
-                .map(|dt| dt.format("%Y-%m-%dT%H:%M:%S").to_string())
-                .collect::<Vec<String>>()
-                .join("/"),
+            DateTimeCoordinates::List(list) => format_values(list, &mut parts),
+            DateTimeCoordinates::RangeSet(ranges) => {
+                let mut singles = Vec::new();
+                for range in ranges.iter() {
+                    if range.start == range.end {
+                        singles.push(range.start);
+                    } else {
+                        format_values(&singles, &mut parts);
+                        singles.clear();
+                        parts.push(format_run(range.start, range.end, range.step));
+                    }
+                }
+                format_values(&singles, &mut parts);
+            }
         }
+        parts.join("/")
     }
 
     pub(crate) fn hash(&self, hasher: &mut std::collections::hash_map::DefaultHasher) {
@@ -59,6 +190,16 @@ impl DateTimeCoordinates {
                     dt.and_utc().timestamp_subsec_nanos().hash(hasher);
                 }
             }
+            DateTimeCoordinates::RangeSet(ranges) => {
+                "range_set".hash(hasher);
+                for range in ranges.iter() {
+                    for dt in [range.start, range.end] {
+                        dt.and_utc().timestamp().hash(hasher);
+                        dt.and_utc().timestamp_subsec_nanos().hash(hasher);
+                    }
+                    nanos(range.step).hash(hasher);
+                }
+            }
         }
     }
 
@@ -66,48 +207,31 @@ impl DateTimeCoordinates {
         &self,
         other: &DateTimeCoordinates,
     ) -> IntersectionResult<DateTimeCoordinates> {
-        match (self, other) {
-            (DateTimeCoordinates::List(list_a), DateTimeCoordinates::List(list_b)) => {
-                use std::collections::HashSet;
-
-                let mut set_b: HashSet<NaiveDateTime> = HashSet::new();
-                for v in list_b.iter() {
-                    set_b.insert(*v);
-                }
-
-                let mut set_a: HashSet<NaiveDateTime> = HashSet::new();
-                for v in list_a.iter() {
-                    set_a.insert(*v);
-                }
-
-                let mut intersection = TinyVec::new();
-                let mut only_a = TinyVec::new();
-
-                let mut added: HashSet<NaiveDateTime> = HashSet::new();
-                for v in list_a.iter() {
-                    if set_b.contains(v) {
-                        if !added.contains(v) {
-                            intersection.push(*v);
-                            added.insert(*v);
-                        }
-                    } else {
-                        only_a.push(*v);
-                    }
-                }
-
-                let mut only_b = TinyVec::new();
-                for v in list_b.iter() {
-                    if !set_a.contains(v) {
-                        only_b.push(*v);
-                    }
-                }
+        let mut intersection = RangeBuilder::default();
+        let mut only_a = RangeBuilder::default();
+        let mut only_b = RangeBuilder::default();
+
+        walk(self.segments(), other.segments(), |side, segment| match side {
+            Side::Both => intersection.push_segment(segment),
+            Side::OnlyA => only_a.push_segment(segment),
+            Side::OnlyB => only_b.push_segment(segment),
+        });
+
+        IntersectionResult {
+            intersection: Self::from_builder(intersection),
+            only_a: Self::from_builder(only_a),
+            only_b: Self::from_builder(only_b),
+        }
+    }
 
-                IntersectionResult {
-                    intersection: DateTimeCoordinates::List(intersection),
-                    only_a: DateTimeCoordinates::List(only_a),
-                    only_b: DateTimeCoordinates::List(only_b),
-                }
-            }
+    /// The values as evenly spaced segments, in ascending order.
+    fn segments(&self) -> Vec<Segment<NaiveDateTime>> {
+        match self {
+            DateTimeCoordinates::List(list) => list.iter().map(|&v| Segment::single(v)).collect(),
+            DateTimeCoordinates::RangeSet(ranges) => ranges
+                .iter()
+                .map(|r| Segment { start: r.start, step: r.step, count: r.len() as i128 })
+                .collect(),
         }
     }
 
@@ -146,6 +270,101 @@ impl DateTimeCoordinates {
     }
 }
 
+/// A duration in nanoseconds, wide enough for any difference between two
+/// `NaiveDateTime`s.
+fn nanos(duration: Duration) -> i128 {
+    duration.num_seconds() as i128 * 1_000_000_000 + duration.subsec_nanos() as i128
+}
+
+/// Returns `start + n * step`.
+fn nth(start: NaiveDateTime, step: Duration, n: i128) -> NaiveDateTime {
+    let offset = nanos(step) * n;
+    start
+        + Duration::seconds((offset / 1_000_000_000) as i64)
+        + Duration::nanoseconds((offset % 1_000_000_000) as i64)
+}
+
+/// Returns `true` if `value` is `start + n * step` for some integer `n`.
+fn on_grid(start: NaiveDateTime, step: Duration, value: NaiveDateTime) -> bool {
+    nanos(value - start) % nanos(step) == 0
+}
+
+impl Grid for NaiveDateTime {
+    type Step = Duration;
+
+    fn unit() -> Duration {
+        Duration::seconds(1)
+    }
+
+    fn units(step: Duration) -> i128 {
+        nanos(step)
+    }
+
+    fn distance(self, to: NaiveDateTime) -> i128 {
+        nanos(to - self)
+    }
+
+    fn advance(self, step: Duration, n: i128) -> NaiveDateTime {
+        nth(self, step, n)
+    }
+
+    fn step_to(self, next: NaiveDateTime) -> Option<Duration> {
+        Some(next - self)
+    }
+}
+
+/// Whether inserting the value at `pos` into sorted `list` created an evenly
+/// spaced run of at least [`MIN_RANGE_LEN`] values.  Such a run must pass
+/// through that value, so only its neighbours need to be looked at.
+fn has_long_run_through(list: &[NaiveDateTime], pos: usize) -> bool {
+    ranges::has_long_run(
+        &list[pos.saturating_sub(MIN_RANGE_LEN - 1)..(pos + MIN_RANGE_LEN).min(list.len())],
+    )
+}
+
+/// Formats sorted values, writing each evenly spaced run of three or more as
+/// `first/to/last[/by/step]`.
+fn format_values(list: &[NaiveDateTime], parts: &mut Vec<String>) {
+    let mut i = 0;
+    while i < list.len() {
+        // Extend a run of equal steps from list[i] as far as possible.
+        let mut j = i + 1;
+        if j < list.len() {
+            let step = list[j] - list[i];
+            while j + 1 < list.len() && list[j + 1] - list[j] == step {
+                j += 1;
+            }
+            if j - i + 1 >= 3 {
+                parts.push(format_run(list[i], list[j], step));
+                i = j + 1;
+                continue;
+            }
+        }
+        parts.push(list[i].format("%Y-%m-%dT%H:%M:%S").to_string());
+        i += 1;
+    }
+}
+
+/// Formats the run `first, first + step, ..., last` as `first/to/last[/by/step]`.
+fn format_run(first: NaiveDateTime, last: NaiveDateTime, step: Duration) -> String {
+    let whole_days = step.num_seconds() % 86_400 == 0
+        && step.subsec_nanos() == 0
+        && first.time() == NaiveTime::MIN;
+    let (a, b) = if whole_days {
+        (first.format("%Y-%m-%d").to_string(), last.format("%Y-%m-%d").to_string())
+    } else {
+        (
+            first.format("%Y-%m-%dT%H:%M:%S").to_string(),
+            last.format("%Y-%m-%dT%H:%M:%S").to_string(),
+        )
+    };
+    if step == Duration::days(1) {
+        format!("{}/to/{}", a, b)
+    } else {
+        format!("{}/to/{}/by/{}", a, b, ranges::format_duration(step))
+    }
+}
+
 impl Default for DateTimeCoordinates {
     fn default() -> Self {
         DateTimeCoordinates::List(TinyVec::new())
@@ -180,21 +399,16 @@ impl From<&str> for DateTimeCoordinates {
 
 impl From<&[NaiveDateTime]> for Coordinates {
     fn from(value: &[NaiveDateTime]) -> Self {
-        let mut vec = TinyVec::new();
-        for &v in value {
-            vec.push(v);
-        }
-        Coordinates::DateTimes(DateTimeCoordinates::List(vec))
+        let mut values = value.to_vec();
+        values.sort();
+        values.dedup();
+        Coordinates::DateTimes(DateTimeCoordinates::from_sorted(values))
     }
 }
 
 impl<const N: usize> From<&[NaiveDateTime; N]> for Coordinates {
     fn from(value: &[NaiveDateTime; N]) -> Self {
-        let mut vec = TinyVec::new();
-        for &v in value.iter() {
-            vec.push(v);
-        }
-        Coordinates::DateTimes(DateTimeCoordinates::List(vec))
+        Coordinates::from(&value[..])
     }
 }
 
@@ -205,8 +419,8 @@ mod tests {
     #[test]
     fn test_datetime_append_and_len() {
         let mut coords = DateTimeCoordinates::default();
-        let d1 = NaiveDate::from_ymd(2020, 1, 1).and_hms(0, 0, 0);
-        let d2 = NaiveDate::from_ymd(2020, 1, 2).and_hms(12, 30, 0);
+        let d1 = NaiveDate::from_ymd_opt(2020, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
+        let d2 = NaiveDate::from_ymd_opt(2020, 1, 2).unwrap().and_hms_opt(12, 30, 0).unwrap();
         coords.append(d1);
         coords.append(d2);
 
@@ -216,14 +430,15 @@ mod tests {
                 assert_eq!(list[0], d1);
                 assert_eq!(list[1], d2);
             }
+            other => panic!("expected a List, got {:?}", other),
         }
     }
 
     #[test]
     fn test_datetime_extend() {
         let mut a = DateTimeCoordinates::default();
-        let d1 = NaiveDate::from_ymd(2020, 1, 1).and_hms(0, 0, 0);
-        let d2 = NaiveDate::from_ymd(2020, 1, 2).and_hms(0, 0, 0);
+        let d1 = NaiveDate::from_ymd_opt(2020, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
+        let d2 = NaiveDate::from_ymd_opt(2020, 1, 2).unwrap().and_hms_opt(0, 0, 0).unwrap();
         a.append(d1);
 
         let mut b = DateTimeCoordinates::default();
@@ -237,12 +452,13 @@ mod tests {
                 assert_eq!(list[0], d1);
                 assert_eq!(list[1], d2);
             }
+            other => panic!("expected a List, got {:?}", other),
         }
     }
 
     #[test]
     fn test_datetime_to_string_and_parse() {
-        let d = NaiveDate::from_ymd(2021, 5, 4).and_hms(6, 7, 8);
+        let d = NaiveDate::from_ymd_opt(2021, 5, 4).unwrap().and_hms_opt(6, 7, 8).unwrap();
         let mut c = DateTimeCoordinates::default();
         c.append(d);
         let s = c.to_string();
@@ -257,9 +473,9 @@ mod tests {
     #[test]
     fn test_datetime_intersect() {
         let mut a = DateTimeCoordinates::default();
-        let d1 = NaiveDate::from_ymd(2020, 1, 1).and_hms(0, 0, 0);
-        let d2 = NaiveDate::from_ymd(2020, 1, 2).and_hms(0, 0, 0);
-        let d3 = NaiveDate::from_ymd(2020, 1, 3).and_hms(0, 0, 0);
+        let d1 = NaiveDate::from_ymd_opt(2020, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
+        let d2 = NaiveDate::from_ymd_opt(2020, 1, 2).unwrap().and_hms_opt(0, 0, 0).unwrap();
+        let d3 = NaiveDate::from_ymd_opt(2020, 1, 3).unwrap().and_hms_opt(0, 0, 0).unwrap();
         a.append(d1);
         a.append(d2);
         a.append(d3);
@@ -267,7 +483,7 @@ mod tests {
         let mut b = DateTimeCoordinates::default();
         b.append(d2);
         b.append(d3);
-        b.append(NaiveDate::from_ymd(2020, 1, 4).and_hms(0, 0, 0));
+        b.append(NaiveDate::from_ymd_opt(2020, 1, 4).unwrap().and_hms_opt(0, 0, 0).unwrap());
 
         let res = a.intersect(&b);
 
@@ -277,6 +493,123 @@ mod tests {
                 assert_eq!(list[0], d2);
                 assert_eq!(list[1], d3);
             }
+            other => panic!("expected a List, got {:?}", other),
+        }
+    }
+
+    fn hourly(days: i64) -> DateTimeCoordinates {
+        let start = NaiveDate::from_ymd_opt(2020, 1, 1).unwrap().and_time(NaiveTime::MIN);
+        DateTimeCoordinates::from_range(start, start + Duration::days(days), Duration::hours(1))
+            .unwrap()
+    }
+
+    fn hash_of(coords: &DateTimeCoordinates) -> u64 {
+        use std::hash::Hasher;
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        coords.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// The number of ranges in a `RangeSet`, single values included.
+    fn runs(coords: &DateTimeCoordinates) -> usize {
+        match coords {
+            DateTimeCoordinates::RangeSet(ranges) => ranges.len(),
+            other => panic!("expected a RangeSet, got {:?}", other),
         }
     }
+
+    fn hourly_from(y: i32, m: u32, d: u32, days: i64) -> DateTimeCoordinates {
+        let start = NaiveDate::from_ymd_opt(y, m, d).unwrap().and_time(NaiveTime::MIN);
+        DateTimeCoordinates::from_range(start, start + Duration::days(days), Duration::hours(1))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_datetime_range_contains_and_len() {
+        let year = hourly(366);
+        assert_eq!(runs(&year), 1);
+        assert_eq!(year.len(), 366 * 24 + 1);
+
+        let t = NaiveDate::from_ymd_opt(2020, 7, 1).unwrap().and_hms_opt(13, 0, 0).unwrap();
+        assert!(year.contains(t));
+        assert!(!year.contains(t + Duration::minutes(30)));
+        assert!(!year.contains(t + Duration::days(400)));
+    }
+
+    #[test]
+    fn test_datetime_append_dedups_and_is_canonical() {
+        let range = hourly(1);
+        let mut appended = DateTimeCoordinates::default();
+        for v in range.iter().rev() {
+            appended.append(v);
+            appended.append(v);
+        }
+        assert_eq!(appended, range);
+        assert_eq!(hash_of(&appended), hash_of(&range));
+
+        // A value off the grid is held next to the run as a single value.  The
+        // first value of the run is kept with it, because scanning from the
+        // left the two start a run that does not continue.
+        let mut with_gap = range.clone();
+        let off_grid = range.iter().next().unwrap() + Duration::minutes(30);
+        with_gap.append(off_grid);
+        assert_eq!(runs(&with_gap), 3);
+        assert_eq!(with_gap.len(), range.len() + 1);
+        assert!(with_gap.contains(off_grid));
+        assert_eq!(with_gap, DateTimeCoordinates::from_sorted(with_gap.iter().collect::<Vec<_>>()));
+    }
+
+    #[test]
+    fn test_datetime_range_intersect() {
+        let year = hourly(366);
+        let march = hourly_from(2020, 3, 1, 31);
+
+        // A year minus a month is two runs, not thousands of values.
+        let res = year.intersect(&march);
+        assert_eq!(res.intersection, march);
+        assert_eq!(runs(&res.only_a), 2);
+        assert_eq!(res.only_a.len(), year.len() - march.len());
+        assert_eq!(res.only_b.len(), 0);
+
+        let mut rebuilt = res.only_a.clone();
+        rebuilt.extend(&res.intersection);
+        assert_eq!(rebuilt, year);
+        assert_eq!(runs(&rebuilt), 1);
+
+        // Different steps fall back to comparing values.
+        let six_hourly = {
+            let start = NaiveDate::from_ymd_opt(2020, 1, 1).unwrap().and_time(NaiveTime::MIN);
+            DateTimeCoordinates::from_range(start, start + Duration::days(2), Duration::hours(6))
+                .unwrap()
+        };
+        let res = hourly(1).intersect(&six_hourly);
+        assert_eq!(res.intersection.len(), 5);
+        assert_eq!(res.only_a.len(), 20);
+        assert_eq!(res.only_b.len(), 4);
+        // The gaps leave no run long enough to be a range.
+        assert!(matches!(res.only_a, DateTimeCoordinates::List(_)));
+    }
+
+    #[test]
+    fn test_datetime_union_of_separate_ranges() {
+        let mut coords = hourly_from(2020, 1, 1, 10);
+        coords.extend(&hourly_from(2020, 3, 1, 10));
+        assert_eq!(runs(&coords), 2);
+        assert_eq!(coords.len(), 2 * (10 * 24 + 1));
+
+        // Filling the gap joins them into one run.
+        let gap = {
+            let start = NaiveDate::from_ymd_opt(2020, 1, 11).unwrap().and_hms_opt(1, 0, 0).unwrap();
+            let end = NaiveDate::from_ymd_opt(2020, 2, 29).unwrap().and_hms_opt(23, 0, 0).unwrap();
+            DateTimeCoordinates::from_range(start, end, Duration::hours(1)).unwrap()
+        };
+        coords.extend(&gap);
+        assert_eq!(coords, hourly_from(2020, 1, 1, 70));
+        assert_eq!(runs(&coords), 1);
+
+        assert_eq!(
+            hourly_from(2020, 1, 1, 1).to_string(),
+            "2020-01-01T00:00:00/to/2020-01-02T00:00:00/by/1h"
+        );
+    }
 }
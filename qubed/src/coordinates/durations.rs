@@ -0,0 +1,287 @@
+use std::fmt;
+use std::hash::Hash;
+
+use chrono::Duration;
+
+use crate::coordinates::{Coordinates, IntersectionResult};
+use crate::utils::tiny_ordered_set::TinyOrderedSet;
+
+/// A single duration coordinate, such as a forecast `step`: either an offset
+/// (`6h`, `30m`, `1d`) or an interval between two offsets (`0-24`).
+///
+/// Values order by start and then by end, an offset coming before the
+/// intervals that start at it: `0h < 0-24 < 6h < 12h`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DurationValue {
+    start: Duration,
+    end: Option<Duration>,
+}
+
+impl DurationValue {
+    pub fn offset(duration: Duration) -> Self {
+        DurationValue { start: duration, end: None }
+    }
+
+    /// An interval from `start` to `end`, or `None` if `end` is before `start`.
+    pub fn interval(start: Duration, end: Duration) -> Option<Self> {
+        (start <= end).then_some(DurationValue { start, end: Some(end) })
+    }
+
+    pub fn start(&self) -> Duration {
+        self.start
+    }
+
+    /// The end of an interval, or `None` for an offset.
+    pub fn end(&self) -> Option<Duration> {
+        self.end
+    }
+
+    /// Parses an offset made of one or more numbers with a `d`, `h`, `m` or `s`
+    /// unit (`6h`, `1h30m`, `-3h`), or an interval `start-end`.  The ends of an
+    /// interval may leave out the unit, in which case it is hours (`0-24`).
+    pub fn parse(s: &str) -> Option<Self> {
+        // Skip the first character so a leading minus sign is not a separator.
+        if let Some(i) = s.get(1..).and_then(|rest| rest.find('-')) {
+            let (start, end) = (&s[..i + 1], &s[i + 2..]);
+            return Self::interval(parse_offset(start, true)?, parse_offset(end, true)?);
+        }
+        parse_offset(s, false).map(Self::offset)
+    }
+}
+
+/// Parses `s` as a sum of `<number><unit>` terms.  A bare number is a number
+/// of hours when `bare_hours` is set, and not a duration otherwise.
+fn parse_offset(s: &str, bare_hours: bool) -> Option<Duration> {
+    if bare_hours && let Ok(hours) = s.parse::<i64>() {
+        return Duration::try_hours(hours);
+    }
+
+    let (sign, mut rest) = match s.strip_prefix('-') {
+        Some(rest) => (-1, rest),
+        None => (1, s),
+    };
+    if rest.is_empty() {
+        return None;
+    }
+    let mut total = Duration::zero();
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+        let n: i64 = rest[..digits].parse().ok()?;
+        let unit = rest[digits..].chars().next()?;
+        let term = match unit {
+            'd' => Duration::try_days(n)?,
+            'h' => Duration::try_hours(n)?,
+            'm' => Duration::try_minutes(n)?,
+            's' => Duration::try_seconds(n)?,
+            _ => return None,
+        };
+        total = total.checked_add(&term)?;
+        rest = &rest[digits + 1..];
+    }
+    Some(total * sign)
+}
+
+/// Writes a duration in whole hours, minutes or seconds, whichever is exact.
+fn format_offset(duration: Duration) -> String {
+    let seconds = duration.num_seconds();
+    if seconds % 3_600 == 0 {
+        format!("{}h", seconds / 3_600)
+    } else if seconds % 60 == 0 {
+        format!("{}m", seconds / 60)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+/// Offsets are written with a unit (`6h`, `30m`); intervals in whole hours
+/// are written without one (`0-24`), others with units on both ends.
+impl fmt::Display for DurationValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.end {
+            None => f.write_str(&format_offset(self.start)),
+            Some(end)
+                if self.start.num_seconds() % 3_600 == 0 && end.num_seconds() % 3_600 == 0 =>
+            {
+                write!(f, "{}-{}", self.start.num_hours(), end.num_hours())
+            }
+            Some(end) => write!(f, "{}-{}", format_offset(self.start), format_offset(end)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DurationCoordinates {
+    Set(TinyOrderedSet<DurationValue, 4>),
+}
+
+impl DurationCoordinates {
+    pub(crate) fn extend(&mut self, new_coords: &DurationCoordinates) {
+        match new_coords {
+            DurationCoordinates::Set(set) => {
+                for &val in set.iter() {
+                    self.append(val);
+                }
+            }
+        }
+    }
+
+    pub(crate) fn append(&mut self, new_coord: DurationValue) {
+        match self {
+            DurationCoordinates::Set(set) => set.insert(new_coord),
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            DurationCoordinates::Set(set) => set.len(),
+        }
+    }
+
+    /// Iterates over the values in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = DurationValue> + '_ {
+        match self {
+            DurationCoordinates::Set(set) => set.iter().copied(),
+        }
+    }
+
+    pub(crate) fn contains(&self, value: DurationValue) -> bool {
+        match self {
+            DurationCoordinates::Set(set) => set.contains(&value),
+        }
+    }
+
+    pub(crate) fn intersect(
+        &self,
+        other: &DurationCoordinates,
+    ) -> IntersectionResult<DurationCoordinates> {
+        match (self, other) {
+            (DurationCoordinates::Set(set_a), DurationCoordinates::Set(set_b)) => {
+                let result = set_a.intersect(set_b);
+                IntersectionResult {
+                    intersection: DurationCoordinates::Set(result.intersection),
+                    only_a: DurationCoordinates::Set(result.only_a),
+                    only_b: DurationCoordinates::Set(result.only_b),
+                }
+            }
+        }
+    }
+
+    pub(crate) fn hash(&self, hasher: &mut impl std::hash::Hasher) {
+        "durations".hash(hasher);
+        match self {
+            DurationCoordinates::Set(set) => {
+                for val in set.iter() {
+                    val.hash(hasher);
+                }
+            }
+        }
+    }
+}
+
+/// The values in ascending order, `/`-separated.
+impl fmt::Display for DurationCoordinates {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, v) in self.iter().enumerate() {
+            if i > 0 {
+                f.write_str("/")?;
+            }
+            write!(f, "{v}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for DurationCoordinates {
+    fn default() -> Self {
+        DurationCoordinates::Set(TinyOrderedSet::new())
+    }
+}
+
+impl From<DurationValue> for Coordinates {
+    fn from(value: DurationValue) -> Self {
+        let mut set = TinyOrderedSet::new();
+        set.insert(value);
+        Coordinates::Durations(DurationCoordinates::Set(set))
+    }
+}
+
+impl From<DurationCoordinates> for Coordinates {
+    fn from(value: DurationCoordinates) -> Self {
+        Coordinates::Durations(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn d(s: &str) -> DurationValue {
+        DurationValue::parse(s).unwrap_or_else(|| panic!("{s:?} should parse"))
+    }
+
+    #[test]
+    fn test_parse_and_display() {
+        assert_eq!(d("6h").start(), Duration::hours(6));
+        assert_eq!(d("30m").start(), Duration::minutes(30));
+        assert_eq!(d("1d").start(), Duration::days(1));
+        assert_eq!(d("1h30m").start(), Duration::minutes(90));
+        assert_eq!(d("-3h").start(), Duration::hours(-3));
+        assert_eq!(
+            d("0-24"),
+            DurationValue::interval(Duration::zero(), Duration::hours(24)).unwrap()
+        );
+
+        for (input, printed) in [
+            ("6h", "6h"),
+            ("30m", "30m"),
+            ("1d", "24h"),
+            ("90m", "90m"),
+            ("0-24", "0-24"),
+            ("0h-30m", "0h-30m"),
+        ] {
+            assert_eq!(d(input).to_string(), printed);
+            assert_eq!(d(printed), d(input));
+        }
+
+        for s in ["6", "h", "6x", "24-0", "1.5h", "", "-"] {
+            assert!(DurationValue::parse(s).is_none(), "{s:?} should not parse");
+        }
+    }
+
+    #[test]
+    fn test_ordering_is_by_time_not_text() {
+        let coords = Coordinates::from_dimension_string("step", "12h/6h/0-24/0h/30m");
+        assert!(matches!(coords, Coordinates::Durations(_)));
+        assert_eq!(coords.to_string(), "0h/0-24/30m/6h/12h");
+    }
+
+    #[test]
+    fn test_intersect() {
+        let a = Coordinates::from_dimension_string("step", "0h/6h/12h/0-24");
+        let b = Coordinates::from_dimension_string("step", "6h/360m/18h/0-24");
+        let result = a.intersect(&b);
+        assert_eq!(result.intersection.to_string(), "0-24/6h");
+        assert_eq!(result.only_a.to_string(), "0h/12h");
+        assert_eq!(result.only_b.to_string(), "18h");
+    }
+
+    #[test]
+    fn test_only_duration_dimensions_read_durations() {
+        // `2d` (2 m dewpoint) and `10m` are params, not time offsets.
+        for s in ["2d", "10m", "2t/2d", "1-2"] {
+            assert!(matches!(Coordinates::from_string(s), Coordinates::Strings(_)), "{s}");
+            let coords = Coordinates::from_dimension_string("param", s);
+            assert!(matches!(coords, Coordinates::Strings(_)), "{s}");
+        }
+        let step = Coordinates::from_dimension_string("step", "0h/6h/0-24");
+        assert!(matches!(step, Coordinates::Durations(_)));
+
+        let ascii = r#"root
+└── class=od
+    └── param=10m/2d/2t
+        └── step=0h/0-24/6h
+"#;
+        let qube = crate::Qube::from_ascii(ascii).unwrap();
+        assert_eq!(qube.to_ascii(), ascii);
+    }
+}
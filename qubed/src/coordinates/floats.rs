@@ -3,25 +3,105 @@ use std::hash::Hash;
 use crate::coordinates::{Coordinates, IntersectionResult};
 use tiny_vec::TinyVec;
 
-#[derive(Debug, Clone, PartialEq)]
+/// How close two floats must be to count as the same coordinate value.
+///
+/// `a` and `b` match when `|a - b| <= max(absolute, relative * max(|a|, |b|))`.
+/// The default allows a relative difference of `1e-9`, enough to absorb
+/// rounding from arithmetic or from a decimal round-trip, e.g. `0.1 + 0.2`
+/// against `0.3`.  NaN only ever matches NaN.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FloatTolerance {
+    pub absolute: f64,
+    pub relative: f64,
+}
+
+impl FloatTolerance {
+    /// Only bit-for-bit equal values match.
+    pub const EXACT: FloatTolerance = FloatTolerance { absolute: 0.0, relative: 0.0 };
+
+    pub fn new(absolute: f64, relative: f64) -> Self {
+        FloatTolerance { absolute, relative }
+    }
+
+    pub fn matches(&self, a: f64, b: f64) -> bool {
+        if a.is_nan() || b.is_nan() {
+            return a.is_nan() && b.is_nan();
+        }
+        (a - b).abs() <= self.absolute.max(self.relative * a.abs().max(b.abs()))
+    }
+
+    /// An upper bound on `|a - b|` for any `b` that matches `a`.
+    fn reach(&self, a: f64) -> f64 {
+        let relative = if self.relative < 1.0 {
+            self.relative * a.abs() / (1.0 - self.relative)
+        } else {
+            f64::INFINITY
+        };
+        self.absolute.max(relative)
+    }
+}
+
+impl Default for FloatTolerance {
+    fn default() -> Self {
+        FloatTolerance { absolute: 0.0, relative: 1e-9 }
+    }
+}
+
+/// Float values, sorted (by [`f64::total_cmp`]) and without duplicates.
+///
+/// Values are normalised on insertion: `-0.0` becomes `0.0` and every NaN
+/// becomes the single canonical `f64::NAN`, which sorts last.  Equality and
+/// hashing compare the stored values exactly, so NaN equals NaN; tolerances
+/// only apply to [`FloatCoordinates::contains`] and
+/// [`FloatCoordinates::intersect`].
+#[derive(Debug, Clone)]
 pub enum FloatCoordinates {
     List(TinyVec<f64, 4>),
 }
 
+fn normalise(value: f64) -> f64 {
+    if value.is_nan() {
+        f64::NAN
+    } else if value == 0.0 {
+        0.0
+    } else {
+        value
+    }
+}
+
+/// Returns `true` if some value of `sorted` matches `value` within `tolerance`.
+fn has_match(sorted: &[f64], value: f64, tolerance: FloatTolerance) -> bool {
+    if value.is_nan() {
+        return sorted.last().is_some_and(|v| v.is_nan());
+    }
+    let reach = tolerance.reach(value);
+    let first = sorted.partition_point(|&v| v < value - reach);
+    sorted[first..]
+        .iter()
+        .take_while(|&&v| v <= value + reach)
+        .any(|&v| tolerance.matches(value, v))
+}
+
 impl FloatCoordinates {
     pub(crate) fn extend(&mut self, new_coords: &FloatCoordinates) {
-        match (self, new_coords) {
-            (FloatCoordinates::List(list), FloatCoordinates::List(new_list)) => {
+        match new_coords {
+            FloatCoordinates::List(new_list) => {
                 for &v in new_list.iter() {
-                    list.push(v);
+                    self.append(v);
                 }
             }
         }
     }
 
     pub(crate) fn append(&mut self, new_coord: f64) {
+        let value = normalise(new_coord);
         match self {
-            FloatCoordinates::List(list) => list.push(new_coord),
+            FloatCoordinates::List(list) => {
+                if let Err(pos) = list.binary_search_by(|v| v.total_cmp(&value)) {
+                    // `pos` is at most `list.len()`, so the insert cannot fail.
+                    let _ = list.insert(pos, value);
+                }
+            }
         }
     }
 
@@ -31,9 +111,16 @@ impl FloatCoordinates {
         }
     }
 
-    pub(crate) fn contains(&self, value: f64) -> bool {
+    /// Iterates over the values in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = f64> + '_ {
         match self {
-            FloatCoordinates::List(list) => list.iter().any(|&v| v.to_bits() == value.to_bits()),
+            FloatCoordinates::List(list) => list.iter().copied(),
+        }
+    }
+
+    pub(crate) fn contains(&self, value: f64, tolerance: FloatTolerance) -> bool {
+        match self {
+            FloatCoordinates::List(list) => has_match(list, normalise(value), tolerance),
         }
     }
 
@@ -56,49 +143,28 @@ impl FloatCoordinates {
         }
     }
 
+    /// Splits the values of `self` and `other` by whether they have a match
+    /// within `tolerance` in the other set.  Values that match keep the value
+    /// from `self` in the intersection.
     pub(crate) fn intersect(
         &self,
         other: &FloatCoordinates,
+        tolerance: FloatTolerance,
     ) -> IntersectionResult<FloatCoordinates> {
         match (self, other) {
             (FloatCoordinates::List(list_a), FloatCoordinates::List(list_b)) => {
-                use std::collections::HashSet;
-
-                let mut set_a: HashSet<u64> = HashSet::new();
-                for v in list_a.iter() {
-                    set_a.insert(v.to_bits());
-                }
-
-                let mut set_b: HashSet<u64> = HashSet::new();
-                for v in list_b.iter() {
-                    set_b.insert(v.to_bits());
-                }
-
                 let mut intersection = TinyVec::new();
                 let mut only_a = TinyVec::new();
-                let mut only_b = TinyVec::new();
-
-                // preserve order from list_a for intersection and only_a
-                let mut added: HashSet<u64> = HashSet::new();
-                for v in list_a.iter() {
-                    let bits = v.to_bits();
-                    if set_b.contains(&bits) {
-                        if !added.contains(&bits) {
-                            intersection.push(*v);
-                            added.insert(bits);
-                        }
+                for &v in list_a.iter() {
+                    if has_match(list_b, v, tolerance) {
+                        intersection.push(v);
                     } else {
-                        only_a.push(*v);
+                        only_a.push(v);
                     }
                 }
 
-                // for only_b, preserve order from list_b skipping those present in set_a
-                for v in list_b.iter() {
-                    let bits = v.to_bits();
-                    if !set_a.contains(&bits) {
-                        only_b.push(*v);
-                    }
-                }
+                let only_b =
+                    list_b.iter().copied().filter(|&v| !has_match(list_a, v, tolerance)).collect();
 
                 IntersectionResult {
                     intersection: FloatCoordinates::List(intersection),
@@ -116,11 +182,19 @@ impl Default for FloatCoordinates {
     }
 }
 
+/// Values are compared bit for bit, so that NaN equals NaN.
+impl PartialEq for FloatCoordinates {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self.iter().zip(other.iter()).all(|(a, b)| a.to_bits() == b.to_bits())
+    }
+}
+
 impl From<f64> for Coordinates {
     fn from(value: f64) -> Self {
-        let mut vec = TinyVec::new();
-        vec.push(value);
-        Coordinates::Floats(FloatCoordinates::List(vec))
+        let mut floats = FloatCoordinates::default();
+        floats.append(value);
+        Coordinates::Floats(floats)
     }
 }
 
@@ -132,11 +206,11 @@ impl From<FloatCoordinates> for Coordinates {
 
 impl From<&[f64]> for Coordinates {
     fn from(value: &[f64]) -> Self {
-        let mut vec = TinyVec::new();
+        let mut floats = FloatCoordinates::default();
         for &v in value {
-            vec.push(v);
+            floats.append(v);
         }
-        Coordinates::Floats(FloatCoordinates::List(vec))
+        Coordinates::Floats(floats)
     }
 }
 
@@ -205,7 +279,7 @@ mod tests {
         b.append(3.0);
         b.append(4.0);
 
-        let result = a.intersect(&b);
+        let result = a.intersect(&b, FloatTolerance::EXACT);
 
         match result.intersection {
             FloatCoordinates::List(list) => {
@@ -230,6 +304,40 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_float_coordinates_are_sorted_and_deduplicated() {
+        let c = Coordinates::from(&[2.5, -1.0, 2.5, 0.0, -0.0][..]);
+        assert_eq!(c.to_string(), "-1/0/2.5");
+        assert_eq!(c, Coordinates::from(&[0.0, 2.5, -1.0][..]));
+    }
+
+    #[test]
+    fn test_nan_is_normalised() {
+        let a = Coordinates::from(&[f64::NAN, 1.0, -f64::NAN][..]);
+        let b = Coordinates::from(&[1.0, f64::NAN][..]);
+        assert_eq!(a.len(), 2);
+        assert_eq!(a, b);
+        assert!(a.contains(f64::NAN));
+        assert_eq!(a.intersect(&b).intersection.len(), 2);
+    }
+
+    #[test]
+    fn test_tolerance() {
+        let a = Coordinates::from(0.1 + 0.2);
+        let b = Coordinates::from(0.3);
+        assert_eq!(a.intersect(&b).intersection.len(), 1);
+        assert!(a.contains(0.3));
+        assert!(a.intersect_with_tolerance(&b, FloatTolerance::EXACT).intersection.is_empty());
+        assert!(!a.contains_with_tolerance(0.3, FloatTolerance::EXACT));
+
+        let grid = Coordinates::from(&[0.0, 0.25, 0.5, 0.75][..]);
+        let points = Coordinates::from(&[0.24, 0.51, 0.9][..]);
+        let result = grid.intersect_with_tolerance(&points, FloatTolerance::new(0.02, 0.0));
+        assert_eq!(result.intersection.to_string(), "0.25/0.5");
+        assert_eq!(result.only_a.to_string(), "0/0.75");
+        assert_eq!(result.only_b.to_string(), "0.9");
+    }
+
     #[test]
     fn test_from_conversions() {
         // From<f64>
@@ -287,38 +395,28 @@ mod tests {
 
 impl<const N: usize> From<&[f64; N]> for Coordinates {
     fn from(value: &[f64; N]) -> Self {
-        let mut vec = TinyVec::new();
-        for &v in value.iter() {
-            vec.push(v);
-        }
-        Coordinates::Floats(FloatCoordinates::List(vec))
+        Coordinates::from(&value[..])
     }
 }
 
 impl From<f32> for Coordinates {
     fn from(value: f32) -> Self {
-        let mut vec = TinyVec::new();
-        vec.push(value as f64);
-        Coordinates::Floats(FloatCoordinates::List(vec))
+        Coordinates::from(value as f64)
     }
 }
 
 impl From<&[f32]> for Coordinates {
     fn from(value: &[f32]) -> Self {
-        let mut vec = TinyVec::new();
+        let mut floats = FloatCoordinates::default();
         for &v in value {
-            vec.push(v as f64);
+            floats.append(v as f64);
         }
-        Coordinates::Floats(FloatCoordinates::List(vec))
+        Coordinates::Floats(floats)
     }
 }
 
 impl<const N: usize> From<&[f32; N]> for Coordinates {
     fn from(value: &[f32; N]) -> Self {
-        let mut vec = TinyVec::new();
-        for &v in value.iter() {
-            vec.push(v as f64);
-        }
-        Coordinates::Floats(FloatCoordinates::List(vec))
+        Coordinates::from(&value[..])
     }
 }
@@ -1,15 +1,31 @@
 use std::hash::Hash;
+use std::num::NonZeroU16;
+use std::ops::Bound;
 
+use chrono::NaiveDate;
+
+use crate::coordinates::ranges::{
+    self, Canonical, Grid, MIN_RANGE_LEN, RangeBuilder, Segment, Side, walk,
+};
 use crate::coordinates::{Coordinates, IntersectionResult};
 use crate::utils::tiny_ordered_set::TinyOrderedSet;
 use tiny_vec::TinyVec;
 
+/// A set of integers.
+///
+/// The representation is canonical, so two `IntegerCoordinates` holding the same
+/// values always compare (and hash) equal: scanning the sorted values from the
+/// left, every arithmetic run of at least [`MIN_RANGE_LEN`] values becomes an
+/// [`IntegerRange`].  If there is no such run the values are kept in a `Set`;
+/// otherwise everything is stored as a `RangeSet`, with the values outside long
+/// runs held as single-value ranges.
 #[derive(Debug, Clone, PartialEq)]
 pub enum IntegerCoordinates {
     Set(TinyOrderedSet<i32, 6>),
     RangeSet(TinyVec<IntegerRange, 2>),
 }
 
+/// The values `start, start + step, ..., end`, where `end` always lies on the grid.
 #[derive(Debug, Clone, PartialEq)]
 pub struct IntegerRange {
     start: i32,
@@ -17,27 +33,70 @@ pub struct IntegerRange {
     step: std::num::NonZeroU16,
 }
 
+impl IntegerRange {
+    fn single(value: i32) -> Self {
+        IntegerRange { start: value, end: value, step: NonZeroU16::MIN }
+    }
+
+    /// The values `start, start + step, ..., end`, or `None` if `end` is below
+    /// `start` or not on the grid.
+    pub(crate) fn new(start: i32, end: i32, step: NonZeroU16) -> Option<Self> {
+        let span = end as i64 - start as i64;
+        (span >= 0 && span % step.get() as i64 == 0).then_some(IntegerRange { start, end, step })
+    }
+
+    pub fn start(&self) -> i32 {
+        self.start
+    }
+
+    pub fn end(&self) -> i32 {
+        self.end
+    }
+
+    pub fn step(&self) -> NonZeroU16 {
+        self.step
+    }
+
+    pub fn len(&self) -> usize {
+        ((self.end as i64 - self.start as i64) / self.step.get() as i64) as usize + 1
+    }
+
+    pub fn contains(&self, value: i32) -> bool {
+        value >= self.start
+            && value <= self.end
+            && (value as i64 - self.start as i64) % self.step.get() as i64 == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = i32> + '_ {
+        (self.start..=self.end).step_by(self.step.get() as usize)
+    }
+}
+
 impl IntegerCoordinates {
     pub(crate) fn extend(&mut self, new_coords: &IntegerCoordinates) {
-        match new_coords {
-            IntegerCoordinates::Set(set) => {
-                for val in set.iter() {
-                    self.append(*val);
-                }
-            }
-            IntegerCoordinates::RangeSet(_) => {
-                unimplemented!("Integer Range compression not currently supported");
-            }
-        }
+        let mut builder = RangeBuilder::default();
+        walk(self.segments(), new_coords.segments(), |_, segment| builder.push_segment(segment));
+        *self = Self::from_builder(builder);
     }
 
     pub(crate) fn append(&mut self, new_coord: i32) {
         match self {
             IntegerCoordinates::Set(set) => {
                 set.insert(new_coord);
+                if set.len() >= MIN_RANGE_LEN && has_long_run_through(set, new_coord) {
+                    let mut builder = RangeBuilder::default();
+                    for &v in set.iter() {
+                        builder.push(v as i64);
+                    }
+                    *self = Self::from_builder(builder);
+                }
             }
             IntegerCoordinates::RangeSet(_) => {
-                unimplemented!("Integer Range compression not currently supported");
+                if !self.contains(new_coord) {
+                    let mut single = TinyOrderedSet::new();
+                    single.insert(new_coord);
+                    self.extend(&IntegerCoordinates::Set(single));
+                }
             }
         }
     }
@@ -45,41 +104,122 @@ impl IntegerCoordinates {
     pub(crate) fn len(&self) -> usize {
         match self {
             IntegerCoordinates::Set(list) => list.len(),
-            IntegerCoordinates::RangeSet(_) => {
-                unimplemented!("Integer Range compression not currently supported")
+            IntegerCoordinates::RangeSet(ranges) => ranges.iter().map(IntegerRange::len).sum(),
+        }
+    }
+
+    /// Iterates over the values in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = i32> + '_ {
+        match self {
+            IntegerCoordinates::Set(set) => itertools::Either::Left(set.iter().copied()),
+            IntegerCoordinates::RangeSet(ranges) => {
+                itertools::Either::Right(ranges.iter().flat_map(IntegerRange::iter))
             }
         }
     }
 
+    /// The values `start, start + step, ...` up to and including `end`, in the
+    /// MARS `start/to/end/by/step` sense.  `step` must be negative when counting
+    /// down; returns `None` if it is zero or points away from `end`.
+    pub fn from_range(start: i32, end: i32, step: i64) -> Option<IntegerCoordinates> {
+        let (start, end) = (start as i64, end as i64);
+        if step == 0 || (end - start).signum() * step.signum() < 0 {
+            return None;
+        }
+        let count = (end - start) / step + 1;
+        let lowest = if step > 0 { start } else { start + (count - 1) * step };
+
+        let mut builder = RangeBuilder::default();
+        builder.push_segment(Segment { start: lowest, step: step.abs(), count: count as i128 });
+        Some(Self::from_builder(builder))
+    }
+
+    fn from_builder(builder: RangeBuilder<i64>) -> Self {
+        match builder.finish() {
+            Canonical::Values(values) => {
+                let mut set = TinyOrderedSet::new();
+                for v in values {
+                    set.insert(v as i32);
+                }
+                IntegerCoordinates::Set(set)
+            }
+            Canonical::Runs(runs) => IntegerCoordinates::RangeSet(
+                runs.into_iter()
+                    .map(|r| IntegerRange {
+                        start: r.start as i32,
+                        end: r.end as i32,
+                        step: NonZeroU16::new(r.step as u16).unwrap(),
+                    })
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Formats the values `/`-separated, writing each stored range as
+    /// `start/to/end[/by/step]`.  Runs of three or more evenly spaced `YYYYMMDD`
+    /// values are written as calendar date ranges, which may span several
+    /// stored ranges.
     pub(crate) fn to_string(&self) -> String {
-        match self {
-            IntegerCoordinates::Set(set) => {
-                set.iter().map(|v| v.to_string()).collect::<Vec<String>>().join("/")
+        let ranges: Vec<IntegerRange> = match self {
+            IntegerCoordinates::Set(set) => set.iter().map(|&v| IntegerRange::single(v)).collect(),
+            IntegerCoordinates::RangeSet(ranges) => ranges.iter().cloned().collect(),
+        };
+
+        let mut parts = Vec::new();
+        let mut dates = DateRun::default();
+
+        for range in &ranges {
+            match (ranges::int_to_date(range.start), ranges::int_to_date(range.end)) {
+                // Within one month, stepping through integers and through calendar
+                // days give the same values.
+                (Some(first), Some(last)) if range.start / 100 == range.end / 100 => {
+                    dates.push(first, last, range.step.get() as i64, range.len(), &mut parts);
+                }
+                // Across months they do not, so fall back to listing the values.
+                (Some(_), Some(_)) => {
+                    for v in range.iter() {
+                        match ranges::int_to_date(v) {
+                            Some(d) => dates.push(d, d, 1, 1, &mut parts),
+                            None => {
+                                dates.flush(&mut parts);
+                                parts.push(v.to_string());
+                            }
+                        }
+                    }
+                }
+                _ => {
+                    dates.flush(&mut parts);
+                    parts.push(match (range.len(), range.step.get()) {
+                        (1, _) => range.start.to_string(),
+                        (_, 1) => format!("{}/to/{}", range.start, range.end),
+                        (_, step) => format!("{}/to/{}/by/{}", range.start, range.end, step),
+                    });
+                }
             }
-            IntegerCoordinates::RangeSet(ranges) => ranges
-                .iter()
-                .map(|v| format!("{}:{}:{}", v.start, v.step, v.end))
-                .collect::<Vec<String>>()
-                .join("/"),
         }
+
+        dates.flush(&mut parts);
+        parts.join("/")
     }
 
     pub(crate) fn intersect(
         &self,
         other: &IntegerCoordinates,
     ) -> IntersectionResult<IntegerCoordinates> {
-        match (self, other) {
-            (IntegerCoordinates::Set(set_a), IntegerCoordinates::Set(set_b)) => {
-                let result = set_a.intersect(set_b);
-                IntersectionResult {
-                    intersection: IntegerCoordinates::Set(result.intersection),
-                    only_a: IntegerCoordinates::Set(result.only_a),
-                    only_b: IntegerCoordinates::Set(result.only_b),
-                }
-            }
-            _ => {
-                unimplemented!("Integer Range compression not currently supported");
-            }
+        let mut intersection = RangeBuilder::default();
+        let mut only_a = RangeBuilder::default();
+        let mut only_b = RangeBuilder::default();
+
+        walk(self.segments(), other.segments(), |side, segment| match side {
+            Side::Both => intersection.push_segment(segment),
+            Side::OnlyA => only_a.push_segment(segment),
+            Side::OnlyB => only_b.push_segment(segment),
+        });
+
+        IntersectionResult {
+            intersection: Self::from_builder(intersection),
+            only_a: Self::from_builder(only_a),
+            only_b: Self::from_builder(only_b),
         }
     }
 
@@ -100,6 +240,128 @@ impl IntegerCoordinates {
             }
         }
     }
+
+    /// The values as evenly spaced segments, in ascending order.
+    fn segments(&self) -> Vec<Segment<i64>> {
+        match self {
+            IntegerCoordinates::Set(set) => {
+                set.iter().map(|&v| Segment::single(v as i64)).collect()
+            }
+            IntegerCoordinates::RangeSet(ranges) => ranges
+                .iter()
+                .map(|r| Segment {
+                    start: r.start as i64,
+                    step: r.step.get() as i64,
+                    count: r.len() as i128,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Integers are widened to `i64` while building ranges, so that steps between
+/// any two `i32` values can be represented.  A range's step must fit in a `u16`.
+impl Grid for i64 {
+    type Step = i64;
+
+    fn unit() -> i64 {
+        1
+    }
+
+    fn units(step: i64) -> i128 {
+        step as i128
+    }
+
+    fn distance(self, to: i64) -> i128 {
+        (to - self) as i128
+    }
+
+    fn advance(self, step: i64, n: i128) -> i64 {
+        self + (step as i128 * n) as i64
+    }
+
+    fn step_to(self, next: i64) -> Option<i64> {
+        let step = next - self;
+        (step <= u16::MAX as i64).then_some(step)
+    }
+}
+
+/// Whether inserting `value` into `set` created an arithmetic run of at least
+/// [`MIN_RANGE_LEN`] values.  Such a run must pass through `value`, so only its
+/// neighbours need to be looked at.
+fn has_long_run_through(set: &TinyOrderedSet<i32, 6>, value: i32) -> bool {
+    let before: Vec<i64> =
+        set.range(..value).rev().take(MIN_RANGE_LEN - 1).map(|&v| v as i64).collect();
+    let window: Vec<i64> = before
+        .into_iter()
+        .rev()
+        .chain(
+            set.range((Bound::Included(value), Bound::Unbounded))
+                .take(MIN_RANGE_LEN)
+                .map(|&v| v as i64),
+        )
+        .collect();
+    ranges::has_long_run(&window)
+}
+
+/// A run of evenly spaced calendar days being collected for printing.
+#[derive(Default)]
+struct DateRun {
+    first: Option<NaiveDate>,
+    last: Option<NaiveDate>,
+    step_days: i64,
+    count: usize,
+}
+
+impl DateRun {
+    /// Adds the days `first, first + step_days, ..., last` (`count` of them),
+    /// flushing the current run first if they do not continue it.
+    fn push(
+        &mut self,
+        first: NaiveDate,
+        last: NaiveDate,
+        step_days: i64,
+        count: usize,
+        parts: &mut Vec<String>,
+    ) {
+        if let Some(run_last) = self.last {
+            let gap = (first - run_last).num_days();
+            let continues = match (self.count, count) {
+                (1, 1) => true,
+                (1, _) => gap == step_days,
+                (_, 1) => gap == self.step_days,
+                _ => gap == self.step_days && step_days == self.step_days,
+            };
+            if continues {
+                if self.count == 1 {
+                    self.step_days = gap;
+                }
+                self.last = Some(last);
+                self.count += count;
+                return;
+            }
+            self.flush(parts);
+        }
+
+        self.first = Some(first);
+        self.last = Some(last);
+        self.step_days = step_days;
+        self.count = count;
+    }
+
+    fn flush(&mut self, parts: &mut Vec<String>) {
+        let (Some(first), Some(last)) = (self.first.take(), self.last.take()) else {
+            return;
+        };
+        let (first, last) = (ranges::date_to_int(first), ranges::date_to_int(last));
+        match (self.count, self.step_days) {
+            (1, _) => parts.push(first.to_string()),
+            (2, _) => parts.push(format!("{}/{}", first, last)),
+            (_, 1) => parts.push(format!("{}/to/{}", first, last)),
+            (_, step) => parts.push(format!("{}/to/{}/by/{}", first, last, step)),
+        }
+        self.count = 0;
+    }
 }
 
 impl From<IntegerCoordinates> for Coordinates {
@@ -118,21 +380,17 @@ impl From<i32> for Coordinates {
 
 impl From<&[i32]> for Coordinates {
     fn from(value: &[i32]) -> Self {
-        let mut set = TinyOrderedSet::new();
+        let mut ints = IntegerCoordinates::default();
         for &v in value {
-            set.insert(v);
+            ints.append(v);
         }
-        Coordinates::Integers(IntegerCoordinates::Set(set))
+        Coordinates::Integers(ints)
     }
 }
 
 impl<const N: usize> From<&[i32; N]> for Coordinates {
     fn from(value: &[i32; N]) -> Self {
-        let mut set = TinyOrderedSet::new();
-        for &v in value {
-            set.insert(v);
-        }
-        Coordinates::Integers(IntegerCoordinates::Set(set))
+        Coordinates::from(&value[..])
     }
 }
 
@@ -146,7 +404,10 @@ impl IntegerCoordinates {
     pub fn contains(&self, value: i32) -> bool {
         match self {
             IntegerCoordinates::Set(set) => set.contains(&value),
-            IntegerCoordinates::RangeSet(_) => unimplemented!("RangeSet contains not implemented"),
+            IntegerCoordinates::RangeSet(ranges) => {
+                let i = ranges.partition_point(|r| r.end < value);
+                ranges.get(i).is_some_and(|r| r.contains(value))
+            }
         }
     }
 }
@@ -187,4 +448,116 @@ mod tests {
         assert_eq!(result.only_a, expected_only_a);
         assert_eq!(result.only_b, expected_only_b);
     }
+
+    fn ints(values: impl IntoIterator<Item = i32>) -> IntegerCoordinates {
+        let mut coords = IntegerCoordinates::default();
+        for v in values {
+            coords.append(v);
+        }
+        coords
+    }
+
+    fn hash_of(coords: &IntegerCoordinates) -> u64 {
+        use std::hash::Hasher;
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        coords.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn long_runs_become_ranges() {
+        let steps = ints(0..=360);
+        match &steps {
+            IntegerCoordinates::RangeSet(ranges) => assert_eq!(ranges.len(), 1),
+            other => panic!("expected a RangeSet, got {:?}", other),
+        }
+        assert_eq!(steps.len(), 361);
+        assert!(steps.contains(0) && steps.contains(180) && steps.contains(360));
+        assert!(!steps.contains(-1) && !steps.contains(361));
+        assert_eq!(steps.iter().collect::<Vec<_>>(), (0..=360).collect::<Vec<_>>());
+
+        // Short runs stay a plain set.
+        assert!(matches!(ints([1, 2, 3, 10, 20]), IntegerCoordinates::Set(_)));
+    }
+
+    #[test]
+    fn representation_is_canonical() {
+        let forwards = ints((0..=100).step_by(5).chain([7, 1000]));
+        let backwards = ints([1000, 7].into_iter().chain((0..=100).rev().step_by(5)));
+        let mut extended = ints([1000, 7]);
+        extended.extend(&ints((0..=100).step_by(5)));
+
+        assert_eq!(forwards, backwards);
+        assert_eq!(forwards, extended);
+        assert_eq!(hash_of(&forwards), hash_of(&extended));
+        assert_eq!(forwards.len(), 23);
+        assert!(forwards.contains(7) && !forwards.contains(6));
+    }
+
+    #[test]
+    fn removing_values_can_form_a_range() {
+        // 0/2/4/5/6/8/.../16 has no run of 8, but without 5 it is 0/to/16/by/2.
+        let a = ints([0, 2, 4, 5, 6, 8, 10, 12, 14, 16]);
+        assert!(matches!(a, IntegerCoordinates::Set(_)));
+
+        let result = a.intersect(&ints([5]));
+        assert!(matches!(result.only_a, IntegerCoordinates::RangeSet(_)));
+        assert_eq!(result.only_a, ints((0..=16).step_by(2)));
+        assert_eq!(result.intersection, ints([5]));
+    }
+
+    #[test]
+    fn ranges_print_compactly() {
+        assert_eq!(ints([1, 2, 3, 10]).to_string(), "1/2/3/10");
+        assert_eq!(ints((0..=240).step_by(6).chain([1000])).to_string(), "0/to/240/by/6/1000");
+
+        // Dates step through the calendar, across month boundaries too.
+        let jan_feb = ints((20240101..=20240131).chain(20240201..=20240229));
+        assert_eq!(jan_feb.to_string(), "20240101/to/20240229");
+        let weekly =
+            ints([20240101, 20240108, 20240115, 20240122, 20240129, 20240205, 20240212, 20240219]);
+        assert_eq!(weekly.to_string(), "20240101/to/20240219/by/7");
+    }
+
+    #[test]
+    fn intersect_matches_btreeset() {
+        use std::collections::BTreeSet;
+
+        // Small deterministic generator so the test needs no extra dependency.
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = |n: u64| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state % n
+        };
+
+        for _ in 0..200 {
+            let mut make = || {
+                let mut values = BTreeSet::new();
+                for _ in 0..next(4) {
+                    let start = next(200) as i32 - 100;
+                    let step = next(5) as usize + 1;
+                    let len = next(30) as i32;
+                    values.extend((start..start + len * step as i32).step_by(step));
+                }
+                for _ in 0..next(10) {
+                    values.insert(next(300) as i32 - 150);
+                }
+                values
+            };
+            let (set_a, set_b) = (make(), make());
+            let (a, b) = (ints(set_a.iter().copied()), ints(set_b.iter().copied()));
+
+            let result = a.intersect(&b);
+            assert_eq!(result.intersection, ints(set_a.intersection(&set_b).copied()));
+            assert_eq!(result.only_a, ints(set_a.difference(&set_b).copied()));
+            assert_eq!(result.only_b, ints(set_b.difference(&set_a).copied()));
+
+            let mut union = a.clone();
+            union.extend(&b);
+            assert_eq!(union, ints(set_a.union(&set_b).copied()));
+            assert_eq!(union.len(), set_a.union(&set_b).count());
+        }
+    }
 }
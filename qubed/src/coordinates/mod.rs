@@ -1,13 +1,16 @@
 pub mod datetime;
+pub mod durations;
 pub mod floats;
 pub mod integers;
 pub mod ops;
+mod ranges;
 pub mod strings;
 use std::hash::Hash;
 
 use chrono::NaiveDateTime;
 use datetime::DateTimeCoordinates;
-use floats::FloatCoordinates;
+use durations::{DurationCoordinates, DurationValue};
+use floats::{FloatCoordinates, FloatTolerance};
 use integers::IntegerCoordinates;
 use strings::StringCoordinates;
 
@@ -24,53 +27,101 @@ pub enum Coordinates {
     Floats(FloatCoordinates),
     Strings(StringCoordinates),
     DateTimes(DateTimeCoordinates),
+    Durations(DurationCoordinates),
     Mixed(Box<MixedCoordinates>),
 }
 
+/// A single coordinate value.
+#[derive(Debug, Clone, PartialEq)]
 pub enum CoordinateTypes {
     Integer(i32),
     Float(f64),
     String(String),
     DateTime(NaiveDateTime),
+    Duration(DurationValue),
 }
 
 #[derive(Debug, Clone, PartialEq, Default)]
 pub struct MixedCoordinates {
-    integers: integers::IntegerCoordinates,
-    floats: FloatCoordinates,
-    strings: StringCoordinates,
-    datetimes: DateTimeCoordinates,
+    pub(crate) integers: integers::IntegerCoordinates,
+    pub(crate) floats: FloatCoordinates,
+    pub(crate) strings: StringCoordinates,
+    pub(crate) datetimes: DateTimeCoordinates,
+    pub(crate) durations: DurationCoordinates,
 }
 
+/// Dimensions whose values are time offsets, read as durations by
+/// [`Coordinates::from_dimension_string`].
+pub const DURATION_DIMENSIONS: &[&str] = &["step", "leadtime", "leadtime_hour"];
+
 impl Coordinates {
     pub fn new() -> Self {
         Coordinates::Empty
     }
 
+    /// Parses a `/`-separated list of values, e.g. `1/2/3` or `od/rd`.
+    ///
+    /// MARS-style ranges are expanded: `0/to/240/by/6` for integers,
+    /// `20240101/to/20241231` for `YYYYMMDD` dates (stepping through calendar
+    /// days) and `2024-01-01T00:00:00/to/2024-01-02T00:00:00/by/6h` for
+    /// date-times.  Values such as `2d` or `10m` stay strings; use
+    /// [`Coordinates::from_dimension_string`] to read them as durations.  See
+    /// [`Coordinates::to_string`] for the reverse.
     pub fn from_string(s: &str) -> Self {
+        Self::parse_values(s, false)
+    }
+
+    /// Parses values of `dimension` like [`Coordinates::from_string`], except
+    /// that for the dimensions in [`DURATION_DIMENSIONS`] durations such as
+    /// `6h`, `30m` or `1d`, and intervals such as `0-24`, become
+    /// [`Coordinates::Durations`].
+    pub fn from_dimension_string(dimension: &str, s: &str) -> Self {
+        Self::parse_values(s, DURATION_DIMENSIONS.contains(&dimension))
+    }
+
+    fn parse_values(s: &str, durations: bool) -> Self {
         if s.is_empty() {
             return Coordinates::Empty;
         }
         let mut coords = Coordinates::Empty;
         let split: Vec<&str> = s.split('/').collect();
+        let items = ranges::split_ranges(&split);
+        let singles: Vec<&str> = items
+            .iter()
+            .filter_map(|item| match item {
+                ranges::Item::Single(part) => Some(*part),
+                ranges::Item::Range(_) => None,
+            })
+            .collect();
 
         // When multiple values are present, ensure consistent typing:
         // if all parse as integers but some have leading zeros, keep all as strings.
-        let all_int = split.iter().all(|p| p.parse::<i32>().is_ok());
-        let any_leading_zero = split.iter().any(|p| {
+        let all_int = singles.iter().all(|p| p.parse::<i32>().is_ok());
+        let any_leading_zero = singles.iter().any(|p| {
             p.len() > 1
                 && p.starts_with('0')
                 && p.chars().nth(1).map_or(false, |c| c.is_ascii_digit())
         });
         let force_strings = all_int && any_leading_zero;
 
-        for part in split {
+        for item in items {
+            let part = match item {
+                ranges::Item::Single(part) => part,
+                ranges::Item::Range(range) => {
+                    coords.extend(&range);
+                    continue;
+                }
+            };
             if force_strings {
                 coords.append(part.to_string());
             } else if let Ok(int_val) = part.parse::<i32>() {
                 coords.append(int_val);
             } else if let Ok(float_val) = part.parse::<f64>() {
                 coords.append(float_val);
+            } else if let Some(datetime) = ranges::parse_iso_datetime(part) {
+                coords.append(datetime);
+            } else if let Some(duration) = DurationValue::parse(part).filter(|_| durations) {
+                coords.append(duration);
             } else {
                 coords.append(part.to_string());
             }
@@ -78,12 +129,19 @@ impl Coordinates {
         coords
     }
 
+    /// Formats the values as a `/`-separated list that [`Coordinates::from_string`]
+    /// parses back to the same coordinates.
+    ///
+    /// Long integer runs are written as MARS-style ranges (`0/to/240/by/6`), and
+    /// runs of consecutive `YYYYMMDD` dates as date ranges (`20240101/to/20241231`).
+    /// Use [`Coordinates::to_value_strings`] to list every value individually.
     pub fn to_string(&self) -> String {
         match self {
             Coordinates::Empty => "".to_string(),
             Coordinates::Integers(ints) => ints.to_string(),
             Coordinates::Floats(floats) => floats.to_string(),
             Coordinates::DateTimes(datetimes) => datetimes.to_string(),
+            Coordinates::Durations(durations) => durations.to_string(),
             Coordinates::Strings(strings) => strings.to_string(),
             Coordinates::Mixed(mixed) => {
                 let mut parts: Vec<String> = Vec::new();
@@ -103,6 +161,10 @@ impl Coordinates {
                 if !datetimes_str.is_empty() {
                     parts.push(datetimes_str);
                 }
+                let durations_str = mixed.durations.to_string();
+                if !durations_str.is_empty() {
+                    parts.push(durations_str);
+                }
                 parts.join("/")
             }
         }
@@ -115,11 +177,13 @@ impl Coordinates {
             Coordinates::Floats(floats) => floats.len(),
             Coordinates::Strings(strings) => strings.len(),
             Coordinates::DateTimes(datetimes) => datetimes.len(),
+            Coordinates::Durations(durations) => durations.len(),
             Coordinates::Mixed(mixed) => {
                 mixed.integers.len()
                     + mixed.floats.len()
                     + mixed.strings.len()
                     + mixed.datetimes.len()
+                    + mixed.durations.len()
             }
         }
     }
@@ -129,6 +193,14 @@ impl Coordinates {
     }
 
     pub fn contains<T>(&self, value: T) -> bool
+    where
+        T: Into<CoordinateTypes>,
+    {
+        self.contains_with_tolerance(value, FloatTolerance::default())
+    }
+
+    /// Like [`Coordinates::contains`], but floats match within `tolerance`.
+    pub fn contains_with_tolerance<T>(&self, value: T, tolerance: FloatTolerance) -> bool
     where
         T: Into<CoordinateTypes>,
     {
@@ -139,15 +211,25 @@ impl Coordinates {
             (Coordinates::DateTimes(datetimes), CoordinateTypes::DateTime(val)) => {
                 datetimes.contains(val)
             }
-            (Coordinates::Floats(floats), CoordinateTypes::Float(val)) => floats.contains(val),
+            (Coordinates::Floats(floats), CoordinateTypes::Float(val)) => {
+                floats.contains(val, tolerance)
+            }
             (Coordinates::Strings(strings), CoordinateTypes::String(val)) => strings.contains(val),
             (Coordinates::Mixed(mixed), CoordinateTypes::Integer(val)) => {
                 mixed.integers.contains(val)
             }
-            (Coordinates::Mixed(mixed), CoordinateTypes::Float(val)) => mixed.floats.contains(val),
+            (Coordinates::Mixed(mixed), CoordinateTypes::Float(val)) => {
+                mixed.floats.contains(val, tolerance)
+            }
+            (Coordinates::Durations(durations), CoordinateTypes::Duration(val)) => {
+                durations.contains(val)
+            }
             (Coordinates::Mixed(mixed), CoordinateTypes::DateTime(val)) => {
                 mixed.datetimes.contains(val)
             }
+            (Coordinates::Mixed(mixed), CoordinateTypes::Duration(val)) => {
+                mixed.durations.contains(val)
+            }
             (Coordinates::Mixed(mixed), CoordinateTypes::String(val)) => {
                 mixed.strings.contains(val)
             }
@@ -169,6 +251,9 @@ impl Coordinates {
             Coordinates::DateTimes(datetimes) => {
                 Box::new(MixedCoordinates { datetimes: datetimes.to_owned(), ..Default::default() })
             }
+            Coordinates::Durations(durations) => {
+                Box::new(MixedCoordinates { durations: durations.to_owned(), ..Default::default() })
+            }
             Coordinates::Empty => Box::new(MixedCoordinates::default()),
             Coordinates::Mixed(_) => {
                 return self;
@@ -185,11 +270,24 @@ impl Coordinates {
             Coordinates::Floats(_) => "Floats",
             Coordinates::Strings(_) => "Strings",
             Coordinates::DateTimes(_) => "DateTimes",
+            Coordinates::Durations(_) => "Durations",
             Coordinates::Mixed(_) => "Mixed",
         }
     }
 
+    /// Splits the values of `self` and `other` into those in both, those only
+    /// in `self` and those only in `other`.  Floats match within the default
+    /// [`FloatTolerance`].
     pub fn intersect(&self, other: &Coordinates) -> IntersectionResult<Coordinates> {
+        self.intersect_with_tolerance(other, FloatTolerance::default())
+    }
+
+    /// Like [`Coordinates::intersect`], but floats match within `tolerance`.
+    pub fn intersect_with_tolerance(
+        &self,
+        other: &Coordinates,
+        tolerance: FloatTolerance,
+    ) -> IntersectionResult<Coordinates> {
         match (self, other) {
             // Empty
             (Coordinates::Empty, _) => IntersectionResult {
@@ -212,7 +310,7 @@ impl Coordinates {
                 }
             }
             (Coordinates::Floats(a), Coordinates::Floats(b)) => {
-                let r = a.intersect(b);
+                let r = a.intersect(b, tolerance);
                 IntersectionResult {
                     intersection: wrap_floats(r.intersection),
                     only_a: wrap_floats(r.only_a),
@@ -235,6 +333,14 @@ impl Coordinates {
                     only_b: wrap_strs(r.only_b),
                 }
             }
+            (Coordinates::Durations(a), Coordinates::Durations(b)) => {
+                let r = a.intersect(b);
+                IntersectionResult {
+                    intersection: wrap_durs(r.intersection),
+                    only_a: wrap_durs(r.only_a),
+                    only_b: wrap_durs(r.only_b),
+                }
+            }
             // Mixed on the left
             (Coordinates::Mixed(mixed), Coordinates::Strings(b)) => {
                 let r = mixed.strings.intersect(b);
@@ -245,6 +351,7 @@ impl Coordinates {
                         floats: mixed.floats.clone(),
                         strings: r.only_a,
                         datetimes: mixed.datetimes.clone(),
+                        durations: mixed.durations.clone(),
                     })),
                     only_b: wrap_strs(r.only_b),
                 }
@@ -258,12 +365,13 @@ impl Coordinates {
                         floats: mixed.floats.clone(),
                         strings: mixed.strings.clone(),
                         datetimes: mixed.datetimes.clone(),
+                        durations: mixed.durations.clone(),
                     })),
                     only_b: wrap_ints(r.only_b),
                 }
             }
             (Coordinates::Mixed(mixed), Coordinates::Floats(b)) => {
-                let r = mixed.floats.intersect(b);
+                let r = mixed.floats.intersect(b, tolerance);
                 IntersectionResult {
                     intersection: wrap_floats(r.intersection),
                     only_a: Coordinates::Mixed(Box::new(MixedCoordinates {
@@ -271,6 +379,7 @@ impl Coordinates {
                         floats: r.only_a,
                         strings: mixed.strings.clone(),
                         datetimes: mixed.datetimes.clone(),
+                        durations: mixed.durations.clone(),
                     })),
                     only_b: wrap_floats(r.only_b),
                 }
@@ -284,33 +393,52 @@ impl Coordinates {
                         floats: mixed.floats.clone(),
                         strings: mixed.strings.clone(),
                         datetimes: r.only_a,
+                        durations: mixed.durations.clone(),
                     })),
                     only_b: wrap_dts(r.only_b),
                 }
             }
+            (Coordinates::Mixed(mixed), Coordinates::Durations(b)) => {
+                let r = mixed.durations.intersect(b);
+                IntersectionResult {
+                    intersection: wrap_durs(r.intersection),
+                    only_a: Coordinates::Mixed(Box::new(MixedCoordinates {
+                        integers: mixed.integers.clone(),
+                        floats: mixed.floats.clone(),
+                        strings: mixed.strings.clone(),
+                        datetimes: mixed.datetimes.clone(),
+                        durations: r.only_a,
+                    })),
+                    only_b: wrap_durs(r.only_b),
+                }
+            }
             (Coordinates::Mixed(a), Coordinates::Mixed(b)) => {
                 let r_ints = a.integers.intersect(&b.integers);
-                let r_floats = a.floats.intersect(&b.floats);
+                let r_floats = a.floats.intersect(&b.floats, tolerance);
                 let r_strs = a.strings.intersect(&b.strings);
                 let r_dts = a.datetimes.intersect(&b.datetimes);
+                let r_durs = a.durations.intersect(&b.durations);
                 IntersectionResult {
                     intersection: Coordinates::Mixed(Box::new(MixedCoordinates {
                         integers: r_ints.intersection,
                         floats: r_floats.intersection,
                         strings: r_strs.intersection,
                         datetimes: r_dts.intersection,
+                        durations: r_durs.intersection,
                     })),
                     only_a: Coordinates::Mixed(Box::new(MixedCoordinates {
                         integers: r_ints.only_a,
                         floats: r_floats.only_a,
                         strings: r_strs.only_a,
                         datetimes: r_dts.only_a,
+                        durations: r_durs.only_a,
                     })),
                     only_b: Coordinates::Mixed(Box::new(MixedCoordinates {
                         integers: r_ints.only_b,
                         floats: r_floats.only_b,
                         strings: r_strs.only_b,
                         datetimes: r_dts.only_b,
+                        durations: r_durs.only_b,
                     })),
                 }
             }
@@ -325,6 +453,7 @@ impl Coordinates {
                         floats: mixed.floats.clone(),
                         strings: r.only_b,
                         datetimes: mixed.datetimes.clone(),
+                        durations: mixed.durations.clone(),
                     })),
                 }
             }
@@ -338,11 +467,12 @@ impl Coordinates {
                         floats: mixed.floats.clone(),
                         strings: mixed.strings.clone(),
                         datetimes: mixed.datetimes.clone(),
+                        durations: mixed.durations.clone(),
                     })),
                 }
             }
             (Coordinates::Floats(a), Coordinates::Mixed(mixed)) => {
-                let r = a.intersect(&mixed.floats);
+                let r = a.intersect(&mixed.floats, tolerance);
                 IntersectionResult {
                     intersection: wrap_floats(r.intersection),
                     only_a: wrap_floats(r.only_a),
@@ -351,6 +481,7 @@ impl Coordinates {
                         floats: r.only_b,
                         strings: mixed.strings.clone(),
                         datetimes: mixed.datetimes.clone(),
+                        durations: mixed.durations.clone(),
                     })),
                 }
             }
@@ -364,6 +495,21 @@ impl Coordinates {
                         floats: mixed.floats.clone(),
                         strings: mixed.strings.clone(),
                         datetimes: r.only_b,
+                        durations: mixed.durations.clone(),
+                    })),
+                }
+            }
+            (Coordinates::Durations(a), Coordinates::Mixed(mixed)) => {
+                let r = a.intersect(&mixed.durations);
+                IntersectionResult {
+                    intersection: wrap_durs(r.intersection),
+                    only_a: wrap_durs(r.only_a),
+                    only_b: Coordinates::Mixed(Box::new(MixedCoordinates {
+                        integers: mixed.integers.clone(),
+                        floats: mixed.floats.clone(),
+                        strings: mixed.strings.clone(),
+                        datetimes: mixed.datetimes.clone(),
+                        durations: r.only_b,
                     })),
                 }
             }
@@ -397,10 +543,14 @@ impl Coordinates {
                 mixed.floats.hash(hasher);
                 mixed.strings.hash(hasher);
                 mixed.datetimes.hash(hasher);
+                mixed.durations.hash(hasher);
             }
             Coordinates::DateTimes(datetimes) => {
                 datetimes.hash(hasher);
             }
+            Coordinates::Durations(durations) => {
+                durations.hash(hasher);
+            }
         }
     }
 }
@@ -425,6 +575,10 @@ fn wrap_dts(c: datetime::DateTimeCoordinates) -> Coordinates {
     if c.len() == 0 { Coordinates::Empty } else { Coordinates::DateTimes(c) }
 }
 
+fn wrap_durs(c: durations::DurationCoordinates) -> Coordinates {
+    if c.len() == 0 { Coordinates::Empty } else { Coordinates::Durations(c) }
+}
+
 fn wrap_floats(c: floats::FloatCoordinates) -> Coordinates {
     if c.len() == 0 { Coordinates::Empty } else { Coordinates::Floats(c) }
 }
@@ -502,12 +656,7 @@ impl Coordinates {
     pub fn iter_sorted_strings(&self) -> Vec<String> {
         match self {
             Coordinates::Empty => vec![],
-            Coordinates::Integers(ints) => match ints {
-                integers::IntegerCoordinates::Set(set) => {
-                    set.iter().map(|v| v.to_string()).collect()
-                }
-                integers::IntegerCoordinates::RangeSet(_) => vec![],
-            },
+            Coordinates::Integers(ints) => ints.iter().map(|v| v.to_string()).collect(),
             Coordinates::Strings(strings) => match strings {
                 strings::StringCoordinates::Set(set) => set.iter().map(|v| v.to_string()).collect(),
             },
@@ -516,15 +665,75 @@ impl Coordinates {
                     list.iter().map(|v| v.to_string()).collect()
                 }
             },
-            Coordinates::DateTimes(dts) => match dts {
-                datetime::DateTimeCoordinates::List(list) => {
-                    list.iter().map(|v| v.format("%Y%m%dT%H%M").to_string()).collect()
-                }
-            },
+            Coordinates::DateTimes(dts) => {
+                dts.iter().map(|v| v.format("%Y%m%dT%H%M").to_string()).collect()
+            }
+            Coordinates::Durations(durs) => durs.iter().map(|v| v.to_string()).collect(),
             Coordinates::Mixed(_) => vec![],
         }
     }
 
+    /// Return every individual coordinate value as a `String`, in stored order.
+    ///
+    /// Unlike [`Coordinates::to_string`], ranges are always expanded, so this is
+    /// the form to use when matching individual values.  `Mixed` lists integers,
+    /// floats, strings, date-times and durations in that order.
+    pub fn to_value_strings(&self) -> Vec<String> {
+        match self {
+            Coordinates::Empty => vec![],
+            Coordinates::Integers(ints) => ints.iter().map(|v| v.to_string()).collect(),
+            Coordinates::Floats(floats::FloatCoordinates::List(list)) => {
+                list.iter().map(|v| v.to_string()).collect()
+            }
+            Coordinates::Strings(strings::StringCoordinates::Set(set)) => {
+                set.iter().map(|v| v.to_string()).collect()
+            }
+            Coordinates::DateTimes(dts) => {
+                dts.iter().map(|v| v.format("%Y-%m-%dT%H:%M:%S").to_string()).collect()
+            }
+            Coordinates::Durations(durs) => durs.iter().map(|v| v.to_string()).collect(),
+            Coordinates::Mixed(mixed) => [
+                Coordinates::Integers(mixed.integers.clone()),
+                Coordinates::Floats(mixed.floats.clone()),
+                Coordinates::Strings(mixed.strings.clone()),
+                Coordinates::DateTimes(mixed.datetimes.clone()),
+                Coordinates::Durations(mixed.durations.clone()),
+            ]
+            .iter()
+            .flat_map(Coordinates::to_value_strings)
+            .collect(),
+        }
+    }
+
+    /// Iterates over every value, in the same order as [`Coordinates::to_value_strings`].
+    pub(crate) fn values(&self) -> Box<dyn Iterator<Item = CoordinateTypes> + '_> {
+        fn strs(
+            strings: &strings::StringCoordinates,
+        ) -> impl Iterator<Item = CoordinateTypes> + '_ {
+            let strings::StringCoordinates::Set(set) = strings;
+            set.iter().map(|v| CoordinateTypes::String(v.to_string()))
+        }
+
+        match self {
+            Coordinates::Empty => Box::new(std::iter::empty()),
+            Coordinates::Integers(ints) => Box::new(ints.iter().map(CoordinateTypes::Integer)),
+            Coordinates::Floats(floats) => Box::new(floats.iter().map(CoordinateTypes::Float)),
+            Coordinates::Strings(strings) => Box::new(strs(strings)),
+            Coordinates::DateTimes(dts) => Box::new(dts.iter().map(CoordinateTypes::DateTime)),
+            Coordinates::Durations(durs) => Box::new(durs.iter().map(CoordinateTypes::Duration)),
+            Coordinates::Mixed(mixed) => Box::new(
+                mixed
+                    .integers
+                    .iter()
+                    .map(CoordinateTypes::Integer)
+                    .chain(mixed.floats.iter().map(CoordinateTypes::Float))
+                    .chain(strs(&mixed.strings))
+                    .chain(mixed.datetimes.iter().map(CoordinateTypes::DateTime))
+                    .chain(mixed.durations.iter().map(CoordinateTypes::Duration)),
+            ),
+        }
+    }
+
     /// Return the 0-based sorted position of the coordinate whose string representation
     /// equals `value_str`, or `None` if not found.
     pub fn coord_index_of(&self, value_str: &str) -> Option<usize> {
@@ -534,8 +743,9 @@ impl Coordinates {
     /// Split this `Coordinates` into a `Vec` of single-value `Coordinates`, one per
     /// element in sorted coordinate order.
     ///
-    /// Only fully-enumerable variants are supported: `Integers(Set)`, `Strings(Set)`.
-    /// For `RangeSet`, `Mixed`, `DateTime`, `Floats`, and `Empty`, returns an empty `Vec`.
+    /// Only fully-enumerable variants are supported: `Integers`, `Strings(Set)` and
+    /// `Durations`.  For `Mixed`, `DateTime`, `Floats`, and `Empty`, returns an
+    /// empty `Vec`.
     ///
     /// Used by `partition_by_metadata` to align per-coordinate metadata values with
     /// the individual coordinates of a merged node.
@@ -546,12 +756,8 @@ impl Coordinates {
                 .into_iter()
                 .map(|s| Coordinates::from(s.as_str()))
                 .collect(),
-            Coordinates::Integers(integers::IntegerCoordinates::Set(_)) => self
-                .iter_sorted_strings()
-                .into_iter()
-                .filter_map(|s| s.parse::<i32>().ok())
-                .map(Coordinates::from)
-                .collect(),
+            Coordinates::Integers(ints) => ints.iter().map(Coordinates::from).collect(),
+            Coordinates::Durations(durs) => durs.iter().map(Coordinates::from).collect(),
             _ => vec![],
         }
     }
@@ -568,6 +774,7 @@ impl Coordinates {
                         set.iter().map(|v| Value::Number(Number::from(*v as i64))).collect();
                     Value::Array(vals)
                 }
+                // Ranges are written in their compact `a/to/b/by/c` form.
                 integers::IntegerCoordinates::RangeSet(_) => Value::String(ints.to_string()),
             },
             Coordinates::Floats(floats) => match floats {
@@ -593,19 +800,13 @@ impl Coordinates {
             Coordinates::Mixed(boxed) => {
                 let mut map = serde_json::Map::new();
 
-                match &boxed.integers {
-                    integers::IntegerCoordinates::Set(set) => {
-                        if set.len() > 0 {
-                            let vals: Vec<Value> = set
-                                .iter()
-                                .map(|v| Value::Number(Number::from(*v as i64)))
-                                .collect();
-                            map.insert("ints".to_string(), Value::Array(vals));
-                        }
-                    }
-                    integers::IntegerCoordinates::RangeSet(_) => {
-                        // fallback to textual form
-                    }
+                if boxed.integers.len() > 0 {
+                    let vals: Vec<Value> = boxed
+                        .integers
+                        .iter()
+                        .map(|v| Value::Number(Number::from(v as i64)))
+                        .collect();
+                    map.insert("ints".to_string(), Value::Array(vals));
                 }
 
                 match &boxed.floats {
@@ -634,34 +835,38 @@ impl Coordinates {
                     }
                 }
 
-                match &boxed.datetimes {
-                    datetime::DateTimeCoordinates::List(list) => {
-                        if list.len() > 0 {
-                            let vals: Vec<Value> = list
-                                .iter()
-                                .map(|dt: &NaiveDateTime| {
-                                    // Serialize NaiveDateTime as an ISO-like string without timezone.
-                                    Value::String(dt.format("%Y%m%dT%H%M").to_string())
-                                })
-                                .collect();
-                            map.insert("datetimes".to_string(), Value::Array(vals));
-                        }
-                    }
-                }
-
-                Value::Object(map)
-            }
-            Coordinates::DateTimes(coords) => match coords {
-                datetime::DateTimeCoordinates::List(list) => {
-                    let vals: Vec<Value> = list
+                if boxed.datetimes.len() > 0 {
+                    let vals: Vec<Value> = boxed
+                        .datetimes
                         .iter()
-                        .map(|dt: &NaiveDateTime| {
+                        .map(|dt: NaiveDateTime| {
+                            // Serialize NaiveDateTime as an ISO-like string without timezone.
                             Value::String(dt.format("%Y%m%dT%H%M").to_string())
                         })
                         .collect();
-                    Value::Array(vals)
+                    map.insert("datetimes".to_string(), Value::Array(vals));
                 }
-            },
+
+                if boxed.durations.len() > 0 {
+                    let vals: Vec<Value> =
+                        boxed.durations.iter().map(|d| Value::String(d.to_string())).collect();
+                    map.insert("durations".to_string(), Value::Array(vals));
+                }
+
+                Value::Object(map)
+            }
+            Coordinates::DateTimes(coords) => {
+                let vals: Vec<Value> = coords
+                    .iter()
+                    .map(|dt: NaiveDateTime| Value::String(dt.format("%Y%m%dT%H%M").to_string()))
+                    .collect();
+                Value::Array(vals)
+            }
+            // Written as strings, so readers need the `durations` key of the
+            // enclosing object to tell them apart from `Strings`.
+            Coordinates::Durations(durs) => {
+                Value::Array(durs.iter().map(|d| Value::String(d.to_string())).collect())
+            }
         }
     }
 
@@ -711,12 +916,10 @@ impl Coordinates {
 
                 if any_float {
                     let mut vec = floats::FloatCoordinates::default();
-                    if let floats::FloatCoordinates::List(list) = &mut vec {
-                        for v in arr.iter() {
-                            if let Value::Number(n) = v {
-                                if let Some(f) = n.as_f64() {
-                                    list.push(f);
-                                }
+                    for v in arr.iter() {
+                        if let Value::Number(n) = v {
+                            if let Some(f) = n.as_f64() {
+                                vec.append(f);
                             }
                         }
                     }
@@ -752,12 +955,10 @@ impl Coordinates {
 
                 if let Some(v) = map.get("floats") {
                     if let Value::Array(arr) = v {
-                        if let floats::FloatCoordinates::List(list) = &mut mixed.floats {
-                            for val in arr.iter() {
-                                if let Value::Number(n) = val {
-                                    if let Some(f) = n.as_f64() {
-                                        list.push(f);
-                                    }
+                        for val in arr.iter() {
+                            if let Value::Number(n) = val {
+                                if let Some(f) = n.as_f64() {
+                                    mixed.floats.append(f);
                                 }
                             }
                         }
@@ -774,6 +975,19 @@ impl Coordinates {
                     }
                 }
 
+                if let Some(Value::Array(arr)) = map.get("durations") {
+                    for val in arr.iter() {
+                        let s = val.as_str().ok_or("Expected string in 'durations'")?;
+                        let d = DurationValue::parse(s)
+                            .ok_or_else(|| format!("Invalid duration {s:?}"))?;
+                        mixed.durations.append(d);
+                    }
+                    // `{"durations": [...]}` on its own is how `Durations` are written.
+                    if map.len() == 1 {
+                        return Ok(Coordinates::Durations(mixed.durations));
+                    }
+                }
+
                 Ok(Coordinates::Mixed(Box::new(mixed)))
             }
             Value::Null => Ok(Coordinates::Empty),
@@ -1,9 +1,12 @@
 use crate::Coordinates;
 use crate::coordinates::CoordinateTypes;
+use crate::coordinates::durations::DurationValue;
 use crate::coordinates::integers::IntegerCoordinates;
+use crate::coordinates::ranges::int_to_date;
 use crate::coordinates::strings::StringCoordinates;
-use crate::utils::tiny_ordered_set::TinyOrderedSet;
-use chrono::NaiveDateTime;
+use chrono::{NaiveDateTime, NaiveTime};
+use std::cmp::Ordering;
+use std::fmt;
 
 impl From<NaiveDateTime> for CoordinateTypes {
     fn from(value: NaiveDateTime) -> Self {
@@ -99,11 +102,27 @@ impl Coordinates {
                     self.convert_to_mixed().extend(new_coords);
                 }
             },
+            Coordinates::Durations(new_durations) => match self {
+                Coordinates::Durations(durations) => {
+                    durations.extend(new_durations);
+                }
+                Coordinates::Mixed(mixed) => {
+                    mixed.durations.extend(new_durations);
+                }
+                Coordinates::Empty => {
+                    let _ = std::mem::replace(self, new_coords.clone());
+                }
+                _ => {
+                    self.convert_to_mixed().extend(new_coords);
+                }
+            },
             Coordinates::Mixed(mixed) => match self {
                 Coordinates::Mixed(self_mixed) => {
                     self_mixed.integers.extend(&mixed.integers);
                     self_mixed.floats.extend(&mixed.floats);
                     self_mixed.strings.extend(&mixed.strings);
+                    self_mixed.datetimes.extend(&mixed.datetimes);
+                    self_mixed.durations.extend(&mixed.durations);
                 }
                 _ => {
                     self.convert_to_mixed().extend(new_coords);
@@ -139,6 +158,9 @@ impl Coordinates {
             CoordinateTypes::DateTime(val) => {
                 self.append_datetime(val);
             }
+            CoordinateTypes::Duration(val) => {
+                self.append_duration(val);
+            }
         }
     }
 
@@ -213,6 +235,24 @@ impl Coordinates {
             }
         }
     }
+
+    fn append_duration(&mut self, value: DurationValue) {
+        match self {
+            Coordinates::Durations(durations) => {
+                durations.append(value);
+            }
+            Coordinates::Mixed(mixed) => {
+                mixed.durations.append(value);
+            }
+            Coordinates::Empty => {
+                *self = Coordinates::from(value);
+            }
+            _ => {
+                self.convert_to_mixed();
+                self.append_duration(value);
+            }
+        }
+    }
 }
 
 impl FromIterator<i32> for Coordinates {
@@ -257,19 +297,68 @@ impl From<f64> for CoordinateTypes {
     }
 }
 
+impl From<DurationValue> for CoordinateTypes {
+    fn from(val: DurationValue) -> Self {
+        CoordinateTypes::Duration(val)
+    }
+}
+
 impl From<String> for CoordinateTypes {
     fn from(val: String) -> Self {
         CoordinateTypes::String(val)
     }
 }
 
+impl From<&str> for CoordinateTypes {
+    fn from(val: &str) -> Self {
+        CoordinateTypes::String(val.to_owned())
+    }
+}
+
+impl CoordinateTypes {
+    /// Orders two single values.  Integers and floats compare numerically, and
+    /// a `YYYYMMDD` integer compares with a date-time as midnight on that day.
+    /// Returns `None` for values that cannot be compared, such as a string
+    /// and a number, or NaN.
+    pub fn compare(&self, other: &CoordinateTypes) -> Option<Ordering> {
+        use CoordinateTypes::*;
+
+        let midnight = |date: i32| int_to_date(date).map(|d| d.and_time(NaiveTime::MIN));
+        match (self, other) {
+            (Integer(a), Integer(b)) => Some(a.cmp(b)),
+            (Integer(a), Float(b)) => (*a as f64).partial_cmp(b),
+            (Float(a), Integer(b)) => a.partial_cmp(&(*b as f64)),
+            (Float(a), Float(b)) => a.partial_cmp(b),
+            (String(a), String(b)) => Some(a.cmp(b)),
+            (DateTime(a), DateTime(b)) => Some(a.cmp(b)),
+            (DateTime(a), Integer(b)) => midnight(*b).map(|b| a.cmp(&b)),
+            (Integer(a), DateTime(b)) => midnight(*a).map(|a| a.cmp(b)),
+            (Duration(a), Duration(b)) => Some(a.cmp(b)),
+            _ => None,
+        }
+    }
+}
+
+/// Formats the value as it appears in [`Coordinates::to_value_strings`].
+impl fmt::Display for CoordinateTypes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CoordinateTypes::Integer(v) => write!(f, "{}", v),
+            CoordinateTypes::Float(v) => write!(f, "{}", v),
+            CoordinateTypes::String(v) => write!(f, "{}", v),
+            CoordinateTypes::DateTime(v) => write!(f, "{}", v.format("%Y-%m-%dT%H:%M:%S")),
+            CoordinateTypes::Duration(v) => write!(f, "{}", v),
+        }
+    }
+}
+
 /// Attempt to convert all values in a StringCoordinates to integers.
 /// Returns Some(IntegerCoordinates) if every string parses as i32 and none
 /// have leading zeros (which would lose formatting information), None otherwise.
 fn try_strings_to_integers(strings: &StringCoordinates) -> Option<IntegerCoordinates> {
     match strings {
         StringCoordinates::Set(set) => {
-            let mut int_set: TinyOrderedSet<i32, 6> = TinyOrderedSet::new();
+            let mut ints = IntegerCoordinates::default();
             for s in set.iter() {
                 let s_str = s.to_string();
                 // Reject strings with leading zeros to preserve formatting
@@ -281,12 +370,12 @@ fn try_strings_to_integers(strings: &StringCoordinates) -> Option<IntegerCoordin
                 }
                 match s_str.parse::<i32>() {
                     Ok(val) => {
-                        int_set.insert(val);
+                        ints.append(val);
                     }
                     Err(_) => return None,
                 }
             }
-            Some(IntegerCoordinates::Set(int_set))
+            Some(ints)
         }
     }
 }
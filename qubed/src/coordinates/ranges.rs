@@ -0,0 +1,468 @@
+//! MARS-style range syntax for coordinate strings: `a/to/b` and `a/to/b/by/c`.
+//!
+//! Three kinds of range are understood:
+//!
+//! - Integer ranges, e.g. `0/to/240/by/6`.  `by` defaults to 1 and may be
+//!   negative when counting down (`10/to/1/by/-3`).
+//! - Date ranges, written with `YYYYMMDD` end points, e.g. `20240101/to/20241231`.
+//!   These step through calendar days (`by` is a number of days) and produce
+//!   integer coordinates, the same as listing the dates one by one would.
+//! - Date-time ranges with ISO 8601 end points, e.g.
+//!   `2024-01-01T00:00:00/to/2024-01-02T00:00:00/by/6h`.  `by` is a number of days,
+//!   or a number followed by `d`, `h`, `m` or `s`.  These produce `DateTimes`.
+//!
+//! It also holds the canonical range construction shared by integer and
+//! date-time coordinates, which store long evenly spaced runs as ranges.
+
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime};
+
+use crate::Coordinates;
+use crate::coordinates::datetime::DateTimeCoordinates;
+use crate::coordinates::integers::IntegerCoordinates;
+
+/// One element of a `/`-separated coordinate string.
+pub(crate) enum Item<'a> {
+    Single(&'a str),
+    Range(Coordinates),
+}
+
+/// Groups `tokens` into single values and ranges.  Tokens of a range that
+/// cannot be parsed are kept as single values.
+pub(crate) fn split_ranges<'a>(tokens: &[&'a str]) -> Vec<Item<'a>> {
+    let mut items = Vec::new();
+    let mut i = 0;
+
+    while i < tokens.len() {
+        let is_range = i + 2 < tokens.len() && tokens[i + 1].eq_ignore_ascii_case("to");
+        if is_range {
+            let has_by = i + 4 < tokens.len() && tokens[i + 3].eq_ignore_ascii_case("by");
+            let by = if has_by { Some(tokens[i + 4]) } else { None };
+            if let Some(range) = parse_range(tokens[i], tokens[i + 2], by) {
+                items.push(Item::Range(range));
+                i += if has_by { 5 } else { 3 };
+                continue;
+            }
+        }
+        items.push(Item::Single(tokens[i]));
+        i += 1;
+    }
+
+    items
+}
+
+fn parse_range(start: &str, end: &str, by: Option<&str>) -> Option<Coordinates> {
+    if let (Some(a), Some(b)) = (parse_plain_int(start), parse_plain_int(end)) {
+        return match (int_to_date(a), int_to_date(b)) {
+            (Some(a), Some(b)) => {
+                let step = by.map_or(Some(1), |s| s.parse::<i64>().ok())?;
+                date_range(a, b, step)
+            }
+            _ => {
+                let step = by.map_or(Some(1), |s| s.parse::<i64>().ok())?;
+                IntegerCoordinates::from_range(a, b, step).map(Coordinates::Integers)
+            }
+        };
+    }
+
+    let (a, b) = (parse_range_end(start)?, parse_range_end(end)?);
+    let step = by.map_or(Some(Duration::days(1)), parse_duration)?;
+    datetime_range(a, b, step)
+}
+
+fn date_range(start: NaiveDate, end: NaiveDate, step_days: i64) -> Option<Coordinates> {
+    if step_days == 0 || (end - start).num_days().signum() * step_days.signum() < 0 {
+        return None;
+    }
+    let mut ints = IntegerCoordinates::default();
+    let mut day = start;
+    while (step_days > 0 && day <= end) || (step_days < 0 && day >= end) {
+        ints.append(date_to_int(day));
+        day = day.checked_add_signed(Duration::days(step_days))?;
+    }
+    Some(Coordinates::Integers(ints))
+}
+
+fn datetime_range(start: NaiveDateTime, end: NaiveDateTime, step: Duration) -> Option<Coordinates> {
+    DateTimeCoordinates::from_range(start, end, step).map(Coordinates::DateTimes)
+}
+
+/// Parses an integer written without leading zeros, which would otherwise be lost.
+fn parse_plain_int(s: &str) -> Option<i32> {
+    let digits = s.strip_prefix('-').unwrap_or(s);
+    if digits.len() > 1 && digits.starts_with('0') {
+        return None;
+    }
+    s.parse().ok()
+}
+
+/// Reads an 8-digit integer as a `YYYYMMDD` calendar date.
+pub(crate) fn int_to_date(value: i32) -> Option<NaiveDate> {
+    if !(10_000_101..=99_991_231).contains(&value) {
+        return None;
+    }
+    NaiveDate::from_ymd_opt(value / 10_000, (value / 100 % 100) as u32, (value % 100) as u32)
+}
+
+pub(crate) fn date_to_int(date: NaiveDate) -> i32 {
+    use chrono::Datelike;
+    date.year() * 10_000 + date.month() as i32 * 100 + date.day() as i32
+}
+
+/// Parses the ISO 8601 form produced when printing a single date-time,
+/// `YYYY-MM-DDTHH:MM:SS`.  A bare `YYYY-MM-DD` is left alone, so that a date
+/// written without a time keeps its spelling.
+pub(crate) fn parse_iso_datetime(s: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S").ok()
+}
+
+/// Parses a date-time range end point, which is also printed as a bare
+/// `YYYY-MM-DD` when the range runs from midnight in whole days.
+fn parse_range_end(s: &str) -> Option<NaiveDateTime> {
+    parse_iso_datetime(s).or_else(|| {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").ok().map(|d| d.and_time(NaiveTime::MIN))
+    })
+}
+
+/// Parses a range step: a plain number of days, or a number with a `d`, `h`,
+/// `m` or `s` suffix.
+fn parse_duration(s: &str) -> Option<Duration> {
+    let (number, unit) = match s.find(|c: char| c.is_ascii_alphabetic()) {
+        Some(i) => s.split_at(i),
+        None => (s, "d"),
+    };
+    let n: i64 = number.parse().ok()?;
+    match unit {
+        "d" => Some(Duration::days(n)),
+        "h" => Some(Duration::hours(n)),
+        "m" => Some(Duration::minutes(n)),
+        "s" => Some(Duration::seconds(n)),
+        _ => None,
+    }
+}
+
+/// Formats a range step in the shortest unit that represents it exactly.
+/// Whole days are written without a unit, matching MARS date ranges.
+pub(crate) fn format_duration(step: Duration) -> String {
+    let seconds = step.num_seconds();
+    if seconds % 86_400 == 0 {
+        (seconds / 86_400).to_string()
+    } else if seconds % 3_600 == 0 {
+        format!("{}h", seconds / 3_600)
+    } else if seconds % 60 == 0 {
+        format!("{}m", seconds / 60)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+// ------------- Canonical construction ------------------
+
+/// Minimum number of evenly spaced values before they are stored as a range
+/// rather than one by one.
+pub(crate) const MIN_RANGE_LEN: usize = 8;
+
+/// A value type whose evenly spaced runs can be stored as ranges.  Distances
+/// are measured in the smallest unit two values can differ by.
+pub(crate) trait Grid: Copy + Default + PartialOrd {
+    type Step: Copy + Default + PartialEq;
+
+    /// The step given to values stored on their own.
+    fn unit() -> Self::Step;
+
+    /// `step` in the smallest unit.
+    fn units(step: Self::Step) -> i128;
+
+    /// `to - self` in the smallest unit.
+    fn distance(self, to: Self) -> i128;
+
+    /// Returns `self + n * step`.
+    fn advance(self, step: Self::Step, n: i128) -> Self;
+
+    /// The step from `self` to a larger `next`, or `None` if a range cannot
+    /// take it.
+    fn step_to(self, next: Self) -> Option<Self::Step>;
+}
+
+/// `count` values starting at `start`, `step` apart.
+#[derive(Clone, Copy)]
+pub(crate) struct Segment<T: Grid> {
+    pub(crate) start: T,
+    pub(crate) step: T::Step,
+    pub(crate) count: i128,
+}
+
+impl<T: Grid> Segment<T> {
+    pub(crate) fn single(value: T) -> Self {
+        Segment { start: value, step: T::unit(), count: 1 }
+    }
+
+    fn take(&self, n: i128) -> Segment<T> {
+        Segment { count: n, ..*self }
+    }
+
+    fn skip(&mut self, n: i128) {
+        self.start = self.start.advance(self.step, n);
+        self.count -= n;
+    }
+
+    /// Number of leading values strictly below `value`.
+    fn count_below(&self, value: T) -> i128 {
+        if value <= self.start {
+            0
+        } else {
+            ((self.start.distance(value) - 1) / T::units(self.step) + 1).min(self.count)
+        }
+    }
+}
+
+/// The values `start, start + step, ..., end`, as produced by [`RangeBuilder`].
+/// A value stored on its own has `start == end` and a step of [`Grid::unit`].
+pub(crate) struct Run<T: Grid> {
+    pub(crate) start: T,
+    pub(crate) end: T,
+    pub(crate) step: T::Step,
+}
+
+/// The canonical form of a strictly increasing sequence of values: the values
+/// themselves if none of their evenly spaced runs has [`MIN_RANGE_LEN`] or
+/// more values, and otherwise a run for every long run and every value outside
+/// one.
+pub(crate) enum Canonical<T: Grid> {
+    Values(Vec<T>),
+    Runs(Vec<Run<T>>),
+}
+
+/// Builds the [`Canonical`] form of a strictly increasing sequence of values,
+/// fed one value or one segment at a time.
+///
+/// The run being built is `start, start + step, ..., last` (`len` values).
+/// When a value does not extend it, a run of [`MIN_RANGE_LEN`] or more values
+/// becomes a range; a shorter run emits all but its last value as singles, and
+/// that last value becomes the start of the next run.
+pub(crate) struct RangeBuilder<T: Grid> {
+    runs: Vec<Run<T>>,
+    has_runs: bool,
+    start: T,
+    last: T,
+    step: T::Step,
+    len: usize,
+}
+
+impl<T: Grid> Default for RangeBuilder<T> {
+    fn default() -> Self {
+        RangeBuilder {
+            runs: Vec::new(),
+            has_runs: false,
+            start: T::default(),
+            last: T::default(),
+            step: T::Step::default(),
+            len: 0,
+        }
+    }
+}
+
+impl<T: Grid> RangeBuilder<T> {
+    pub(crate) fn push(&mut self, value: T) {
+        match self.len {
+            0 => {
+                self.start = value;
+                self.last = value;
+                self.len = 1;
+            }
+            1 => match self.last.step_to(value) {
+                Some(step) => {
+                    self.step = step;
+                    self.last = value;
+                    self.len = 2;
+                }
+                None => {
+                    self.push_single(self.start);
+                    self.start = value;
+                    self.last = value;
+                }
+            },
+            _ if self.last.step_to(value) == Some(self.step) => {
+                self.last = value;
+                self.len += 1;
+            }
+            _ => {
+                self.break_run();
+                self.push(value);
+            }
+        }
+    }
+
+    pub(crate) fn push_segment(&mut self, mut segment: Segment<T>) {
+        while segment.count > 0 {
+            // Fast path: the whole segment continues the current run.
+            if self.len >= 2
+                && segment.step == self.step
+                && segment.start == self.last.advance(self.step, 1)
+            {
+                self.last = segment.start.advance(segment.step, segment.count - 1);
+                self.len += segment.count as usize;
+                return;
+            }
+            self.push(segment.start);
+            segment.skip(1);
+        }
+    }
+
+    fn push_single(&mut self, value: T) {
+        self.runs.push(Run { start: value, end: value, step: T::unit() });
+    }
+
+    fn break_run(&mut self) {
+        if self.len >= MIN_RANGE_LEN {
+            self.runs.push(Run { start: self.start, end: self.last, step: self.step });
+            self.has_runs = true;
+            self.len = 0;
+        } else {
+            let mut v = self.start;
+            while v != self.last {
+                self.push_single(v);
+                v = v.advance(self.step, 1);
+            }
+            self.start = self.last;
+            self.len = 1;
+        }
+    }
+
+    pub(crate) fn finish(mut self) -> Canonical<T> {
+        if self.len > 0 {
+            self.break_run();
+            if self.len == 1 {
+                self.push_single(self.start);
+            }
+        }
+
+        if self.has_runs {
+            Canonical::Runs(self.runs)
+        } else {
+            Canonical::Values(self.runs.into_iter().map(|r| r.start).collect())
+        }
+    }
+}
+
+/// Whether sorted `window` contains an evenly spaced run of at least
+/// [`MIN_RANGE_LEN`] values.  Used after inserting a value, with the window
+/// holding its neighbours, since any such run must pass through it.
+pub(crate) fn has_long_run<T: Grid>(window: &[T]) -> bool {
+    let mut run = 1;
+    for i in 1..window.len() {
+        run = match window[i - 1].step_to(window[i]) {
+            None => 1,
+            Some(step) if i > 1 && window[i - 2].step_to(window[i - 1]) == Some(step) => run + 1,
+            Some(_) => 2,
+        };
+        if run >= MIN_RANGE_LEN {
+            return true;
+        }
+    }
+    false
+}
+
+pub(crate) enum Side {
+    OnlyA,
+    OnlyB,
+    Both,
+}
+
+/// Walks the ascending segments `a` and `b` together, reporting every segment
+/// of values as being only in `a`, only in `b`, or in both.
+///
+/// Whole segments are reported at once where possible, so walking two long
+/// ranges costs in proportion to the number of ranges, not values.
+pub(crate) fn walk<T: Grid>(
+    a: Vec<Segment<T>>,
+    b: Vec<Segment<T>>,
+    mut visit: impl FnMut(Side, Segment<T>),
+) {
+    let mut iter_a = a.into_iter();
+    let mut iter_b = b.into_iter();
+    let mut current_a = iter_a.next();
+    let mut current_b = iter_b.next();
+
+    loop {
+        match (&mut current_a, &mut current_b) {
+            (Some(x), Some(y)) => {
+                if x.start < y.start {
+                    let n = x.count_below(y.start);
+                    visit(Side::OnlyA, x.take(n));
+                    x.skip(n);
+                } else if y.start < x.start {
+                    let n = y.count_below(x.start);
+                    visit(Side::OnlyB, y.take(n));
+                    y.skip(n);
+                } else {
+                    let n = if x.step == y.step { x.count.min(y.count) } else { 1 };
+                    visit(Side::Both, x.take(n));
+                    x.skip(n);
+                    y.skip(n);
+                }
+            }
+            (Some(x), None) => {
+                visit(Side::OnlyA, *x);
+                x.count = 0;
+            }
+            (None, Some(y)) => {
+                visit(Side::OnlyB, *y);
+                y.count = 0;
+            }
+            (None, None) => break,
+        }
+
+        if current_a.as_ref().is_some_and(|s| s.count == 0) {
+            current_a = iter_a.next();
+        }
+        if current_b.as_ref().is_some_and(|s| s.count == 0) {
+            current_b = iter_b.next();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ints(values: impl IntoIterator<Item = i32>) -> Coordinates {
+        values.into_iter().collect()
+    }
+
+    #[test]
+    fn integer_ranges() {
+        assert_eq!(Coordinates::from_string("1/to/240/by/6"), ints((1..=240).step_by(6)));
+        assert_eq!(Coordinates::from_string("0/TO/10"), ints(0..=10));
+        assert_eq!(Coordinates::from_string("10/to/1/by/-3"), ints([1, 4, 7, 10]));
+        assert_eq!(Coordinates::from_string("1/to/3/7/to/9"), ints([1, 2, 3, 7, 8, 9]));
+    }
+
+    #[test]
+    fn date_ranges_follow_the_calendar() {
+        let dates = Coordinates::from_string("20240227/to/20240302");
+        assert_eq!(dates, ints([20240227, 20240228, 20240229, 20240301, 20240302]));
+
+        let weekly = Coordinates::from_string("20240101/to/20240131/by/7");
+        assert_eq!(weekly, ints([20240101, 20240108, 20240115, 20240122, 20240129]));
+    }
+
+    #[test]
+    fn datetime_ranges() {
+        let dts = Coordinates::from_string("2024-01-01T00:00:00/to/2024-01-01T18:00:00/by/6h");
+        assert_eq!(dts.len(), 4);
+        assert!(matches!(dts, Coordinates::DateTimes(_)));
+    }
+
+    #[test]
+    fn malformed_ranges_stay_strings() {
+        for s in ["1/to", "to/5", "1/to/5/by/0", "0001/to/0005", "1/to/x"] {
+            assert!(
+                matches!(
+                    Coordinates::from_string(s),
+                    Coordinates::Strings(_) | Coordinates::Mixed(_)
+                ),
+                "{s:?} should not parse as a range"
+            );
+        }
+    }
+}
@@ -30,23 +30,20 @@ impl Datacube {
 
 impl Qube {
     pub fn to_datacubes(&self) -> Vec<Datacube> {
-        let mut datacubes = Vec::new();
-
-        let datacube_paths = self.leaf_node_ids_paths();
-        for datacube_path in datacube_paths {
-            let mut datacube = Datacube::new();
-            for node_id in datacube_path {
-                if let Some(dim) = self.dimension_str(self.node_dim(node_id).unwrap()) {
-                    if let Some(coords) = self.node(node_id).map(|node| node.coordinates().clone())
-                    {
-                        datacube.add_coordinate(&dim, coords.clone());
-                    }
-                }
+        self.iter_datacubes().collect()
+    }
+
+    /// The datacube spanned by the nodes of `path`.
+    pub(crate) fn datacube_of_path(&self, path: &[NodeIdx]) -> Datacube {
+        let mut datacube = Datacube::new();
+        for &node_id in path {
+            if let Some(dim) = self.dimension_str(self.node_dim(node_id).unwrap())
+                && let Some(node) = self.node(node_id)
+            {
+                datacube.add_coordinate(dim, node.coordinates().clone());
             }
-            datacubes.push(datacube);
         }
-
-        datacubes
+        datacube
     }
 
     /// Build a Qube from a single Datacube.
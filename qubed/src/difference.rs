@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 
 use crate::coordinates::Coordinates;
+use crate::metadata::{Metadata, MetadataValues};
 use crate::{NodeIdx, Qube};
 
 impl Qube {
@@ -35,6 +36,7 @@ impl Qube {
     pub fn subtract(&self, other: &Qube) -> Qube {
         // Seed the result with a full copy of A.
         let mut result = Qube::new();
+        result.copy_float_tolerances(self);
         let result_root = result.root();
         let self_root = self.root();
         result.copy_subtree(self, self_root, result_root);
@@ -133,7 +135,8 @@ impl Qube {
                     continue;
                 }
 
-                let res = self_coords.intersect(&other_coords);
+                let res = self_coords
+                    .intersect_with_tolerance(&other_coords, self.float_tolerance(dim_str));
                 let only_self = res.only_a;
                 let intersection = res.intersection;
 
@@ -239,6 +242,286 @@ impl Qube {
     }
 }
 
+// ---------------------------------------------------------------------------
+// Symmetric difference
+// ---------------------------------------------------------------------------
+
+/// Value written under the provenance key for identifiers that only exist in `self`.
+pub const PROVENANCE_LEFT: &str = "left";
+/// Value written under the provenance key for identifiers that only exist in `other`.
+pub const PROVENANCE_RIGHT: &str = "right";
+
+impl Qube {
+    /// Computes the symmetric difference A △ B.
+    ///
+    /// Returns a new [`Qube`] containing every identifier present in exactly one
+    /// of `self` (A) and `other` (B).  Neither operand is consumed or modified.
+    ///
+    /// Both trees are walked together in a single pass.  At each dimension level
+    /// every A-node is intersected with every B-node of the same dimension:
+    ///
+    /// - **Only-A / only-B values** are copied into the result together with
+    ///   their full subtree.
+    /// - **Intersection values** are dropped when both nodes are leaves, and
+    ///   recursed into when both have children.  A leaf on one side and a deeper
+    ///   branch on the other are distinct identifiers (as in [`Qube::subtract`]),
+    ///   so both are kept.
+    ///
+    /// Each side keeps its own metadata.  The result is compressed before being
+    /// returned.
+    pub fn symmetric_difference(&self, other: &Qube) -> Qube {
+        self.symmetric_difference_impl(other, None)
+    }
+
+    /// Like [`Qube::symmetric_difference`], but additionally tags every surviving
+    /// identifier with the metadata key `key`, set to [`PROVENANCE_LEFT`] for
+    /// identifiers only in `self` and [`PROVENANCE_RIGHT`] for those only in `other`.
+    pub fn symmetric_difference_with_provenance(&self, other: &Qube, key: &str) -> Qube {
+        self.symmetric_difference_impl(other, Some(key))
+    }
+
+    fn symmetric_difference_impl(&self, other: &Qube, key: Option<&str>) -> Qube {
+        // Compressed copies guarantee that same-dimension siblings are
+        // non-overlapping, which the per-node remainder tracking relies on.
+        let mut a = self.clone();
+        a.compress();
+        let mut b = other.clone();
+        b.compress();
+
+        let tags = match key {
+            Some(k) => [
+                Metadata::single_key(k, MetadataValues::single_string(PROVENANCE_LEFT)),
+                Metadata::single_key(k, MetadataValues::single_string(PROVENANCE_RIGHT)),
+            ],
+            None => [Metadata::new(), Metadata::new()],
+        };
+
+        let (a_root, b_root) = (a.root(), b.root());
+        let inherited = [
+            a.get_node_metadata(a_root).cloned().unwrap_or_default(),
+            b.get_node_metadata(b_root).cloned().unwrap_or_default(),
+        ];
+
+        let mut result = Qube::new();
+        result.copy_float_tolerances(&a);
+        let result_root = result.root();
+        let walk = SymmetricWalk { a: &a, b: &b, tags: &tags };
+        walk.node_symmetric_difference(&mut result, a_root, b_root, result_root, &inherited);
+
+        result.compress();
+        result.deduplicate_metadata();
+        result
+    }
+}
+
+/// The two (compressed) operands of a symmetric difference and the provenance
+/// tags applied to nodes copied from each of them.
+struct SymmetricWalk<'a> {
+    a: &'a Qube,
+    b: &'a Qube,
+    tags: &'a [Metadata; 2],
+}
+
+impl SymmetricWalk<'_> {
+    /// Writes the symmetric difference of the A-subtree at `a_id` and the
+    /// B-subtree at `b_id` underneath `result_id`.
+    ///
+    /// `inherited` holds the effective metadata of the two paths leading to
+    /// `a_id` and `b_id`; it is attached to any one-sided subtree copied into
+    /// the result so that ancestor metadata is not lost or misattributed.
+    fn node_symmetric_difference(
+        &self,
+        result: &mut Qube,
+        a_id: NodeIdx,
+        b_id: NodeIdx,
+        result_id: NodeIdx,
+        inherited: &[Metadata; 2],
+    ) {
+        let a_kids = dim_kids_by_name(self.a, a_id);
+        let b_kids = dim_kids_by_name(self.b, b_id);
+
+        // Keep A's dimension order, then any dimensions only B has.
+        let mut dims: Vec<&str> = a_kids.iter().map(|(d, _)| d.as_str()).collect();
+        for (d, _) in &b_kids {
+            if !dims.contains(&d.as_str()) {
+                dims.push(d);
+            }
+        }
+
+        for dim_str in dims {
+            let lookup = |kids: &[(String, Vec<NodeIdx>)]| -> Vec<NodeIdx> {
+                kids.iter().find(|(d, _)| d == dim_str).map(|(_, k)| k.clone()).unwrap_or_default()
+            };
+            let a_group = lookup(&a_kids);
+            let b_group = lookup(&b_kids);
+            let tolerance = self.a.float_tolerance(dim_str);
+
+            let mut a_remaining: Vec<Coordinates> =
+                a_group.iter().map(|&id| self.a.node_ref(id).unwrap().coords().clone()).collect();
+            let mut b_remaining: Vec<Coordinates> =
+                b_group.iter().map(|&id| self.b.node_ref(id).unwrap().coords().clone()).collect();
+
+            for (i, &a_kid) in a_group.iter().enumerate() {
+                let a_node = self.a.node_ref(a_kid).unwrap();
+                let a_is_leaf = a_node.children().is_empty();
+
+                for (j, &b_kid) in b_group.iter().enumerate() {
+                    let b_node = self.b.node_ref(b_kid).unwrap();
+                    let b_is_leaf = b_node.children().is_empty();
+
+                    // Different schema depths: distinct identifiers, both sides survive.
+                    if a_is_leaf != b_is_leaf {
+                        continue;
+                    }
+
+                    let intersection = a_node
+                        .coords()
+                        .intersect_with_tolerance(b_node.coords(), tolerance)
+                        .intersection;
+                    if intersection.is_empty() {
+                        continue;
+                    }
+
+                    a_remaining[i] =
+                        a_remaining[i].intersect_with_tolerance(&intersection, tolerance).only_a;
+                    b_remaining[j] =
+                        b_remaining[j].intersect_with_tolerance(&intersection, tolerance).only_a;
+
+                    if a_is_leaf {
+                        // Common leaf values are in both operands: drop them.
+                        continue;
+                    }
+
+                    let (new_child, is_new) = result
+                        .get_or_create_child_matching(
+                            dim_str,
+                            result_id,
+                            intersection.clone(),
+                            false,
+                        )
+                        .unwrap();
+
+                    let child_inherited = [
+                        overlay(
+                            &inherited[0],
+                            &flattened(
+                                &a_node.metadata().restricted_to(a_node.coords(), &intersection),
+                            ),
+                        ),
+                        overlay(
+                            &inherited[1],
+                            &flattened(
+                                &b_node.metadata().restricted_to(b_node.coords(), &intersection),
+                            ),
+                        ),
+                    ];
+                    self.node_symmetric_difference(
+                        result,
+                        a_kid,
+                        b_kid,
+                        new_child,
+                        &child_inherited,
+                    );
+
+                    // Everything below was common to both sides: drop the placeholder.
+                    if is_new && result.node_ref(new_child).unwrap().children().is_empty() {
+                        result.remove_node(new_child).unwrap();
+                    }
+                }
+
+                // Whatever no B-node matched is only in A.
+                let remaining = std::mem::take(&mut a_remaining[i]);
+                copy_one_sided(
+                    result,
+                    self.a,
+                    a_kid,
+                    remaining,
+                    result_id,
+                    &inherited[0],
+                    &self.tags[0],
+                );
+            }
+
+            for (kid, remaining) in b_group.iter().zip(b_remaining) {
+                copy_one_sided(
+                    result,
+                    self.b,
+                    *kid,
+                    remaining,
+                    result_id,
+                    &inherited[1],
+                    &self.tags[1],
+                );
+            }
+        }
+    }
+}
+
+/// Children of `node_id` grouped by dimension name, in the node's dimension order.
+fn dim_kids_by_name(qube: &Qube, node_id: NodeIdx) -> Vec<(String, Vec<NodeIdx>)> {
+    let children = qube.node_ref(node_id).unwrap().children();
+    children
+        .iter()
+        .filter_map(|(dim, kids)| {
+            qube.dimension_str(dim).map(|s| (s.to_owned(), kids.iter().copied().collect()))
+        })
+        .collect()
+}
+
+/// Copies `source_id` (restricted to `coords`) and its subtree from `source` under
+/// `result_parent`, attaching the inherited path metadata and the provenance tag.
+fn copy_one_sided(
+    result: &mut Qube,
+    source: &Qube,
+    source_id: NodeIdx,
+    coords: Coordinates,
+    result_parent: NodeIdx,
+    inherited: &Metadata,
+    tag: &Metadata,
+) {
+    if coords.is_empty() {
+        return;
+    }
+    let node = source.node_ref(source_id).unwrap();
+    let dim_str = source.dimension_str(node.dim()).unwrap();
+    let own = node.metadata().restricted_to(node.coords(), &coords);
+    let meta = overlay(&overlay(inherited, &own), tag);
+
+    let is_leaf = node.children().is_empty();
+    let (new_child, _) =
+        result.get_or_create_child_matching(dim_str, result_parent, coords, is_leaf).unwrap();
+    let merged = result.node_ref(new_child).unwrap().metadata().merge_with(&meta);
+    *result.node_mut(new_child).unwrap().metadata_mut() = merged;
+    result.copy_subtree(source, source_id, new_child);
+}
+
+/// Returns `base` with every key in `top` replaced by `top`'s value, matching the
+/// child-overrides-ancestor rule of [`Qube::resolve_all_metadata`].
+fn overlay(base: &Metadata, top: &Metadata) -> Metadata {
+    let mut result = base.clone();
+    for (k, v) in top.iter() {
+        result.set(k.clone(), v.clone());
+    }
+    result
+}
+
+/// Replaces `PerCoordStrings` values with the flat union of their strings, so the
+/// metadata can be attached to a node with different coordinates.
+fn flattened(meta: &Metadata) -> Metadata {
+    let mut result = Metadata::new();
+    for (k, v) in meta.iter() {
+        let v = if v.is_per_coord_strings() {
+            let all = v.as_string_vec();
+            let refs: Vec<&str> = all.iter().map(|s| s.as_str()).collect();
+            MetadataValues::from_strings(&refs)
+        } else {
+            v.clone()
+        };
+        result.set(k.clone(), v);
+    }
+    result
+}
+
 // ---------------------------------------------------------------------------
 // Trait impl: `&a - &b`
 // ---------------------------------------------------------------------------
@@ -249,3 +532,14 @@ impl std::ops::Sub for &Qube {
         self.subtract(rhs)
     }
 }
+
+// ---------------------------------------------------------------------------
+// Trait impl: `&a ^ &b`
+// ---------------------------------------------------------------------------
+
+impl std::ops::BitXor for &Qube {
+    type Output = Qube;
+    fn bitxor(self, rhs: Self) -> Qube {
+        self.symmetric_difference(rhs)
+    }
+}
@@ -0,0 +1,391 @@
+use crate::select::Selector;
+use crate::{CoordinateTypes, Coordinates, NodeIdx, Qube};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+
+/// Why [`Qube::select`] kept what it kept, see [`Qube::explain_select`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelectExplanation {
+    /// One entry per selected dimension, in the order of the selection.
+    pub dimensions: Vec<DimensionExplanation>,
+    /// When the selection is empty, the depth of the nodes where the last
+    /// surviving branch was cut; the root's children are at depth 1.  `None`
+    /// if the selection keeps something, or the Qube is empty.
+    pub cut_depth: Option<usize>,
+    /// The selected dimensions that cut branches at `cut_depth`.
+    pub cut_by: Vec<String>,
+}
+
+/// How one selected dimension matched the branches that reached it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DimensionExplanation {
+    pub dimension: String,
+    /// What was asked for.
+    pub requested: Selector,
+    /// The values of this dimension on the branches the rest of the selection
+    /// let through.  Empty if no branch got as far as this dimension.
+    pub present: Coordinates,
+    /// The subset of `present` that the selector matched.
+    pub matched: Coordinates,
+    /// The number of nodes of this dimension where nothing matched, each of
+    /// which cuts its whole branch.
+    pub cut_branches: usize,
+    /// Whether any node of the Qube has this dimension.  If not, the selection
+    /// of it constrains nothing, which usually means the key is misspelt.
+    pub known: bool,
+    /// For a dimension the Qube lacks, its dimensions with the closest names;
+    /// otherwise the present values closest to each requested string that
+    /// was not found.
+    pub suggestions: Vec<String>,
+}
+
+impl SelectExplanation {
+    /// Whether the selection keeps nothing.
+    pub fn is_empty(&self) -> bool {
+        self.cut_depth.is_some()
+    }
+}
+
+#[derive(Default)]
+struct DimensionStats {
+    present: Coordinates,
+    matched: Coordinates,
+    cut_branches: usize,
+}
+
+/// Follows [`Qube::select`] through the Qube, recording what each selected
+/// dimension saw and where branches were cut.
+struct ExplainWalk<'a> {
+    qube: &'a Qube,
+    selection: &'a HashMap<&'a str, Selector>,
+    stats: HashMap<&'a str, DimensionStats>,
+    cuts: Vec<(usize, &'a str)>,
+    deepest: usize,
+    reached_leaf: bool,
+}
+
+impl<'a> ExplainWalk<'a> {
+    fn walk(&mut self, id: NodeIdx, depth: usize) -> Result<(), String> {
+        let node = self.qube.node_ref(id).ok_or_else(|| format!("Node {:?} not found", id))?;
+        self.deepest = self.deepest.max(depth);
+        let children: Vec<NodeIdx> = node.children().values().flatten().copied().collect();
+        if children.is_empty() {
+            self.reached_leaf |= depth > 0;
+            return Ok(());
+        }
+
+        for child_id in children {
+            let child = self
+                .qube
+                .node_ref(child_id)
+                .ok_or_else(|| format!("Child node {:?} not found", child_id))?;
+            let dimension = self.qube.dimension_str(child.dim()).ok_or_else(|| {
+                format!("Dimension {:?} not found in key store. Should not happen.", child.dim())
+            })?;
+
+            if let Some(selected) = self.selection.get(dimension) {
+                let tolerance = self.qube.float_tolerance(dimension);
+                let stats = self.stats.entry(dimension).or_default();
+                let mut any = false;
+                for value in child.coords().values() {
+                    if selected.matches(&value, tolerance) {
+                        stats.matched.append(value.clone());
+                        any = true;
+                    }
+                    stats.present.append(value);
+                }
+                if !any {
+                    stats.cut_branches += 1;
+                    self.cuts.push((depth + 1, dimension));
+                    continue;
+                }
+            }
+
+            self.walk(child_id, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl Qube {
+    /// Explains the result of [`Qube::select`] with `selection` in the default
+    /// mode: for each selected dimension, the values asked for, the values
+    /// present on the branches that reached it and which of those matched, and,
+    /// if nothing is kept, the depth at which the last branch was cut.
+    /// Requested strings that are not present come with the nearest present
+    /// values as suggestions, and misspelt dimensions with the nearest
+    /// dimension names.
+    pub fn explain_select<C>(&self, selection: &[(&str, C)]) -> Result<SelectExplanation, String>
+    where
+        C: Into<Selector> + Clone,
+    {
+        let selectors: HashMap<&str, Selector> =
+            selection.iter().map(|(k, v)| (*k, v.clone().into().for_dimension(k))).collect();
+
+        let mut walk = ExplainWalk {
+            qube: self,
+            selection: &selectors,
+            stats: HashMap::new(),
+            cuts: Vec::new(),
+            deepest: 0,
+            reached_leaf: false,
+        };
+        walk.walk(self.root(), 0)?;
+
+        let root_dim = self.node_ref(self.root()).and_then(|root| self.dimension_str(root.dim()));
+        let known: BTreeSet<&str> = self
+            .all_dim_ids()
+            .iter()
+            .filter_map(|d| self.dimension_str(d))
+            .filter(|&d| Some(d) != root_dim)
+            .collect();
+
+        let mut dimensions = Vec::new();
+        let mut seen = BTreeSet::new();
+        for (dimension, _) in selection {
+            if !seen.insert(*dimension) {
+                continue;
+            }
+            let requested = selectors[dimension].clone();
+            let stats = walk.stats.remove(dimension).unwrap_or_default();
+            let is_known = known.contains(dimension);
+
+            let suggestions = if is_known {
+                let present: Vec<String> = stats
+                    .present
+                    .values()
+                    .filter_map(|v| match v {
+                        CoordinateTypes::String(s) => Some(s),
+                        _ => None,
+                    })
+                    .collect();
+                let mut suggestions = Vec::new();
+                for missing in requested_strings(&requested) {
+                    if present.contains(&missing) {
+                        continue;
+                    }
+                    for s in nearest(&missing, present.iter().map(String::as_str)) {
+                        if !suggestions.contains(&s) {
+                            suggestions.push(s);
+                        }
+                    }
+                }
+                suggestions
+            } else {
+                nearest(dimension, known.iter().copied())
+            };
+
+            dimensions.push(DimensionExplanation {
+                dimension: dimension.to_string(),
+                requested,
+                present: stats.present,
+                matched: stats.matched,
+                cut_branches: stats.cut_branches,
+                known: is_known,
+                suggestions,
+            });
+        }
+
+        let cut_depth =
+            if walk.reached_leaf || self.is_empty() { None } else { Some(walk.deepest + 1) };
+        let mut cut_by: Vec<String> = walk
+            .cuts
+            .iter()
+            .filter(|(depth, _)| Some(*depth) == cut_depth)
+            .map(|(_, dim)| dim.to_string())
+            .collect();
+        cut_by.sort();
+        cut_by.dedup();
+
+        Ok(SelectExplanation { dimensions, cut_depth, cut_by })
+    }
+}
+
+/// The plain strings a selector asks for, which are the ones worth correcting.
+fn requested_strings(selector: &Selector) -> Vec<String> {
+    match selector {
+        Selector::Values(coords) => coords
+            .values()
+            .filter_map(|v| match v {
+                CoordinateTypes::String(s) => Some(s),
+                _ => None,
+            })
+            .collect(),
+        Selector::AnyOf(selectors) => selectors.iter().flat_map(requested_strings).collect(),
+        _ => vec![],
+    }
+}
+
+/// Up to three of the `candidates` closest to `target` by edit distance,
+/// ignoring case.  Candidates that differ in more than about a third of their
+/// characters are never suggested.
+fn nearest<'c>(target: &str, candidates: impl Iterator<Item = &'c str>) -> Vec<String> {
+    let target_lower = target.to_lowercase();
+    let mut scored: Vec<(usize, &str)> = candidates
+        .filter_map(|candidate| {
+            let distance = edit_distance(&target_lower, &candidate.to_lowercase());
+            let limit = target.chars().count().max(candidate.chars().count()).div_ceil(3);
+            (distance <= limit.max(1)).then_some((distance, candidate))
+        })
+        .collect();
+    scored.sort();
+    scored.dedup();
+    let best = scored.first().map(|(distance, _)| *distance);
+    scored
+        .into_iter()
+        .take_while(|(distance, _)| Some(*distance) == best)
+        .take(3)
+        .map(|(_, candidate)| candidate.to_string())
+        .collect()
+}
+
+/// Levenshtein distance between `a` and `b`, counted in characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, &cb) in b.iter().enumerate() {
+            let substitute = previous[j] + usize::from(ca != cb);
+            current.push(substitute.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+// -------------------------
+// Display
+// -------------------------
+
+/// One line per dimension, followed by where the selection was cut, e.g.
+///
+/// ```text
+/// class: requested od, present od/rd, matched od
+/// param: requested 999, present 1/2/3, matched nothing
+/// nothing selected: every branch was cut at depth 2 by param
+/// ```
+impl fmt::Display for SelectExplanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for dim in &self.dimensions {
+            write!(f, "{}: requested {}", dim.dimension, dim.requested)?;
+            if !dim.known {
+                write!(f, ", but no node has this dimension")?;
+            } else if dim.present.is_empty() {
+                write!(f, ", but no branch reached this dimension")?;
+            } else if dim.matched.is_empty() {
+                write!(f, ", present {}, matched nothing", dim.present.to_string())?;
+            } else {
+                write!(
+                    f,
+                    ", present {}, matched {}",
+                    dim.present.to_string(),
+                    dim.matched.to_string()
+                )?;
+            }
+            if !dim.suggestions.is_empty() {
+                write!(f, " (did you mean {}?)", dim.suggestions.join(", "))?;
+            }
+            writeln!(f)?;
+        }
+        if let Some(depth) = self.cut_depth {
+            write!(f, "nothing selected: every branch was cut at depth {}", depth)?;
+            if !self.cut_by.is_empty() {
+                write!(f, " by {}", self.cut_by.join(", "))?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SelectMode;
+
+    fn example() -> Qube {
+        let input = r#"root
+├── class=od
+│   ├── expver=0001
+│   │   └── param=1/2
+│   └── expver=0002
+│       └── param=3
+└── class=rd
+    └── expver=0001
+        └── param=4"#;
+        Qube::from_ascii(input).unwrap()
+    }
+
+    #[test]
+    fn test_explain_values_present_on_reached_branches() -> Result<(), String> {
+        let qube = example();
+        let explanation = qube.explain_select(&[
+            ("class", Selector::from("od")),
+            ("param", Selector::from(&[2, 4])),
+        ])?;
+
+        assert_eq!(explanation.cut_depth, None);
+        assert!(!explanation.is_empty());
+
+        let class = &explanation.dimensions[0];
+        assert_eq!(class.present.to_string(), "od/rd");
+        assert_eq!(class.matched.to_string(), "od");
+        assert_eq!(class.cut_branches, 1);
+
+        // param=4 is only under class=rd, which the class selection cut.
+        let param = &explanation.dimensions[1];
+        assert_eq!(param.present.to_string(), "1/2/3");
+        assert_eq!(param.matched.to_string(), "2");
+        assert_eq!(param.cut_branches, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_explain_empty_selection() -> Result<(), String> {
+        let qube = example();
+        let explanation = qube.explain_select(&[
+            ("class", Selector::from("od")),
+            ("expver", Selector::from("0003")),
+        ])?;
+        assert!(explanation.is_empty());
+        assert_eq!(explanation.cut_depth, Some(2));
+        assert_eq!(explanation.cut_by, vec!["expver"]);
+        assert!(
+            qube.select(&[("class", "od"), ("expver", "0003")], SelectMode::Default)?.is_empty()
+        );
+
+        let expver = &explanation.dimensions[1];
+        assert_eq!(expver.matched, Coordinates::Empty);
+        assert_eq!(expver.suggestions, vec!["0001", "0002"]);
+
+        let text = explanation.to_string();
+        assert!(text.contains("expver: requested 0003, present 0001/0002, matched nothing"));
+        assert!(text.contains("cut at depth 2 by expver"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_explain_suggests_close_strings_and_dimensions() -> Result<(), String> {
+        let qube = example();
+        let explanation =
+            qube.explain_select(&[("class", Selector::from("OD")), ("parm", Selector::from(1))])?;
+
+        let class = &explanation.dimensions[0];
+        assert_eq!(class.suggestions, vec!["od"]);
+
+        let parm = &explanation.dimensions[1];
+        assert!(!parm.known);
+        assert_eq!(parm.suggestions, vec!["param"]);
+        assert!(explanation.to_string().contains("no node has this dimension"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("parm", "param"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(nearest("xyz", ["od", "rd"].into_iter()), Vec::<String>::new());
+    }
+}
@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+
+use crate::{NodeIdx, Qube};
+
+impl Qube {
+    /// Computes the set intersection A ∩ B.
+    ///
+    /// Returns a new [`Qube`] containing every identifier present in both `self` (A)
+    /// and `other` (B).  Neither operand is consumed or modified.
+    ///
+    /// # Semantics
+    ///
+    /// Both trees are walked together.  At each dimension level every A-node is
+    /// intersected with every B-node of the same dimension using
+    /// [`Coordinates::intersect`](crate::Coordinates::intersect):
+    ///
+    /// - *Both are leaves*: the intersection values are kept as a leaf.
+    /// - *Both have children*: the operation recurses into the two subtrees for the
+    ///   intersection values.  The node is kept only if the recursion found at least
+    ///   one common identifier underneath it.
+    /// - *One is a leaf, the other has children*: the schemas differ, so the
+    ///   identifiers are distinct (the same convention as [`Qube::subtract`]) and
+    ///   nothing is kept.
+    ///
+    /// Metadata from both sides is merged with [`Metadata::merge_with`], as
+    /// [`Qube::append`] does, after restricting per-coordinate metadata to the
+    /// surviving values.  The result is compressed before being returned.
+    ///
+    /// [`Metadata::merge_with`]: crate::Metadata::merge_with
+    pub fn intersect(&self, other: &Qube) -> Qube {
+        let mut result = Qube::new();
+        result.copy_float_tolerances(self);
+        if self.is_empty() || other.is_empty() {
+            return result;
+        }
+
+        let self_root = self.root();
+        let other_root = other.root();
+        let result_root = result.root();
+
+        let self_meta = self.get_node_metadata(self_root).cloned().unwrap_or_default();
+        let other_meta = other.get_node_metadata(other_root).cloned().unwrap_or_default();
+        *result.node_mut(result_root).unwrap().metadata_mut() = self_meta.merge_with(&other_meta);
+
+        self.node_intersect(other, &mut result, self_root, other_root, result_root);
+
+        result.compress();
+        result.deduplicate_metadata();
+        result
+    }
+
+    /// Writes the intersection of the A-subtree at `self_id` and the B-subtree at
+    /// `other_id` underneath `result_id` in `result`.
+    fn node_intersect(
+        &self,
+        other: &Qube,
+        result: &mut Qube,
+        self_id: NodeIdx,
+        other_id: NodeIdx,
+        result_id: NodeIdx,
+    ) {
+        let self_children = self.node_ref(self_id).unwrap().children().clone();
+        let other_children = other.node_ref(other_id).unwrap().children().clone();
+
+        // Key by string: the two Qubes may have independent interner tables.
+        let mut other_dim_kids: HashMap<&str, Vec<NodeIdx>> = HashMap::new();
+        for (dim, kids) in &other_children {
+            if let Some(s) = other.dimension_str(dim) {
+                other_dim_kids.entry(s).or_default().extend(kids);
+            }
+        }
+
+        for (self_dim, self_kids) in &self_children {
+            let dim_str = match self.dimension_str(self_dim) {
+                Some(s) => s,
+                None => continue,
+            };
+            let other_kids = match other_dim_kids.get(dim_str) {
+                Some(kids) => kids,
+                None => continue, // B has no node for this dimension here.
+            };
+
+            for &self_kid in self_kids {
+                let self_node = self.node_ref(self_kid).unwrap();
+                let self_is_leaf = self_node.children().is_empty();
+
+                for &other_kid in other_kids {
+                    let other_node = other.node_ref(other_kid).unwrap();
+                    let other_is_leaf = other_node.children().is_empty();
+
+                    // Leaf on one side only: different schema depths, no shared identifiers.
+                    if self_is_leaf != other_is_leaf {
+                        continue;
+                    }
+
+                    let intersection = self_node
+                        .coords()
+                        .intersect_with_tolerance(
+                            other_node.coords(),
+                            self.float_tolerance(dim_str),
+                        )
+                        .intersection;
+                    if intersection.is_empty() {
+                        continue;
+                    }
+
+                    let meta = self_node
+                        .metadata()
+                        .restricted_to(self_node.coords(), &intersection)
+                        .merge_with(
+                            &other_node
+                                .metadata()
+                                .restricted_to(other_node.coords(), &intersection),
+                        );
+
+                    let (new_child, is_new) = result
+                        .get_or_create_child_matching(
+                            dim_str,
+                            result_id,
+                            intersection,
+                            self_is_leaf,
+                        )
+                        .unwrap();
+                    let merged = result.node_ref(new_child).unwrap().metadata().merge_with(&meta);
+                    *result.node_mut(new_child).unwrap().metadata_mut() = merged;
+
+                    if self_is_leaf {
+                        continue;
+                    }
+
+                    self.node_intersect(other, result, self_kid, other_kid, new_child);
+
+                    // Nothing in common below this node: drop the placeholder.
+                    if is_new && result.node_ref(new_child).unwrap().children().is_empty() {
+                        result.remove_node(new_child).unwrap();
+                    }
+                }
+            }
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Trait impl: `&a & &b`
+// ---------------------------------------------------------------------------
+
+impl std::ops::BitAnd for &Qube {
+    type Output = Qube;
+    fn bitand(self, rhs: Self) -> Qube {
+        self.intersect(rhs)
+    }
+}
@@ -0,0 +1,446 @@
+use crate::{CoordinateTypes, Datacube, Dimension, NodeIdx, Qube};
+use std::collections::{BTreeSet, HashMap};
+
+/// One fully expanded identifier: a value for each dimension on its leaf
+/// path, in path order.
+pub type Identifier<'q> = Vec<(&'q str, CoordinateTypes)>;
+
+/// A depth-first walk over the leaf paths of a Qube that produces one path at
+/// a time, unlike [`Qube::leaf_node_ids_paths`] which collects them all.
+///
+/// The cursor holds no borrow of the Qube, which is passed to each call, so
+/// it can be kept alongside a Qube it does not own, as the Python bindings do.
+/// If the Qube changes between calls, the walk stops at the first node that
+/// no longer exists.
+#[derive(Debug, Clone)]
+pub struct LeafCursor {
+    /// The nodes from the root down to the current one, each with its
+    /// children and the position of the next child to visit.
+    stack: Vec<(NodeIdx, Vec<NodeIdx>, usize)>,
+    started: bool,
+}
+
+impl LeafCursor {
+    pub fn new() -> Self {
+        LeafCursor { stack: Vec::new(), started: false }
+    }
+
+    /// The next path from the root to a leaf.  A Qube with no children has a
+    /// single path holding just the root.
+    pub fn next_path(&mut self, qube: &Qube) -> Option<Vec<NodeIdx>> {
+        if !self.started {
+            self.started = true;
+            let root = qube.root();
+            let children = children_of(qube, root)?;
+            if children.is_empty() {
+                return Some(vec![root]);
+            }
+            self.stack.push((root, children, 0));
+        }
+
+        loop {
+            let (_, children, next) = self.stack.last_mut()?;
+            let Some(&child) = children.get(*next) else {
+                self.stack.pop();
+                continue;
+            };
+            *next += 1;
+
+            let Some(grandchildren) = children_of(qube, child) else {
+                self.stack.clear();
+                return None;
+            };
+            if grandchildren.is_empty() {
+                let mut path: Vec<NodeIdx> = self.stack.iter().map(|(id, _, _)| *id).collect();
+                path.push(child);
+                return Some(path);
+            }
+            self.stack.push((child, grandchildren, 0));
+        }
+    }
+
+    /// The datacube of the next leaf path, as in [`Qube::to_datacubes`].
+    pub fn next_datacube(&mut self, qube: &Qube) -> Option<Datacube> {
+        self.next_path(qube).map(|path| qube.datacube_of_path(&path))
+    }
+}
+
+impl Default for LeafCursor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Enumerates the identifiers of a Qube one at a time: for each leaf path,
+/// every combination of one value per node on it, the last dimension
+/// varying fastest.  Like [`LeafCursor`], it is handed the Qube on each call.
+#[derive(Debug, Clone, Default)]
+pub struct IdentifierCursor {
+    leaves: LeafCursor,
+    /// The dimensions and values of the nodes on the current path, below the root.
+    dims: Vec<Dimension>,
+    values: Vec<Vec<CoordinateTypes>>,
+    /// The index into `values` of the next identifier, or `None` once the
+    /// current path is used up.
+    position: Option<Vec<usize>>,
+}
+
+impl IdentifierCursor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A cursor whose first identifier is the one at `offset` in the order
+    /// of [`Qube::iter_identifiers`].  Whole subtrees before it are skipped
+    /// using their identifier counts, so this costs one pass over the nodes
+    /// rather than enumerating `offset` identifiers.
+    pub fn starting_at(qube: &Qube, offset: u128) -> Self {
+        Self::seek(qube, &suffix_counts(qube), offset)
+    }
+
+    fn seek(qube: &Qube, suffixes: &HashMap<NodeIdx, u128>, offset: u128) -> Self {
+        let mut cursor = IdentifierCursor::new();
+        cursor.leaves.started = true;
+
+        // Descend to the leaf path holding `offset`, leaving the stack as
+        // `LeafCursor::next_path` would after returning that path.
+        let mut remaining = offset;
+        let mut prefix: u128 = 1;
+        let mut id = qube.root();
+        loop {
+            let Some(children) = children_of(qube, id) else { return cursor };
+            if children.is_empty() {
+                break;
+            }
+            let mut chosen = None;
+            for (i, &child) in children.iter().enumerate() {
+                let values = qube.node_ref(child).map_or(0, |node| node.coords().len() as u128);
+                let size = prefix
+                    .saturating_mul(values)
+                    .saturating_mul(suffixes.get(&child).copied().unwrap_or(0));
+                if remaining < size {
+                    chosen = Some((i, child, values));
+                    break;
+                }
+                remaining -= size;
+            }
+            let Some((i, child, values)) = chosen else {
+                cursor.leaves.stack.clear();
+                return cursor;
+            };
+            cursor.leaves.stack.push((id, children, i + 1));
+            prefix = prefix.saturating_mul(values);
+            id = child;
+        }
+        if id == qube.root() {
+            return cursor;
+        }
+
+        for (ancestor, _, _) in cursor.leaves.stack.iter().skip(1) {
+            let Some(node) = qube.node_ref(*ancestor) else { return cursor };
+            cursor.dims.push(*node.dim());
+            cursor.values.push(node.coords().values().collect());
+        }
+        let Some(leaf) = qube.node_ref(id) else { return cursor };
+        cursor.dims.push(*leaf.dim());
+        cursor.values.push(leaf.coords().values().collect());
+
+        // The position within the path, the last dimension varying fastest.
+        let mut position = vec![0; cursor.values.len()];
+        for (i, values) in position.iter_mut().zip(&cursor.values).rev() {
+            let len = values.len() as u128;
+            *i = (remaining % len) as usize;
+            remaining /= len;
+        }
+        cursor.position = Some(position);
+        cursor
+    }
+
+    /// The next identifier, as `(dimension, value)` pairs in path order.
+    pub fn next_identifier<'q>(&mut self, qube: &'q Qube) -> Option<Identifier<'q>> {
+        loop {
+            if let Some(position) = &mut self.position {
+                let identifier = self
+                    .dims
+                    .iter()
+                    .zip(&self.values)
+                    .zip(position.iter())
+                    .map(|((dim, values), &i)| {
+                        (qube.dimension_str(dim).unwrap_or_default(), values[i].clone())
+                    })
+                    .collect();
+
+                // Advance like an odometer, the last dimension fastest.
+                let mut exhausted = true;
+                for (i, values) in position.iter_mut().zip(&self.values).rev() {
+                    *i += 1;
+                    if *i < values.len() {
+                        exhausted = false;
+                        break;
+                    }
+                    *i = 0;
+                }
+                if exhausted {
+                    self.position = None;
+                }
+                return Some(identifier);
+            }
+
+            let path = self.leaves.next_path(qube)?;
+            self.dims.clear();
+            self.values.clear();
+            for &id in path.iter().skip(1) {
+                let node = qube.node_ref(id)?;
+                self.dims.push(*node.dim());
+                self.values.push(node.coords().values().collect());
+            }
+            // A node without values has no identifiers below it, and an empty
+            // Qube has none at all.
+            if !self.dims.is_empty() && self.values.iter().all(|values| !values.is_empty()) {
+                self.position = Some(vec![0; self.values.len()]);
+            }
+        }
+    }
+}
+
+fn children_of(qube: &Qube, id: NodeIdx) -> Option<Vec<NodeIdx>> {
+    Some(qube.node_ref(id)?.children().values().flatten().copied().collect())
+}
+
+/// For every node, the number of ways to complete an identifier below it:
+/// 1 for a leaf, otherwise the sum over its children of their number of
+/// values times their own count.  Saturates at `u128::MAX`.
+fn suffix_counts(qube: &Qube) -> HashMap<NodeIdx, u128> {
+    fn count(qube: &Qube, id: NodeIdx, counts: &mut HashMap<NodeIdx, u128>) -> u128 {
+        let children = children_of(qube, id).unwrap_or_default();
+        let total = if children.is_empty() {
+            1
+        } else {
+            children.iter().fold(0u128, |total, &child| {
+                let values = qube.node_ref(child).map_or(0, |node| node.coords().len() as u128);
+                total.saturating_add(values.saturating_mul(count(qube, child, counts)))
+            })
+        };
+        counts.insert(id, total);
+        total
+    }
+
+    let mut counts = HashMap::new();
+    count(qube, qube.root(), &mut counts);
+    counts
+}
+
+/// SplitMix64, so that a seed draws the same sample on every platform and
+/// with every version of the crate.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// A uniform value in `0..bound`, which must not be 0.
+    fn below(&mut self, bound: u128) -> u128 {
+        // Reject the top of the range that would favour small values.
+        let zone = u128::MAX - u128::MAX % bound;
+        loop {
+            let value = (u128::from(self.next_u64()) << 64) | u128::from(self.next_u64());
+            if value < zone {
+                return value % bound;
+            }
+        }
+    }
+}
+
+// -------------------------
+// Qube
+// -------------------------
+
+impl Qube {
+    /// The datacubes of [`Qube::to_datacubes`], built one at a time.
+    pub fn iter_datacubes(&self) -> impl Iterator<Item = Datacube> + '_ {
+        let mut cursor = LeafCursor::new();
+        std::iter::from_fn(move || cursor.next_datacube(self))
+    }
+
+    /// Every identifier of the Qube, fully expanded into one value per
+    /// dimension, without holding more than one leaf path in memory.
+    pub fn iter_identifiers(&self) -> impl Iterator<Item = Identifier<'_>> + '_ {
+        let mut cursor = IdentifierCursor::new();
+        std::iter::from_fn(move || cursor.next_identifier(self))
+    }
+
+    /// Up to `limit` identifiers starting at `offset`, in the order of
+    /// [`Qube::iter_identifiers`], so consecutive pages never overlap or
+    /// skip an identifier.  Subtrees before `offset` are skipped by count.
+    pub fn page(&self, offset: u128, limit: usize) -> Vec<Identifier<'_>> {
+        let mut cursor = IdentifierCursor::starting_at(self, offset);
+        std::iter::from_fn(|| cursor.next_identifier(self)).take(limit).collect()
+    }
+
+    /// `n` distinct identifiers drawn uniformly at random, so a subtree is
+    /// picked in proportion to its number of identifiers.  The same `seed`
+    /// gives the same sample.  Returned in the order of
+    /// [`Qube::iter_identifiers`]; all of them if there are at most `n`.
+    pub fn sample(&self, n: usize, seed: u64) -> Vec<Identifier<'_>> {
+        let suffixes = suffix_counts(self);
+        let total = if self.is_empty() { 0 } else { suffixes[&self.root()] };
+        if total <= n as u128 {
+            return self.iter_identifiers().collect();
+        }
+
+        // Floyd's algorithm: `n` distinct offsets in `0..total` without
+        // materialising the range.
+        let mut rng = SplitMix64(seed);
+        let mut offsets = BTreeSet::new();
+        for upper in total - n as u128..total {
+            let offset = rng.below(upper + 1);
+            if !offsets.insert(offset) {
+                offsets.insert(upper);
+            }
+        }
+
+        offsets
+            .into_iter()
+            .filter_map(|offset| {
+                IdentifierCursor::seek(self, &suffixes, offset).next_identifier(self)
+            })
+            .collect()
+    }
+
+    /// The leaf paths of [`Qube::leaf_node_ids_paths`], one at a time.
+    pub fn iter_leaf_paths(&self) -> impl Iterator<Item = Vec<NodeIdx>> + '_ {
+        let mut cursor = LeafCursor::new();
+        std::iter::from_fn(move || cursor.next_path(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example() -> Qube {
+        let input = r#"root
+├── class=od
+│   ├── expver=0001
+│   │   └── param=1/2
+│   └── expver=0002
+│       └── param=3
+└── class=rd
+    └── param=1/2/3"#;
+        Qube::from_ascii(input).unwrap()
+    }
+
+    #[test]
+    fn test_iter_leaf_paths_matches_collected_paths() {
+        let qube = example();
+        let paths: Vec<Vec<NodeIdx>> = qube.iter_leaf_paths().collect();
+        assert_eq!(paths, qube.leaf_node_ids_paths());
+        assert_eq!(qube.iter_datacubes().count(), qube.datacube_count());
+
+        let empty = Qube::new();
+        assert_eq!(empty.iter_leaf_paths().collect::<Vec<_>>(), vec![vec![empty.root()]]);
+    }
+
+    #[test]
+    fn test_iter_datacubes() {
+        let qube = example();
+        let first = qube.iter_datacubes().next().unwrap();
+        assert_eq!(first.coordinates()["class"].to_string(), "od");
+        assert_eq!(first.coordinates()["expver"].to_string(), "0001");
+        assert_eq!(first.coordinates()["param"].to_string(), "1/2");
+
+        let last = qube.iter_datacubes().last().unwrap();
+        assert_eq!(last.coordinates()["class"].to_string(), "rd");
+        assert!(!last.coordinates().contains_key("expver"));
+    }
+
+    #[test]
+    fn test_iter_identifiers() {
+        let qube = example();
+        let identifiers: Vec<String> = qube
+            .iter_identifiers()
+            .map(|identifier| {
+                let parts: Vec<String> =
+                    identifier.iter().map(|(dim, value)| format!("{}={}", dim, value)).collect();
+                parts.join(",")
+            })
+            .collect();
+
+        assert_eq!(
+            identifiers,
+            vec![
+                "class=od,expver=0001,param=1",
+                "class=od,expver=0001,param=2",
+                "class=od,expver=0002,param=3",
+                "class=rd,param=1",
+                "class=rd,param=2",
+                "class=rd,param=3",
+            ]
+        );
+        assert_eq!(Qube::new().iter_identifiers().count(), 0);
+    }
+
+    #[test]
+    fn test_identifier_cursor_stops_when_nodes_are_removed() {
+        let mut qube = example();
+        let mut cursor = IdentifierCursor::new();
+        assert!(cursor.next_identifier(&qube).is_some());
+
+        // The rest of class=od is still there, class=rd is gone.
+        let rd = qube.leaf_node_ids_paths()[2][1];
+        qube.remove_node(rd).unwrap();
+        assert_eq!(std::iter::from_fn(|| cursor.next_identifier(&qube)).count(), 2);
+    }
+
+    #[test]
+    fn test_page_matches_iteration_order() {
+        let qube = example();
+        let all: Vec<_> = qube.iter_identifiers().collect();
+        assert_eq!(all.len(), 6);
+        for offset in 0..=all.len() + 1 {
+            for limit in 0..4 {
+                let expected: Vec<_> = all.iter().skip(offset).take(limit).cloned().collect();
+                assert_eq!(qube.page(offset as u128, limit), expected, "offset {}", offset);
+            }
+        }
+        assert!(Qube::new().page(0, 10).is_empty());
+    }
+
+    #[test]
+    fn test_sample() {
+        let qube = example();
+        let all: Vec<_> = qube.iter_identifiers().collect();
+
+        let sample = qube.sample(3, 42);
+        assert_eq!(sample.len(), 3);
+        assert_eq!(sample, qube.sample(3, 42));
+        // Distinct, and in iteration order.
+        let positions: Vec<usize> =
+            sample.iter().map(|id| all.iter().position(|a| a == id).unwrap()).collect();
+        assert!(positions.windows(2).all(|w| w[0] < w[1]));
+
+        assert_eq!(qube.sample(10, 1), all);
+        assert!(Qube::new().sample(3, 1).is_empty());
+    }
+
+    #[test]
+    fn test_sample_is_weighted_by_subtree_size() {
+        // class=od holds 3 of the 4 identifiers.
+        let qube = Qube::from_ascii(
+            "root\n├── class=od\n│   └── param=1/2/3\n└── class=rd\n    └── param=1",
+        )
+        .unwrap();
+        let od = (0..1000)
+            .filter(|&seed| {
+                let sample = qube.sample(1, seed);
+                sample[0][0].1 == CoordinateTypes::String("od".to_string())
+            })
+            .count();
+        assert!((650..850).contains(&od), "od drawn {} times out of 1000", od);
+    }
+}
@@ -1,17 +1,31 @@
+mod comparison;
 mod compress;
 mod coordinates;
 pub mod datacube;
 mod difference;
+pub mod explain;
+mod intersection;
+pub mod iter;
+pub mod mapped;
 mod merge;
 pub mod metadata;
 mod qube;
+mod reorder;
 pub mod select;
 pub mod serde;
 mod utils;
-mod view;
+pub mod view;
 
-pub use coordinates::Coordinates;
+pub use coordinates::floats::FloatTolerance;
 pub use coordinates::integers::IntegerCoordinates;
+pub use coordinates::{CoordinateTypes, Coordinates, DURATION_DIMENSIONS};
 pub use datacube::Datacube;
+pub use difference::{PROVENANCE_LEFT, PROVENANCE_RIGHT};
+pub use explain::{DimensionExplanation, SelectExplanation};
+pub use iter::{Identifier, IdentifierCursor, LeafCursor};
+pub use mapped::{MappedNodeRef, MappedQube};
 pub use metadata::{Metadata, MetadataValues};
 pub use qube::{Dimension, NodeIdx, Qube};
+pub use reorder::OrderOptimization;
+pub use select::{Pattern, SelectMode, Selector};
+pub use view::{QubeView, QubeViewNode};
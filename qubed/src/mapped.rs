@@ -0,0 +1,576 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::ops::Range;
+use std::path::Path;
+
+use memmap2::Mmap;
+
+use crate::select::check_keys_known;
+use crate::serde::binary::{self, Reader};
+use crate::{Coordinates, Datacube, FloatTolerance, Metadata, NodeIdx, Qube, SelectMode, Selector};
+
+/// A read-only Qube read straight from the binary format of
+/// [`Qube::to_bytes`], usually a memory-mapped file.  Nodes are decoded only
+/// when asked for, so opening is near-instant whatever the size of the
+/// Qube, and processes mapping the same file share its pages.
+///
+/// Nodes are numbered depth-first from the root, which is node 0.
+pub struct MappedQube {
+    bytes: Backing,
+    dimensions: Vec<String>,
+    float_tolerance: FloatTolerance,
+    dimension_float_tolerances: HashMap<String, FloatTolerance>,
+    string_offsets: Range<usize>,
+    node_index: Range<usize>,
+}
+
+enum Backing {
+    Mapped(Mmap),
+    Owned(Vec<u8>),
+}
+
+impl MappedQube {
+    /// Maps the binary Qube at `path`.  The checksum is not checked, as that
+    /// would read the whole file; call [`MappedQube::verify`] for that.
+    ///
+    /// The file must not be modified while it is mapped: reads would see the
+    /// new bytes, or fail if it shrinks.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let file =
+            File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        // SAFETY: the file is only read, and callers are told not to modify
+        // it while it is mapped.
+        let mmap = unsafe { Mmap::map(&file) }
+            .map_err(|e| format!("Failed to map {}: {}", path.display(), e))?;
+        Self::new(Backing::Mapped(mmap))
+    }
+
+    /// Reads a binary Qube held in memory, such as one received over the
+    /// network, without decoding its nodes.
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, String> {
+        Self::new(Backing::Owned(bytes))
+    }
+
+    fn new(bytes: Backing) -> Result<Self, String> {
+        let sections = binary::sections(bytes.as_slice())?;
+        Ok(MappedQube {
+            bytes,
+            dimensions: sections.dimensions,
+            float_tolerance: sections.float_tolerance,
+            dimension_float_tolerances: sections.dimension_tolerances.into_iter().collect(),
+            string_offsets: sections.string_offsets,
+            node_index: sections.node_index,
+        })
+    }
+
+    /// Checks the whole file against its checksum.
+    pub fn verify(&self) -> Result<(), String> {
+        binary::verify_checksum(self.bytes.as_slice())
+    }
+
+    pub fn root(&self) -> MappedNodeRef<'_> {
+        MappedNodeRef { qube: self, id: 0 }
+    }
+
+    pub fn node(&self, id: usize) -> Option<MappedNodeRef<'_>> {
+        (id < self.len()).then_some(MappedNodeRef { qube: self, id })
+    }
+
+    /// The number of nodes below the root, as [`Qube::node_count`] counts them.
+    pub fn node_count(&self) -> usize {
+        self.len() - 1
+    }
+
+    fn len(&self) -> usize {
+        self.node_index.len() / 16
+    }
+
+    /// The name of every dimension in the Qube, including `root`.
+    pub fn dimensions(&self) -> &[String] {
+        &self.dimensions
+    }
+
+    /// The float tolerance used when selecting values of `dimension`.
+    pub fn float_tolerance(&self, dimension: &str) -> FloatTolerance {
+        self.dimension_float_tolerances.get(dimension).copied().unwrap_or(self.float_tolerance)
+    }
+
+    /// Decodes the whole Qube, checking its checksum.
+    pub fn to_qube(&self) -> Result<Qube, String> {
+        Qube::from_bytes(self.bytes.as_slice())
+    }
+
+    /// The datacubes of [`Qube::to_datacubes`], one per leaf, in the same
+    /// order.
+    pub fn to_datacubes(&self) -> Result<Vec<Datacube>, String> {
+        fn traverse<'m>(
+            node: MappedNodeRef<'m>,
+            path: &mut Vec<(&'m str, Coordinates)>,
+            datacubes: &mut Vec<Datacube>,
+        ) -> Result<(), String> {
+            path.push((node.dimension()?, node.coordinates()?));
+            if node.children_count() == 0 {
+                let mut datacube = Datacube::new();
+                for (dim, coords) in path.iter() {
+                    datacube.add_coordinate(dim, coords.clone());
+                }
+                datacubes.push(datacube);
+            } else {
+                for child in node.all_children() {
+                    traverse(child, path, datacubes)?;
+                }
+            }
+            path.pop();
+            Ok(())
+        }
+
+        let mut datacubes = Vec::new();
+        traverse(self.root(), &mut Vec::new(), &mut datacubes)?;
+        Ok(datacubes)
+    }
+
+    /// Selects as [`Qube::select`] does.  Only the subtrees whose selected
+    /// dimensions have at least one matching value are decoded; the rest of
+    /// the file is never read.
+    pub fn select<C>(&self, selection: &[(&str, C)], mode: SelectMode) -> Result<Qube, String>
+    where
+        C: Into<Selector> + Clone,
+    {
+        if mode == SelectMode::Strict {
+            let known: HashSet<&str> = self.dimensions.iter().map(String::as_str).collect();
+            check_keys_known(&known, selection.iter().map(|(key, _)| *key))?;
+        }
+
+        // A key selected twice is left to `Qube::select` to combine.
+        let mut selectors: HashMap<&str, Option<Selector>> = HashMap::new();
+        for (key, selector) in selection {
+            selectors
+                .entry(key)
+                .and_modify(|s| *s = None)
+                .or_insert_with(|| Some(selector.clone().into().for_dimension(key)));
+        }
+
+        let mut candidates = Qube::new();
+        candidates.set_float_tolerance(self.float_tolerance);
+        for (dim, tolerance) in &self.dimension_float_tolerances {
+            candidates.set_dimension_float_tolerance(dim, *tolerance);
+        }
+        let root = candidates.root();
+        if let Some(node) = candidates.node_mut(root) {
+            *node.coords_mut() = self.root().coordinates()?;
+            *node.metadata_mut() = self.root().metadata()?;
+        }
+
+        copy_matching(self.root(), root, &selectors, self, &mut candidates)?;
+
+        // Every node of a selected dimension may have been skipped, which
+        // strict selection would otherwise report as an unknown key.
+        if mode == SelectMode::Strict
+            && candidates.check_dimensions_exist(selection.iter().map(|(key, _)| *key)).is_err()
+        {
+            let mut empty = Qube::new();
+            empty.copy_float_tolerances(&candidates);
+            return Ok(empty);
+        }
+        candidates.select(selection, mode)
+    }
+
+    /// The byte offset and number of descendants of node `id`, or `None` if
+    /// its index entry lies outside the data.
+    fn entry(&self, id: usize) -> Option<(usize, usize)> {
+        let start = id.checked_mul(16)?.checked_add(self.node_index.start)?;
+        let bytes = self.bytes.as_slice().get(start..start.checked_add(16)?)?;
+        let offset = u64::from_le_bytes(bytes[..8].try_into().expect("8 bytes"));
+        let descendants = u64::from_le_bytes(bytes[8..].try_into().expect("8 bytes"));
+        Some((
+            usize::try_from(offset).unwrap_or(usize::MAX),
+            usize::try_from(descendants).unwrap_or(usize::MAX),
+        ))
+    }
+
+    /// The number of descendants of node `id`, or 0 if it has no entry.
+    fn descendants(&self, id: usize) -> usize {
+        self.entry(id).map_or(0, |(_, descendants)| descendants)
+    }
+
+    /// The id just past the subtree of node `id`, `1 + descendants` nodes
+    /// after it.  Counts are checked when the Qube is opened, but the bytes
+    /// of a mapped file can still change underneath, so this never steps past
+    /// the index.
+    fn subtree_end(&self, id: usize) -> usize {
+        let end = self.descendants(id).checked_add(1).and_then(|len| id.checked_add(len));
+        end.map_or(self.len(), |end| end.min(self.len()))
+    }
+
+    /// A reader at node `id`.  A node without an entry is read from past the
+    /// end, so that decoding it fails rather than panics.
+    fn reader(&self, id: usize) -> Reader<'_> {
+        let bytes = self.bytes.as_slice();
+        let body = &bytes[..bytes.len().saturating_sub(4)];
+        let offset = self.entry(id).map_or(usize::MAX, |(offset, _)| offset);
+        Reader::indexed(body, offset, self.string_offsets.clone())
+    }
+}
+
+/// Copies the children of `node` under `parent`, skipping those of a selected
+/// dimension with no matching value.  Returns whether anything was copied, so
+/// a node whose children were all skipped is dropped rather than left as a
+/// leaf.
+fn copy_matching(
+    node: MappedNodeRef<'_>,
+    parent: NodeIdx,
+    selectors: &HashMap<&str, Option<Selector>>,
+    mapped: &MappedQube,
+    candidates: &mut Qube,
+) -> Result<bool, String> {
+    let mut copied = false;
+    for child in node.all_children() {
+        let dim = child.dimension()?;
+        let coords = child.coordinates()?;
+        if let Some(Some(selector)) = selectors.get(dim) {
+            let tolerance = mapped.float_tolerance(dim);
+            if !coords.values().any(|value| selector.matches(&value, tolerance)) {
+                continue;
+            }
+        }
+        let dim = candidates.get_or_intern_dim(dim);
+        let id = candidates.insert_child(dim, parent, coords);
+        if let Some(copy) = candidates.node_mut(id) {
+            *copy.metadata_mut() = child.metadata()?;
+        }
+        if child.children_count() > 0 && !copy_matching(child, id, selectors, mapped, candidates)? {
+            candidates.remove_node(id)?;
+            continue;
+        }
+        copied = true;
+    }
+    Ok(copied)
+}
+
+impl Backing {
+    fn as_slice(&self) -> &[u8] {
+        match self {
+            Backing::Mapped(mmap) => mmap,
+            Backing::Owned(bytes) => bytes,
+        }
+    }
+}
+
+/// A node of a [`MappedQube`], decoded from the file each time it is read.
+#[derive(Clone, Copy)]
+pub struct MappedNodeRef<'m> {
+    qube: &'m MappedQube,
+    id: usize,
+}
+
+impl<'m> MappedNodeRef<'m> {
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn dimension(&self) -> Result<&'m str, String> {
+        let index = self.qube.reader(self.id).varint()?;
+        self.qube
+            .dimensions
+            .get(index as usize)
+            .map(String::as_str)
+            .ok_or_else(|| format!("Binary Qube dimension index {} out of range", index))
+    }
+
+    pub fn coordinates(&self) -> Result<Coordinates, String> {
+        let mut reader = self.qube.reader(self.id);
+        reader.varint()?;
+        reader.coordinates()
+    }
+
+    pub fn metadata(&self) -> Result<Metadata, String> {
+        let mut reader = self.qube.reader(self.id);
+        reader.varint()?;
+        reader.coordinates()?;
+        reader.metadata()
+    }
+
+    /// The children in the order [`Qube`] keeps them: grouped by dimension,
+    /// then in insertion order.
+    pub fn all_children(&self) -> impl Iterator<Item = MappedNodeRef<'m>> + 'm {
+        let qube = self.qube;
+        let end = qube.subtree_end(self.id);
+        let mut next = self.id + 1;
+        std::iter::from_fn(move || {
+            if next >= end {
+                return None;
+            }
+            let child = MappedNodeRef { qube, id: next };
+            next = qube.subtree_end(child.id);
+            Some(child)
+        })
+    }
+
+    /// The children of dimension `dim`, skipping any whose dimension cannot
+    /// be read.
+    pub fn children<'a>(&self, dim: &'a str) -> impl Iterator<Item = MappedNodeRef<'m>> + 'a
+    where
+        'm: 'a,
+    {
+        self.all_children().filter(move |child| child.dimension().is_ok_and(|d| d == dim))
+    }
+
+    /// The dimensions of the children, in the order they are kept.
+    pub fn child_dimensions(&self) -> Result<Vec<&'m str>, String> {
+        let mut dims: Vec<&'m str> = Vec::new();
+        for child in self.all_children() {
+            let dim = child.dimension()?;
+            if dims.last() != Some(&dim) {
+                dims.push(dim);
+            }
+        }
+        Ok(dims)
+    }
+
+    /// Every dimension below this node.
+    pub fn span(&self) -> Result<HashSet<&'m str>, String> {
+        let mut dims = HashSet::new();
+        for id in self.id + 1..self.qube.subtree_end(self.id) {
+            dims.insert(MappedNodeRef { qube: self.qube, id }.dimension()?);
+        }
+        Ok(dims)
+    }
+
+    /// The id of the parent, found by descending from the root through the
+    /// child whose subtree holds this node.  `None` for the root.
+    pub fn parent(&self) -> Option<usize> {
+        let mut node = self.qube.root();
+        loop {
+            let child = node.all_children().find(|c| self.id < self.qube.subtree_end(c.id))?;
+            if child.id == self.id {
+                return Some(node.id);
+            }
+            node = child;
+        }
+    }
+
+    pub fn parent_node(&self) -> Option<MappedNodeRef<'m>> {
+        self.qube.node(self.parent()?)
+    }
+
+    /// The ids of the parent, its parent and so on up to the root.
+    pub fn ancestors(&self) -> impl Iterator<Item = usize> + 'm {
+        let qube = self.qube;
+        std::iter::successors(self.parent(), move |&id| qube.node(id)?.parent())
+    }
+
+    pub fn children_count(&self) -> usize {
+        self.all_children().count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Dimension;
+
+    fn sample_qube() -> Qube {
+        let mut qube = Qube::from_ascii(
+            r#"root
+├── class=od
+│   ├── expver=0001
+│   │   └── param=1/2/3
+│   └── expver=0002
+│       └── param=1/2
+└── class=rd
+    ├── expver=0001
+    │   └── param=1/2/3
+    │       └── levelist=0.5/1.0
+    └── expver=0002
+        └── step=0/6/12"#,
+        )
+        .unwrap();
+        qube.set_dimension_float_tolerance("levelist", FloatTolerance::new(0.01, 0.0));
+        qube
+    }
+
+    fn format_datacubes(datacubes: &[Datacube]) -> Vec<String> {
+        datacubes
+            .iter()
+            .map(|datacube| {
+                let mut coords: Vec<_> = datacube
+                    .coordinates()
+                    .iter()
+                    .map(|(dim, coords)| format!("{}={}", dim, coords.to_string()))
+                    .collect();
+                coords.sort();
+                coords.join(", ")
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_mapped_nodes_match_qube() {
+        let qube = sample_qube();
+        let mapped = MappedQube::from_bytes(qube.to_bytes()).unwrap();
+        mapped.verify().unwrap();
+
+        assert_eq!(mapped.node_count(), qube.node_count());
+        assert_eq!(mapped.root().dimension().unwrap(), "root");
+        let classes: Vec<_> = mapped
+            .root()
+            .all_children()
+            .map(|c| (c.dimension().unwrap(), c.coordinates().unwrap().to_string()))
+            .collect();
+        assert_eq!(classes, vec![("class", "od".to_string()), ("class", "rd".to_string())]);
+
+        let rd = mapped.root().all_children().nth(1).unwrap();
+        assert_eq!(rd.children_count(), 2);
+        let last = rd.all_children().nth(1).unwrap().all_children().next().unwrap();
+        assert_eq!(last.children_count(), 0);
+        assert_eq!(last.dimension().unwrap(), "step");
+        assert_eq!(mapped.node(last.id()).unwrap().coordinates().unwrap().to_string(), "0/6/12");
+        assert!(mapped.node(mapped.node_count() + 1).is_none());
+        assert_eq!(mapped.to_qube().unwrap(), qube);
+    }
+
+    #[test]
+    fn test_mapped_navigation_matches_node_ref() {
+        let qube = sample_qube();
+        let mapped = MappedQube::from_bytes(qube.to_bytes()).unwrap();
+        let name = |dim: &Dimension| qube.dimension_str(dim).unwrap();
+
+        // Mapped nodes are numbered depth-first, children in Qube order.
+        fn number(qube: &Qube, id: NodeIdx, ids: &mut Vec<NodeIdx>) {
+            ids.push(id);
+            for child in qube.node(id).unwrap().all_children() {
+                number(qube, child, ids);
+            }
+        }
+        let mut ids = Vec::new();
+        number(&qube, qube.root(), &mut ids);
+        let mapped_id: HashMap<NodeIdx, usize> =
+            ids.iter().enumerate().map(|(i, &id)| (id, i)).collect();
+        assert_eq!(ids.len(), mapped.len());
+
+        for (i, &id) in ids.iter().enumerate() {
+            let node = qube.node(id).unwrap();
+            let mapped_node = mapped.node(i).unwrap();
+
+            assert_eq!(mapped_node.parent(), node.parent().map(|p| mapped_id[&p]));
+            assert_eq!(mapped_node.parent_node().map(|p| p.id()), mapped_node.parent());
+            let ancestors: Vec<usize> = node.ancestors().map(|a| mapped_id[&a]).collect();
+            assert_eq!(mapped_node.ancestors().collect::<Vec<_>>(), ancestors);
+
+            let span: HashSet<&str> = node.span().iter().map(name).collect();
+            assert_eq!(mapped_node.span().unwrap(), span);
+
+            let dims: Vec<&str> = node.child_dimensions().map(name).collect();
+            assert_eq!(mapped_node.child_dimensions().unwrap(), dims);
+            for (key, dim) in node.child_dimensions().zip(dims) {
+                let children: Vec<usize> =
+                    node.children(*key).unwrap().map(|c| mapped_id[&c]).collect();
+                let mapped_children: Vec<usize> =
+                    mapped_node.children(dim).map(|c| c.id()).collect();
+                assert_eq!(mapped_children, children);
+            }
+        }
+
+        assert_eq!(mapped.root().parent(), None);
+        assert_eq!(mapped.root().children("expver").count(), 0);
+    }
+
+    #[test]
+    fn test_mapped_datacubes_match_qube() {
+        let qube = sample_qube();
+        let mapped = MappedQube::from_bytes(qube.to_bytes()).unwrap();
+        assert_eq!(
+            format_datacubes(&mapped.to_datacubes().unwrap()),
+            format_datacubes(&qube.to_datacubes())
+        );
+    }
+
+    #[test]
+    fn test_mapped_select_matches_qube() {
+        let qube = sample_qube();
+        let mapped = MappedQube::from_bytes(qube.to_bytes()).unwrap();
+
+        let selections: Vec<Vec<(&str, Selector)>> = vec![
+            vec![("class", Selector::from(&["rd"]))],
+            vec![("param", Selector::from(&[2, 3])), ("expver", Selector::from(&["0001"]))],
+            vec![("levelist", Selector::from(&[1.001]))],
+            vec![("step", Selector::ge(6))],
+            vec![("class", Selector::from(&["xx"]))],
+            vec![("step", Selector::from(99))],
+            vec![("param", Selector::from(3)), ("step", Selector::lt(6))],
+        ];
+        for selection in &selections {
+            for mode in [SelectMode::Default, SelectMode::Prune, SelectMode::Strict] {
+                assert_eq!(
+                    mapped.select(selection, mode).unwrap(),
+                    qube.select(selection, mode).unwrap(),
+                    "{:?} {:?}",
+                    selection,
+                    mode
+                );
+            }
+        }
+
+        let err = mapped.select(&[("clas", &["od"])], SelectMode::Strict).unwrap_err();
+        assert_eq!(err, qube.select(&[("clas", &["od"])], SelectMode::Strict).unwrap_err());
+    }
+
+    #[test]
+    fn test_mapped_open_file() {
+        let qube = sample_qube();
+        let path = std::env::temp_dir().join(format!("qubed-mapped-{}.qube", std::process::id()));
+        std::fs::write(&path, qube.to_bytes()).unwrap();
+
+        let mapped = MappedQube::open(&path).unwrap();
+        mapped.verify().unwrap();
+        assert_eq!(mapped.to_qube().unwrap(), qube);
+        drop(mapped);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(MappedQube::open(&path).err().unwrap().starts_with("Failed to open"));
+    }
+
+    #[test]
+    fn test_mapped_rejects_bad_input() {
+        assert!(MappedQube::from_bytes(b"nope".to_vec()).is_err());
+
+        let mut corrupt = sample_qube().to_bytes();
+        let middle = corrupt.len() / 2;
+        corrupt[middle] ^= 0xff;
+        if let Ok(mapped) = MappedQube::from_bytes(corrupt) {
+            assert!(mapped.verify().unwrap_err().contains("checksum"));
+        }
+    }
+
+    #[test]
+    fn test_mapped_rejects_corrupt_descendant_counts() {
+        let bytes = sample_qube().to_bytes();
+        let node_index = binary::sections(&bytes).unwrap().node_index;
+        let set_count = |bytes: &mut [u8], id: usize, count: u64| {
+            let at = node_index.start + id * 16 + 8;
+            bytes[at..at + 8].copy_from_slice(&count.to_le_bytes());
+        };
+
+        for (id, count) in [(0, u64::MAX), (0, (node_index.len() / 16) as u64), (1, u64::MAX - 1)] {
+            let mut corrupt = bytes.clone();
+            set_count(&mut corrupt, id, count);
+            let err = MappedQube::from_bytes(corrupt).err().unwrap();
+            assert!(err.contains("descendants"), "{}", err);
+        }
+
+        // The bytes of a mapped file can change after it is opened.
+        let mut mapped = MappedQube::from_bytes(bytes.clone()).unwrap();
+        let Backing::Owned(owned) = &mut mapped.bytes else { unreachable!() };
+        set_count(owned, 0, u64::MAX);
+        set_count(owned, 1, u64::MAX);
+        assert_eq!(mapped.root().children_count(), 1);
+        mapped.to_datacubes().unwrap();
+
+        // As can their length.
+        let Backing::Owned(owned) = &mut mapped.bytes else { unreachable!() };
+        owned.truncate(node_index.start + 8);
+        assert!(mapped.to_datacubes().is_err());
+        assert!(mapped.node(1).unwrap().coordinates().is_err());
+    }
+}
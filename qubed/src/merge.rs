@@ -1,6 +1,6 @@
 use crate::metadata::Metadata;
 use crate::qube::Dimension;
-use crate::{NodeIdx, Qube};
+use crate::{Coordinates, NodeIdx, Qube};
 use std::collections::HashMap;
 
 impl Qube {
@@ -20,12 +20,16 @@ impl Qube {
         map
     }
 
-    /// Performs a union operation between two nodes in two different Qubes.
+    /// Performs a union operation between a node of `self` and a node of `other`.
+    ///
+    /// `other` is only read.  Where the merge would push metadata down in `other`,
+    /// the pushed metadata is carried along in `other_meta` instead.
     fn node_merge(
         &mut self,
-        other: &mut Qube,
+        other: &Qube,
         self_id: NodeIdx,
         other_id: NodeIdx,
+        other_meta: &Metadata,
         dim_map: &HashMap<Dimension, Dimension>,
     ) -> NodeIdx {
         // Before descending into children, check whether the two nodes carry different
@@ -35,14 +39,14 @@ impl Qube {
         // on that child).  Pushing down here normalises both trees to the same level
         // before the structural merge so metadata is never silently lost or misattributed.
         let self_meta = self.get_node_metadata(self_id).cloned().unwrap_or_default();
-        let other_meta = other.get_node_metadata(other_id).cloned().unwrap_or_default();
-        if self_meta != other_meta {
-            let self_is_leaf = self.node_ref(self_id).map_or(true, |n| n.children().is_empty());
+        let mut pushed_meta = Metadata::new();
+        if &self_meta != other_meta {
+            let self_is_leaf = self.node_ref(self_id).is_none_or(|n| n.children().is_empty());
 
             if self_is_leaf {
                 // Leaf node: merge_with handles it directly since there are no
                 // children to push into.
-                let merged = self_meta.merge_with(&other_meta);
+                let merged = self_meta.merge_with(other_meta);
                 *self.node_mut(self_id).unwrap().metadata_mut() = merged;
             } else {
                 // Non-leaf: push metadata ONE level down to direct children only.
@@ -52,7 +56,7 @@ impl Qube {
                 // collapsing all location values into a flat union at root.
                 // push_metadata_to_children clears the node's own metadata.
                 self.push_metadata_to_children(self_id);
-                other.push_metadata_to_children(other_id);
+                pushed_meta = other_meta.clone();
                 // Do NOT write the merged union back onto self_id here.
                 // Doing so causes every subsequent append to spread the
                 // accumulated union to all leaves via push_metadata_to_children,
@@ -74,7 +78,7 @@ impl Qube {
             node.children().clone()
         };
 
-        let mut dim_child_map: HashMap<Dimension, (Vec<NodeIdx>, Vec<NodeIdx>)> = HashMap::new();
+        let mut dim_child_map: HashMap<Dimension, (Vec<NodeIdx>, Vec<OtherChild>)> = HashMap::new();
 
         for (dim, self_kids) in self_children {
             dim_child_map.entry(dim).or_default().0.extend(self_kids);
@@ -82,70 +86,65 @@ impl Qube {
         for (dim, other_kids) in other_children {
             // Translate other's dimension ID to self's namespace.
             let self_dim = dim_map.get(&dim).copied().unwrap_or(dim);
-            dim_child_map.entry(self_dim).or_default().1.extend(other_kids);
+            dim_child_map.entry(self_dim).or_default().1.extend(other_kids.iter().map(|&id| {
+                let node = other.node_ref(id).unwrap();
+                let mut metadata = node.metadata().clone();
+                if !pushed_meta.is_empty() {
+                    metadata = metadata.merge_with(&pushed_meta);
+                }
+                OtherChild { id, coords: node.coords().clone(), metadata }
+            }));
         }
 
         // For each dimension, perform an internal set operation on the groups.
-        let dims: Vec<Dimension> = dim_child_map.keys().copied().collect();
-
-        for dim in dims {
-            let (these_kids, those_kids) = {
-                let entry = dim_child_map.entry(dim).or_default();
-                (entry.0.clone(), entry.1.clone())
-            };
-
+        for (_, (these_kids, mut those_kids)) in dim_child_map {
             if these_kids.is_empty() {
                 // Dimension exists only in `other`: copy every node (and its subtree) into self.
                 for other_node in those_kids {
-                    let (dim_str, coords) = {
-                        let n = other.node_ref(other_node).unwrap();
-                        let d = other.dimension_str(n.dim()).unwrap().to_owned();
-                        let c = n.coords().clone();
-                        (d, c)
+                    let dim_str = {
+                        let n = other.node_ref(other_node.id).unwrap();
+                        other.dimension_str(n.dim()).unwrap().to_owned()
                     };
-                    let new_child =
-                        self.get_or_create_child(&dim_str, self_id, Some(coords)).unwrap();
-                    self.copy_subtree(other, other_node, new_child);
+                    let new_child = self
+                        .get_or_create_child(&dim_str, self_id, Some(other_node.coords))
+                        .unwrap();
+                    self.copy_subtree(other, other_node.id, new_child);
                 }
             } else {
-                let _new_children =
-                    self.internal_set_operation(other, &these_kids, &those_kids, dim_map);
+                self.internal_set_operation(other, &these_kids, &mut those_kids, dim_map);
             }
         }
 
-        return self.root();
+        self.root()
     }
 
     /// Performs a set operation between two groups of nodes from two Qubes.
+    ///
+    /// The coordinates of `other_nodes` shrink as their values are merged, so a
+    /// later node of `self` only sees what is left.
     fn internal_set_operation(
         &mut self,
-        other: &mut Qube,
+        other: &Qube,
         self_ids: &[NodeIdx],
-        other_ids: &[NodeIdx],
+        other_nodes: &mut [OtherChild],
         dim_map: &HashMap<Dimension, Dimension>,
-    ) -> Option<Vec<NodeIdx>> {
-        let mut return_vec = Vec::new();
-
+    ) {
         for node in self_ids {
-            for other_node in other_ids {
+            for other_node in other_nodes.iter_mut() {
                 let self_coords = self.node_ref(*node).unwrap().coords();
-                let other_coords = other.node_ref(*other_node).unwrap().coords();
 
-                let (parent_a, dim_a, parent_b, dim_b) = {
+                let (parent_a, dim_a, dim_b) = {
                     let actual_node = self.node_ref(*node).unwrap();
-                    let actual_other_node = other.node_ref(*other_node).unwrap();
-
-                    (
-                        actual_node.parent().unwrap(),
-                        actual_node.dim(),
-                        actual_other_node.parent().unwrap(),
-                        actual_other_node.dim(),
-                    )
+                    let actual_other_node = other.node_ref(other_node.id).unwrap();
+
+                    (actual_node.parent().unwrap(), actual_node.dim(), actual_other_node.dim())
                 };
 
                 // Perform the shallow operation to get the set of values only in self,
                 // those only in other, and those in the intersection.
-                let intersection_res = self_coords.intersect(other_coords);
+                let tolerance = self.float_tolerance(self.dimension_str(dim_a).unwrap());
+                let intersection_res =
+                    self_coords.intersect_with_tolerance(&other_node.coords, tolerance);
                 let actual_intersection = intersection_res.intersection;
                 let only_self = intersection_res.only_a;
                 let only_other = intersection_res.only_b;
@@ -155,31 +154,19 @@ impl Qube {
                 let dim_str = self.dimension_str(dim_a).unwrap().to_owned();
                 let other_dim_str = other.dimension_str(dim_b).unwrap().to_owned();
 
-                if actual_intersection.len() != 0 {
+                if !actual_intersection.is_empty() {
                     let check_new_child_a = self.check_if_new_child(
                         &dim_str,
                         parent_a,
                         Some(actual_intersection.clone()),
                     );
-                    let check_new_child_b = other.check_if_new_child(
-                        &other_dim_str,
-                        parent_b,
-                        Some(actual_intersection.clone()),
-                    );
                     let new_node_a = self
-                        .get_or_create_child(&dim_str, parent_a, Some(actual_intersection.clone()))
-                        .unwrap();
-
-                    let new_node_b = other
-                        .get_or_create_child(&other_dim_str, parent_b, Some(actual_intersection))
+                        .get_or_create_child(&dim_str, parent_a, Some(actual_intersection))
                         .unwrap();
 
                     if check_new_child_a.unwrap() {
                         self.copy_branch(*node, new_node_a);
                     }
-                    if check_new_child_b.unwrap() {
-                        other.copy_branch(*other_node, new_node_b);
-                    }
 
                     // Seed the new intersection node in self with the metadata of the
                     // node being split.  The recursive node_merge + compress that
@@ -188,15 +175,17 @@ impl Qube {
                         self.get_node_metadata(*node).cloned().unwrap_or_default();
                     *self.node_mut(new_node_a).unwrap().metadata_mut() = self_meta;
 
-                    let other_meta: Metadata =
-                        other.get_node_metadata(*other_node).cloned().unwrap_or_default();
-                    *other.node_mut(new_node_b).unwrap().metadata_mut() = other_meta;
-
-                    let _nested_result = self.node_merge(other, new_node_a, new_node_b, dim_map);
+                    let _nested_result = self.node_merge(
+                        other,
+                        new_node_a,
+                        other_node.id,
+                        &other_node.metadata,
+                        dim_map,
+                    );
                 }
 
                 // If there are values only in self, update the coordinates of the current node.
-                if only_self.len() != 0 {
+                if !only_self.is_empty() {
                     let actual_node = self.node_mut(*node).unwrap();
                     *actual_node.coords_mut() = only_self;
                 }
@@ -207,7 +196,7 @@ impl Qube {
                 // If it already exists (because it was created by the intersection path of a
                 // different self×other pair earlier in this loop), copy_subtree + metadata
                 // assignment would clobber the already-merged subtree and metadata.
-                if only_other.len() != 0 {
+                if !only_other.is_empty() {
                     let is_new_b = self
                         .check_if_new_child(&other_dim_str, parent_a, Some(only_other.clone()))
                         .unwrap_or(true);
@@ -217,47 +206,58 @@ impl Qube {
                         .unwrap();
 
                     if is_new_b {
-                        self.copy_subtree(other, *other_node, new_node_only_b);
+                        self.copy_subtree(other, other_node.id, new_node_only_b);
 
                         // Propagate the metadata from other's node to the new node.
-                        let other_meta: Metadata =
-                            other.get_node_metadata(*other_node).cloned().unwrap_or_default();
-                        *self.node_mut(new_node_only_b).unwrap().metadata_mut() = other_meta;
+                        *self.node_mut(new_node_only_b).unwrap().metadata_mut() =
+                            other_node.metadata.clone();
                     }
 
-                    let actual_other_node = other.node_mut(*other_node).unwrap();
-                    *actual_other_node.coords_mut() = only_other;
-                }
-
-                {
-                    return_vec.push(*node);
+                    other_node.coords = only_other;
                 }
             }
         }
-
-        return Some(return_vec);
     }
 
     /// Performs a union operation between two Qubes.
+    ///
+    /// `other` is left empty afterwards; use [`Qube::union_in_place`] to keep it.
     pub fn append(&mut self, other: &mut Qube) {
+        self.union_in_place(other);
+        // Clear the other Qube
+        *other = Qube::new();
+    }
+
+    /// Returns the union of `self` and `other` as a new [`Qube`].
+    ///
+    /// Unlike [`Qube::append`], neither operand is modified.
+    pub fn union(&self, other: &Qube) -> Qube {
+        let mut result = self.clone();
+        result.union_in_place(other);
+        result
+    }
+
+    /// Merges `other` into `self`, leaving `other` untouched.
+    pub fn union_in_place(&mut self, other: &Qube) {
         // This method starts at the root of both Qubes and recursively merges their nodes.
         // After the union, the tree is compressed to remove duplicates and empty nodes.
 
         let self_root_id = self.root();
         let other_root_id = other.root();
+        let other_root_meta = other.get_node_metadata(other_root_id).cloned().unwrap_or_default();
 
         // Fast-path: if self is empty, copy_subtree is used instead of node_merge, so the
         // per-level conflict detection in node_merge never fires.  Handle the root-level
-        // metadata mismatch here explicitly before the copy.
+        // metadata mismatch here explicitly after the copy.
         if self.is_empty() {
             let self_root_meta = self.get_node_metadata(self_root_id).cloned().unwrap_or_default();
-            let other_root_meta =
-                other.get_node_metadata(other_root_id).cloned().unwrap_or_default();
+            self.copy_subtree(other, other_root_id, self_root_id);
             if self_root_meta != other_root_meta {
-                other.push_metadata_to_leaves(other_root_id);
+                // Push other's root metadata from self's root, then put self's own back.
+                *self.node_mut(self_root_id).unwrap().metadata_mut() = other_root_meta;
+                self.push_metadata_to_leaves(self_root_id);
+                *self.node_mut(self_root_id).unwrap().metadata_mut() = self_root_meta;
             }
-            self.copy_subtree(other, other_root_id, self_root_id);
-            *other = Qube::new();
             // Ensure append behavior is consistent: always compress and dedup after merging.
             self.compress();
             self.deduplicate_metadata();
@@ -270,25 +270,25 @@ impl Qube {
 
         // General path: node_merge recurses through the tree and pushes metadata at every
         // level where the two sides disagree, so no explicit push is needed here.
-        self.node_merge(other, self_root_id, other_root_id, &dim_map);
+        self.node_merge(other, self_root_id, other_root_id, &other_root_meta, &dim_map);
         self.compress();
         self.deduplicate_metadata();
-        // Clear the other Qube
-        *other = Qube::new();
     }
 
     /// Performs a union operation between many Qubes
     pub fn append_many(&mut self, others: &mut Vec<Qube>) {
         let others_len = others.len();
-        for (i, other) in others.iter_mut().enumerate() {
+        for (i, other) in others.iter().enumerate() {
             let self_root_id = self.root();
             let other_root_id = other.root();
+            let other_root_meta =
+                other.get_node_metadata(other_root_id).cloned().unwrap_or_default();
 
             // Build per-pair translation map so dimension IDs are correctly matched.
             let dim_map = self.build_dim_translation(other);
 
             // Perform the union with the current Qube
-            self.node_merge(other, self_root_id, other_root_id, &dim_map);
+            self.node_merge(other, self_root_id, other_root_id, &other_root_meta, &dim_map);
 
             // Print progress update
             println!("Union completed for Qube {}/{}", i + 1, others_len);
@@ -305,10 +305,35 @@ impl Qube {
     }
 }
 
+/// A child of a node of `other` during a merge: its id, the coordinates not yet
+/// merged into `self`, and its metadata including anything pushed down from its
+/// parent.
+struct OtherChild {
+    id: NodeIdx,
+    coords: Coordinates,
+    metadata: Metadata,
+}
+
+// ---------------------------------------------------------------------------
+// Trait impls: `&a | &b` and `a |= &b`
+// ---------------------------------------------------------------------------
+
+impl std::ops::BitOr for &Qube {
+    type Output = Qube;
+    fn bitor(self, rhs: Self) -> Qube {
+        self.union(rhs)
+    }
+}
+
+impl std::ops::BitOrAssign<&Qube> for Qube {
+    fn bitor_assign(&mut self, rhs: &Qube) {
+        self.union_in_place(rhs);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::Coordinates;
     use crate::datacube::Datacube;
 
     fn dc(pairs: &[(&str, &str)]) -> Datacube {
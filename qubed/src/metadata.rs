@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 
+use crate::Coordinates;
 use crate::utils::tiny_ordered_set::TinyOrderedSet;
 use tiny_str::TinyString;
 
@@ -285,6 +286,46 @@ impl Metadata {
         }
         result
     }
+
+    /// Restrict metadata attached to `original` coordinates to the subset `kept`.
+    ///
+    /// Uniform values are returned unchanged.  `PerCoordStrings` vectors are
+    /// re-aligned to the sorted order of `kept`; when every kept coordinate resolves
+    /// to the same inner set the value is collapsed to a plain `Strings` set.  If the
+    /// kept coordinates cannot be enumerated, the flattened union is used instead.
+    pub(crate) fn restricted_to(&self, original: &Coordinates, kept: &Coordinates) -> Metadata {
+        let mut result = Metadata::new();
+        for (key, val) in self.iter() {
+            let restricted = match val {
+                MetadataValues::PerCoordStrings(vec) => {
+                    let per_coord: Option<Vec<Vec<String>>> = kept
+                        .iter_sorted_strings()
+                        .iter()
+                        .map(|s| original.coord_index_of(s).and_then(|idx| vec.get(idx)).cloned())
+                        .collect();
+                    match per_coord {
+                        Some(per_coord) if !per_coord.is_empty() => {
+                            if per_coord.iter().all(|inner| inner == &per_coord[0]) {
+                                let refs: Vec<&str> =
+                                    per_coord[0].iter().map(|s| s.as_str()).collect();
+                                MetadataValues::from_strings(&refs)
+                            } else {
+                                MetadataValues::PerCoordStrings(per_coord)
+                            }
+                        }
+                        _ => {
+                            let all = val.as_string_vec();
+                            let refs: Vec<&str> = all.iter().map(|s| s.as_str()).collect();
+                            MetadataValues::from_strings(&refs)
+                        }
+                    }
+                }
+                other => other.clone(),
+            };
+            result.set(key.clone(), restricted);
+        }
+        result
+    }
 }
 
 #[cfg(test)]
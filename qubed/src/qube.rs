@@ -6,7 +6,8 @@ use std::hash::{Hash, Hasher};
 use std::sync::atomic::{AtomicU64, Ordering};
 use tiny_vec::TinyVec;
 
-use crate::coordinates::Coordinates;
+use crate::coordinates::floats::FloatTolerance;
+use crate::coordinates::{CoordinateTypes, Coordinates};
 use crate::metadata::{Metadata, MetadataValues};
 
 new_key_type! {
@@ -51,6 +52,8 @@ pub struct Qube {
     nodes: SlotMap<NodeIdx, Node>,
     root_id: NodeIdx,
     key_store: Rodeo<MiniSpur>,
+    float_tolerance: FloatTolerance,
+    dimension_float_tolerances: HashMap<String, FloatTolerance>,
 }
 
 /// Read-only reference to a node
@@ -132,13 +135,49 @@ impl Qube {
             metadata: Metadata::new(),
         });
 
-        Qube { nodes, root_id, key_store }
+        Qube {
+            nodes,
+            root_id,
+            key_store,
+            float_tolerance: FloatTolerance::default(),
+            dimension_float_tolerances: HashMap::new(),
+        }
     }
 
     pub fn root(&self) -> NodeIdx {
         self.root_id
     }
 
+    /// Sets how closely float coordinates must agree to match, in every
+    /// dimension without a tolerance of its own.
+    pub fn set_float_tolerance(&mut self, tolerance: FloatTolerance) {
+        self.float_tolerance = tolerance;
+    }
+
+    /// Sets how closely float coordinates of `dimension` must agree to match.
+    pub fn set_dimension_float_tolerance(&mut self, dimension: &str, tolerance: FloatTolerance) {
+        self.dimension_float_tolerances.insert(dimension.to_owned(), tolerance);
+    }
+
+    /// The tolerance used to match float coordinates of `dimension` in
+    /// [`Qube::select`], the set operations and the comparison predicates.
+    /// Operations on two Qubes use the tolerances of the left-hand one, and
+    /// their results inherit them.
+    pub fn float_tolerance(&self, dimension: &str) -> FloatTolerance {
+        self.dimension_float_tolerances.get(dimension).copied().unwrap_or(self.float_tolerance)
+    }
+
+    /// The default float tolerance and the per-dimension ones.
+    pub(crate) fn float_tolerances(&self) -> (FloatTolerance, &HashMap<String, FloatTolerance>) {
+        (self.float_tolerance, &self.dimension_float_tolerances)
+    }
+
+    /// Copies the float tolerances of `other` onto `self`.
+    pub(crate) fn copy_float_tolerances(&mut self, other: &Qube) {
+        self.float_tolerance = other.float_tolerance;
+        self.dimension_float_tolerances = other.dimension_float_tolerances.clone();
+    }
+
     /// Get a read-only reference to a node
     pub fn node(&self, id: NodeIdx) -> Option<NodeRef<'_>> {
         let node = self.nodes.get(id)?;
@@ -202,6 +241,47 @@ impl Qube {
         }
 
         // Create a new child node if no match is found
+        Ok(self.insert_child(dim, parent_id, coords))
+    }
+
+    /// Like [`Qube::get_or_create_child`], but only reuses an existing child whose
+    /// leaf-ness matches `is_leaf`.  A leaf and a deeper branch with the same
+    /// coordinates are distinct identifiers and must not share a node.
+    ///
+    /// Returns the node and whether it was newly created.
+    pub(crate) fn get_or_create_child_matching(
+        &mut self,
+        key: &str,
+        parent_id: NodeIdx,
+        coords: Coordinates,
+        is_leaf: bool,
+    ) -> Result<(NodeIdx, bool), String> {
+        let parent = self
+            .nodes
+            .get(parent_id)
+            .ok_or_else(|| format!("Parent node {:?} not found", parent_id))?;
+        let dim = Dimension(self.key_store.get_or_intern(key));
+
+        let existing = parent.children.get(&dim).and_then(|children| {
+            children.iter().copied().find(|&child_id| {
+                self.nodes.get(child_id).is_some_and(|child| {
+                    child.coords == coords && child.children.is_empty() == is_leaf
+                })
+            })
+        });
+
+        match existing {
+            Some(child_id) => Ok((child_id, false)),
+            None => Ok((self.insert_child(dim, parent_id, coords), true)),
+        }
+    }
+
+    pub(crate) fn insert_child(
+        &mut self,
+        dim: Dimension,
+        parent_id: NodeIdx,
+        coords: Coordinates,
+    ) -> NodeIdx {
         let node_id = self.nodes.insert(Node {
             dim,
             structural_hash: AtomicU64::new(0),
@@ -220,7 +300,36 @@ impl Qube {
         // Invalidate ancestor hashes
         self.invalidate_ancestors(parent_id);
 
-        Ok(node_id)
+        node_id
+    }
+
+    /// Like [`Qube::insert_child`], for a Qube being built node by node as it
+    /// is loaded.  The node gets its metadata straight away, and no ancestor
+    /// hashes are invalidated, since none have been computed yet.
+    pub(crate) fn insert_loaded_child(
+        &mut self,
+        dim: Dimension,
+        parent_id: NodeIdx,
+        coords: Coordinates,
+        metadata: Metadata,
+    ) -> NodeIdx {
+        let node_id = self.nodes.insert(Node {
+            dim,
+            structural_hash: AtomicU64::new(0),
+            coords,
+            parent: Some(parent_id),
+            children: BTreeMap::new(),
+            metadata,
+        });
+        if let Some(parent) = self.nodes.get_mut(parent_id) {
+            parent.children.entry(dim).or_insert_with(TinyVec::new).push(node_id);
+        }
+        node_id
+    }
+
+    /// Makes room for `additional` more nodes.
+    pub(crate) fn reserve_nodes(&mut self, additional: usize) {
+        self.nodes.reserve(additional);
     }
 
     pub fn all_unique_dim_coords(&self) -> BTreeMap<String, Coordinates> {
@@ -682,6 +791,97 @@ impl Qube {
 
         count_leaves(self, self.root())
     }
+
+    /// The number of nodes below the root, i.e. the size of the tree as
+    /// serialised.
+    pub fn node_count(&self) -> usize {
+        let mut count = 0;
+        let mut stack = vec![self.root()];
+        while let Some(node_id) = stack.pop() {
+            let node = self.nodes.get(node_id).expect("valid node");
+            let children = node.children().values().flatten();
+            count += children.clone().count();
+            stack.extend(children);
+        }
+        count
+    }
+
+    /// The number of identifiers: for each leaf path, the product of the
+    /// number of values of its nodes, summed over the paths.  Unlike
+    /// [`Qube::datacube_count`], `class=od/rd, param=1/2/3` counts as 6.
+    /// Saturates at `u128::MAX`.
+    pub fn identifier_count(&self) -> u128 {
+        fn count(qube: &Qube, node_id: NodeIdx) -> u128 {
+            let node = qube.nodes.get(node_id).expect("valid node");
+            let values = node.coords().len() as u128;
+            if node.children().is_empty() {
+                return values;
+            }
+            node.children()
+                .values()
+                .flatten()
+                .fold(0u128, |total, &child| total.saturating_add(count(qube, child)))
+                .saturating_mul(values)
+        }
+
+        let root = self.nodes.get(self.root()).expect("valid root");
+        root.children()
+            .values()
+            .flatten()
+            .fold(0u128, |total, &child| total.saturating_add(count(self, child)))
+    }
+
+    /// For each value of `dimension`, the number of identifiers that have it,
+    /// ordered as [`CoordinateTypes::compare`] orders them.  A value counts
+    /// once per identifier through every node it appears on.  Empty if no node
+    /// has `dimension`.
+    pub fn value_histogram(&self, dimension: &str) -> Vec<(CoordinateTypes, u128)> {
+        /// Returns the number of identifiers below `node_id`, counting each of
+        /// its values, and adds `above` times that for each value of a
+        /// `dimension` node to `counts`.
+        fn walk(
+            qube: &Qube,
+            node_id: NodeIdx,
+            above: u128,
+            dimension: Dimension,
+            counts: &mut HashMap<String, (CoordinateTypes, u128)>,
+        ) -> u128 {
+            let node = qube.nodes.get(node_id).expect("valid node");
+            let values = node.coords().len() as u128;
+            let above_children = above.saturating_mul(values);
+            let below = if node.children().is_empty() {
+                1
+            } else {
+                node.children().values().flatten().fold(0u128, |total, &child| {
+                    total.saturating_add(walk(qube, child, above_children, dimension, counts))
+                })
+            };
+
+            if node.dim == dimension {
+                let uses = above.saturating_mul(below);
+                for value in node.coords().values() {
+                    let entry = counts.entry(value.to_string()).or_insert((value, 0));
+                    entry.1 = entry.1.saturating_add(uses);
+                }
+            }
+            below.saturating_mul(values)
+        }
+
+        let Some(dimension) = self.dimension(dimension) else {
+            return Vec::new();
+        };
+        let mut counts = HashMap::new();
+        let root = self.nodes.get(self.root()).expect("valid root");
+        for &child in root.children().values().flatten() {
+            walk(self, child, 1, dimension, &mut counts);
+        }
+
+        let mut histogram: Vec<(CoordinateTypes, u128)> = counts.into_values().collect();
+        histogram.sort_by(|(a, _), (b, _)| {
+            a.compare(b).unwrap_or_else(|| a.to_string().cmp(&b.to_string()))
+        });
+        histogram
+    }
 }
 
 impl Qube {
@@ -1615,4 +1815,33 @@ mod tests {
             loc
         );
     }
+
+    #[test]
+    fn test_identifier_count_and_value_histogram() {
+        let input = r#"root
+├── class=od
+│   ├── expver=0001/0002
+│   │   └── param=1/2/3
+│   └── expver=0003
+│       └── param=1
+└── class=rd
+    └── param=1/2"#;
+        let qube = Qube::from_ascii(input).unwrap();
+        assert_eq!(qube.datacube_count(), 3);
+        assert_eq!(qube.identifier_count(), 9);
+        assert_eq!(qube.identifier_count(), qube.iter_identifiers().count() as u128);
+
+        let histogram = |dim: &str| -> Vec<(String, u128)> {
+            qube.value_histogram(dim).into_iter().map(|(v, n)| (v.to_string(), n)).collect()
+        };
+        let expected = |pairs: &[(&str, u128)]| -> Vec<(String, u128)> {
+            pairs.iter().map(|(v, n)| (v.to_string(), *n)).collect()
+        };
+        assert_eq!(histogram("param"), expected(&[("1", 4), ("2", 3), ("3", 2)]));
+        assert_eq!(histogram("expver"), expected(&[("0001", 3), ("0002", 3), ("0003", 1)]));
+        assert_eq!(histogram("class"), expected(&[("od", 7), ("rd", 2)]));
+        assert!(qube.value_histogram("step").is_empty());
+
+        assert_eq!(Qube::new().identifier_count(), 0);
+    }
 }
@@ -0,0 +1,372 @@
+use crate::coordinates::Coordinates;
+use crate::metadata::Metadata;
+use crate::qube::{NodeIdx, Qube};
+use std::collections::{HashMap, HashSet};
+
+/// The result of [`Qube::optimize_order`].
+#[derive(Debug, Clone)]
+pub struct OrderOptimization {
+    /// The reordered Qube, or a copy of the original if no order found was
+    /// smaller.
+    pub qube: Qube,
+    /// The order `qube` was built with, outermost first.  Empty if `qube` is
+    /// the original.
+    pub order: Vec<String>,
+    /// [`Qube::node_count`] of the original.
+    pub nodes_before: usize,
+    /// [`Qube::node_count`] of `qube`.
+    pub nodes_after: usize,
+}
+
+/// A node of a branch, in its new position.
+struct Entry<'q> {
+    dim: &'q str,
+    coords: &'q Coordinates,
+    metadata: Metadata,
+}
+
+impl Qube {
+    /// Rebuilds the Qube with its dimensions nested in `order`, e.g.
+    /// `reorder(&["param"])` to put `param` at the top.
+    ///
+    /// Dimensions not listed in `order` go below the listed ones, in the order
+    /// they had on each branch.  A branch without some of the listed
+    /// dimensions just skips them.  Each metadata key stays on the node that
+    /// set it for the branch, so every identifier resolves to the same
+    /// metadata as before.  The result is compressed and keeps this Qube's
+    /// float tolerances.
+    pub fn reorder(&self, order: &[&str]) -> Qube {
+        let branches = self.branches();
+        self.build_reordered(&branches, order)
+    }
+
+    /// Searches for the dimension order giving the fewest nodes, and returns
+    /// the Qube reordered with it.
+    ///
+    /// The search is greedy: starting from the dimensions sorted by their
+    /// number of distinct values, it fixes the outermost dimension first,
+    /// trying each remaining one in turn and keeping whichever gives the
+    /// smallest tree.  That takes a number of [`Qube::reorder`]s quadratic in
+    /// the number of dimensions, and is not guaranteed to find the best order.
+    pub fn optimize_order(&self) -> OrderOptimization {
+        let nodes_before = self.node_count();
+        let unchanged = || OrderOptimization {
+            qube: self.clone(),
+            order: Vec::new(),
+            nodes_before,
+            nodes_after: nodes_before,
+        };
+
+        let mut remaining: Vec<(String, usize)> = self
+            .all_unique_dim_coords()
+            .into_iter()
+            .map(|(dim, coords)| (dim, coords.len()))
+            .collect();
+        if remaining.len() < 2 {
+            return unchanged();
+        }
+        remaining.sort_by_key(|(_, cardinality)| *cardinality);
+        let mut remaining: Vec<String> = remaining.into_iter().map(|(dim, _)| dim).collect();
+
+        let branches = self.branches();
+        let mut order: Vec<String> = Vec::new();
+        let mut best: Option<(Qube, usize)> = None;
+        while remaining.len() > 1 {
+            let mut pick: Option<(usize, Qube, usize)> = None;
+            for (i, candidate) in remaining.iter().enumerate() {
+                let trial: Vec<&str> = order
+                    .iter()
+                    .chain(std::iter::once(candidate))
+                    .chain(remaining.iter().filter(|dim| *dim != candidate))
+                    .map(String::as_str)
+                    .collect();
+                let qube = self.build_reordered(&branches, &trial);
+                let count = qube.node_count();
+                if pick.as_ref().is_none_or(|(_, _, best_count)| count < *best_count) {
+                    pick = Some((i, qube, count));
+                }
+            }
+            let (i, qube, count) = pick.expect("at least two candidates");
+            order.push(remaining.remove(i));
+            best = Some((qube, count));
+        }
+        order.append(&mut remaining);
+
+        match best {
+            Some((qube, nodes_after)) if nodes_after < nodes_before => {
+                OrderOptimization { qube, order, nodes_before, nodes_after }
+            }
+            _ => unchanged(),
+        }
+    }
+
+    /// Each leaf path as its nodes below the root, outermost first.
+    fn branches(&self) -> Vec<Vec<Entry<'_>>> {
+        let mut branches = Vec::new();
+        for path in self.iter_leaf_paths() {
+            let mut branch = Vec::new();
+
+            // A key set on several nodes of the branch resolves to the deepest
+            // one, so only that node keeps it.
+            let mut claimed: HashSet<&str> = HashSet::new();
+            for &id in path.iter().skip(1).rev() {
+                let node = self.node_ref(id).expect("valid node");
+                let dim = self.dimension_str(node.dim()).expect("dimension in key store");
+                let mut metadata = Metadata::new();
+                for (key, values) in node.metadata().iter() {
+                    if claimed.insert(key.as_str()) {
+                        metadata.set(key.clone(), values.clone());
+                    }
+                }
+                branch.push(Entry { dim, coords: node.coords(), metadata });
+            }
+            branch.reverse();
+            branches.push(branch);
+        }
+        branches
+    }
+
+    fn build_reordered(&self, branches: &[Vec<Entry>], order: &[&str]) -> Qube {
+        let rank: HashMap<&str, usize> =
+            order.iter().enumerate().map(|(i, &dim)| (dim, i)).collect();
+
+        let mut result = Qube::new();
+        result.copy_float_tolerances(self);
+        let result_root = result.root();
+        if let Some(root) = self.node_ref(self.root())
+            && let Some(node) = result.node_mut(result_root)
+        {
+            *node.metadata_mut() = root.metadata().clone();
+        }
+        if self.is_empty() {
+            return result;
+        }
+
+        let sorted: Vec<Vec<&Entry>> = branches
+            .iter()
+            .map(|branch| {
+                let mut sorted: Vec<&Entry> = branch.iter().collect();
+                // Stable, so unlisted dimensions keep their relative order.
+                sorted.sort_by_key(|entry| rank.get(entry.dim).copied().unwrap_or(order.len()));
+                sorted
+            })
+            .collect();
+        let sorted: Vec<&[&Entry]> = sorted.iter().map(Vec::as_slice).collect();
+        result.build_branches(result_root, &sorted);
+        result.compress();
+        result
+    }
+
+    /// Adds `branches` below `parent`.  Values shared by several branches go
+    /// on one node, so siblings never overlap unless their metadata differs,
+    /// and `compress` merges siblings with identical subtrees afterwards.
+    fn build_branches(&mut self, parent: NodeIdx, branches: &[&[&Entry]]) {
+        let mut by_dim: Vec<(&str, Vec<usize>)> = Vec::new();
+        for (i, branch) in branches.iter().enumerate() {
+            let dim = branch[0].dim;
+            match by_dim.iter_mut().find(|(d, _)| *d == dim) {
+                Some((_, members)) => members.push(i),
+                None => by_dim.push((dim, vec![i])),
+            }
+        }
+
+        for (dim, members) in by_dim {
+            // Which branches hold each value of this dimension.
+            let mut values = Vec::new();
+            let mut holders: Vec<Vec<usize>> = Vec::new();
+            let mut index: HashMap<String, usize> = HashMap::new();
+            for &i in &members {
+                for value in branches[i][0].coords.values() {
+                    let slot = *index.entry(value.to_string()).or_insert_with(|| {
+                        values.push(value.clone());
+                        holders.push(Vec::new());
+                        holders.len() - 1
+                    });
+                    holders[slot].push(i);
+                }
+            }
+
+            // Values held by the same branches share a node, in the order the
+            // values were first seen.
+            let mut groups: Vec<(Vec<usize>, Coordinates)> = Vec::new();
+            let mut group_of: HashMap<Vec<usize>, usize> = HashMap::new();
+            for (value, holders) in values.into_iter().zip(holders) {
+                let slot = *group_of.entry(holders.clone()).or_insert_with(|| {
+                    groups.push((holders, Coordinates::Empty));
+                    groups.len() - 1
+                });
+                groups[slot].1.append(value);
+            }
+
+            for (holders, coords) in groups {
+                // Branches can only share the node if they agree on its
+                // metadata, and on whether it is their leaf.
+                let mut parts: Vec<(Metadata, bool, Vec<&[&Entry]>)> = Vec::new();
+                for i in holders {
+                    let entry = branches[i][0];
+                    let metadata = entry.metadata.restricted_to(entry.coords, &coords);
+                    let rest = &branches[i][1..];
+                    let is_leaf = rest.is_empty();
+                    match parts.iter_mut().find(|(m, l, _)| *m == metadata && *l == is_leaf) {
+                        Some((_, _, rests)) => rests.push(rest),
+                        None => parts.push((metadata, is_leaf, vec![rest])),
+                    }
+                }
+
+                for (metadata, is_leaf, rests) in parts {
+                    let dim = self.get_or_intern_dim(dim);
+                    let child = self.insert_child(dim, parent, coords.clone());
+                    if let Some(node) = self.node_mut(child) {
+                        *node.metadata_mut() = metadata;
+                    }
+                    if !is_leaf {
+                        self.build_branches(child, &rests);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MetadataValues;
+
+    /// The identifiers as sorted `dim=value` lists, independent of nesting.
+    fn identifiers(qube: &Qube) -> Vec<Vec<String>> {
+        let mut all: Vec<Vec<String>> = qube
+            .iter_identifiers()
+            .map(|identifier| {
+                let mut parts: Vec<String> =
+                    identifier.iter().map(|(dim, value)| format!("{}={}", dim, value)).collect();
+                parts.sort();
+                parts
+            })
+            .collect();
+        all.sort();
+        all
+    }
+
+    #[test]
+    fn test_reorder_regular_qube() -> Result<(), String> {
+        let input = r#"root
+└── class=od/rd
+    └── expver=0001/0002
+        └── param=1/2/3"#;
+        let qube = Qube::from_ascii(input)?;
+
+        let reordered = qube.reorder(&["param", "class"]);
+        let expected = r#"root
+└── param=1/2/3
+    └── class=od/rd
+        └── expver=0001/0002"#;
+        assert_eq!(reordered.to_ascii(), Qube::from_ascii(expected)?.to_ascii());
+        assert_eq!(identifiers(&reordered), identifiers(&qube));
+
+        let back = reordered.reorder(&["class", "expver", "param"]);
+        assert_eq!(back.to_ascii(), qube.to_ascii());
+        Ok(())
+    }
+
+    #[test]
+    fn test_reorder_irregular_branches() -> Result<(), String> {
+        let input = r#"root
+├── class=od
+│   ├── expver=0001
+│   │   └── param=1/2
+│   └── expver=0002
+│       └── param=1
+└── class=rd
+    └── param=1/3"#;
+        let qube = Qube::from_ascii(input)?;
+
+        let reordered = qube.reorder(&["param"]);
+        assert_eq!(identifiers(&reordered), identifiers(&qube));
+
+        let expected = r#"root
+├── param=1
+│   ├── class=od
+│   │   └── expver=0001/0002
+│   └── class=rd
+├── param=2
+│   └── class=od
+│       └── expver=0001
+└── param=3
+    └── class=rd"#;
+        assert_eq!(reordered.to_ascii(), Qube::from_ascii(expected)?.to_ascii());
+        Ok(())
+    }
+
+    #[test]
+    fn test_reorder_keeps_metadata() -> Result<(), String> {
+        let input = r#"root
+├── class=od
+│   └── param=1/2
+└── class=rd
+    └── param=1/2"#;
+        let mut qube = Qube::from_ascii(input)?;
+        let paths = qube.leaf_node_ids_paths();
+        let (od, od_leaf) = (paths[0][1], paths[0][2]);
+        let rd_leaf = paths[1][2];
+        qube.set_metadata(od, "path", MetadataValues::single_string("/class/od"))?;
+        qube.set_metadata(od_leaf, "path", MetadataValues::single_string("/od/params"))?;
+        qube.set_metadata(rd_leaf, "path", MetadataValues::single_string("/rd/params"))?;
+
+        let reordered = qube.reorder(&["param"]);
+        assert_eq!(identifiers(&reordered), identifiers(&qube));
+
+        let mut resolved: Vec<(String, Vec<String>)> = reordered
+            .iter_leaf_paths()
+            .map(|path| {
+                let leaf = *path.last().unwrap();
+                let class = reordered.node(leaf).unwrap().coordinates().to_string();
+                let metadata = reordered.resolve_all_metadata(leaf, &HashMap::new());
+                (class, metadata.get("path").unwrap().as_string_vec())
+            })
+            .collect();
+        resolved.sort();
+        assert_eq!(
+            resolved,
+            vec![
+                ("od".to_string(), vec!["/od/params".to_string()]),
+                ("rd".to_string(), vec!["/rd/params".to_string()]),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_optimize_order_puts_independent_dimension_outside() -> Result<(), String> {
+        let input = r#"root
+├── class=od
+│   └── expver=0001
+│       └── step=0/1
+└── class=rd
+    └── expver=0002
+        └── step=0/1"#;
+        let qube = Qube::from_ascii(input)?;
+
+        let optimized = qube.optimize_order();
+        assert_eq!(optimized.nodes_before, 6);
+        assert_eq!(optimized.nodes_after, 5);
+        assert_eq!(optimized.order, vec!["step", "class", "expver"]);
+        assert_eq!(optimized.qube.node_count(), 5);
+        assert_eq!(identifiers(&optimized.qube), identifiers(&qube));
+        Ok(())
+    }
+
+    #[test]
+    fn test_optimize_order_keeps_optimal_qube() -> Result<(), String> {
+        let input = r#"root
+└── class=od/rd
+    └── param=1/2"#;
+        let qube = Qube::from_ascii(input)?;
+
+        let optimized = qube.optimize_order();
+        assert_eq!((optimized.nodes_before, optimized.nodes_after), (2, 2));
+        assert!(optimized.order.is_empty());
+        assert_eq!(optimized.qube.to_ascii(), qube.to_ascii());
+        Ok(())
+    }
+}
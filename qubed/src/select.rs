@@ -1,203 +1,448 @@
-use crate::{Coordinates, Dimension, NodeIdx, Qube};
-use std::collections::{HashMap, HashSet};
-
-// TODO: select should return a QubeView, but this is an optimization
+use crate::coordinates::datetime::DateTimeCoordinates;
+use crate::coordinates::integers::IntegerCoordinates;
+use crate::coordinates::strings::StringCoordinates;
+use crate::view::QubeView;
+use crate::{
+    CoordinateTypes, Coordinates, DURATION_DIMENSIONS, Dimension, FloatTolerance, NodeIdx, Qube,
+};
+use chrono::{Duration, NaiveDateTime};
+use regex::Regex;
+use smallbitvec::SmallBitVec;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+/// Fails with the sorted list of `keys` missing from `known`, as
+/// [`Qube::check_dimensions_exist`] does.
+pub(crate) fn check_keys_known<'k>(
+    known: &HashSet<&str>,
+    keys: impl IntoIterator<Item = &'k str>,
+) -> Result<(), String> {
+    let mut unknown: Vec<&str> = keys.into_iter().filter(|k| !known.contains(k)).collect();
+    if unknown.is_empty() {
+        return Ok(());
+    }
+    unknown.sort_unstable();
+    unknown.dedup();
+    Err(format!("Selection keys not found in the Qube: {}", unknown.join(", ")))
+}
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SelectMode {
     Default,
     Prune,
+    /// Keep only the leaf paths that contain every selected dimension, and
+    /// fail if a selected dimension appears nowhere in the Qube.
+    Strict,
 }
 
-pub(crate) struct WalkPair {
-    pub(crate) left: NodeIdx,
-    pub(crate) right: NodeIdx,
+/// Which values of a dimension to keep in [`Qube::select`].
+///
+/// Anything that converts into [`Coordinates`] converts into
+/// [`Selector::Values`], so plain value lists can be passed as before.
+/// Comparisons follow [`CoordinateTypes::compare`]: integers and floats compare
+/// numerically, strings lexicographically, and a value that cannot be compared
+/// with a bound, such as a string against a number, never matches it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Selector {
+    /// Any of these values.  Floats match within the Qube's float tolerance.
+    Values(Coordinates),
+    /// Values between `lo` and `hi`, including both ends if `inclusive`.
+    Range { lo: CoordinateTypes, hi: CoordinateTypes, inclusive: bool },
+    /// Values above `value`, or equal to it if `inclusive`.
+    GreaterThan { value: CoordinateTypes, inclusive: bool },
+    /// Values below `value`, or equal to it if `inclusive`.
+    LessThan { value: CoordinateTypes, inclusive: bool },
+    /// Values the inner selector does not match.
+    Not(Box<Selector>),
+    /// Values matched by at least one of the selectors.
+    AnyOf(Vec<Selector>),
+    /// Every value.  Unlike leaving the dimension out, this still counts as
+    /// selecting it for [`SelectMode::Prune`].
+    Wildcard,
+    /// String values matching a glob or regular expression.  Values of other
+    /// types never match.
+    Pattern(Pattern),
 }
 
-impl Qube {
-    // Select takes a dictionary of key-vecvalues pairs and returns a QubeView
-    // It does not matter which order the keys are specified
+/// A glob or regular expression that must match a whole string value.
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    source: String,
+    regex: Regex,
+}
 
-    pub fn select<C>(&self, selection: &[(&str, C)], mode: SelectMode) -> Result<Qube, String>
-    where
-        C: Into<Coordinates> + Clone,
-    {
-        let root = self.root();
-        let mut result = Qube::new();
+impl Pattern {
+    /// A regular expression, anchored at both ends: `00.1` matches `0001` but
+    /// not `00010`.
+    pub fn regex(pattern: &str) -> Result<Self, String> {
+        let regex = Regex::new(&format!("^(?:{})$", pattern))
+            .map_err(|e| format!("Invalid regex {:?}: {}", pattern, e))?;
+        Ok(Pattern { source: pattern.to_owned(), regex })
+    }
 
-        // Propagate root-node metadata into the result root so that location
-        // metadata consolidated up to the source root is not silently dropped.
-        let root_meta = self.get_node_metadata(root).cloned().unwrap_or_default();
-        if !root_meta.is_empty() {
-            let result_root = result.root();
-            if let Some(node) = result.node_mut(result_root) {
-                *node.metadata_mut() = root_meta;
-            }
-        }
+    /// A shell-style glob: `*` matches any run of characters, `?` any single
+    /// character and `[abc]`, `[a-z]` or `[!abc]` one character from a set.
+    pub fn glob(pattern: &str) -> Result<Self, String> {
+        let regex = Regex::new(&format!("^{}$", glob_to_regex(pattern)))
+            .map_err(|e| format!("Invalid glob {:?}: {}", pattern, e))?;
+        Ok(Pattern { source: pattern.to_owned(), regex })
+    }
 
-        let selection: HashMap<&str, Coordinates> =
-            selection.iter().map(|(k, v)| (*k, v.clone().into())).collect();
+    /// The pattern as it was written.
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
 
-        let parents = WalkPair { left: root, right: result.root() };
+    pub fn is_match(&self, value: &str) -> bool {
+        self.regex.is_match(value)
+    }
+}
 
-        self.select_recurse(&selection, &mut result, parents)?;
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.regex.as_str() == other.regex.as_str()
+    }
+}
 
-        // Prune any nodes which do not have all selected dimensions
-        if mode == SelectMode::Prune {
-            let mut has_none_of: HashSet<&str> = HashSet::new();
-            for key in selection.keys() {
-                has_none_of.insert(*key);
+fn glob_to_regex(glob: &str) -> String {
+    let mut out = String::new();
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => out.push_str(".*"),
+            '?' => out.push('.'),
+            '[' if glob_class_end(chars.clone()) => {
+                out.push('[');
+                if chars.next_if(|&c| c == '!' || c == '^').is_some() {
+                    out.push('^');
+                }
+                let mut first = true;
+                for c in chars.by_ref() {
+                    match c {
+                        ']' if !first => break,
+                        '\\' | '[' | ']' | '&' | '~' => {
+                            out.push('\\');
+                            out.push(c);
+                        }
+                        c => out.push(c),
+                    }
+                    first = false;
+                }
+                out.push(']');
             }
-
-            let result_root = result.root();
-            result.prune(result_root, has_none_of);
+            c => out.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
         }
-
-        Ok(result)
     }
+    out
+}
 
-    fn select_recurse(
-        &self,
-        selection: &HashMap<&str, Coordinates>,
-        result: &mut Qube,
-        parents: WalkPair,
-    ) -> Result<(), String> {
-        let source_node =
-            self.node(parents.left).ok_or_else(|| format!("Node {:?} not found", parents.left))?;
+/// Whether a glob character class that has just been opened is closed again;
+/// an unclosed `[` is matched literally.
+fn glob_class_end(rest: impl Iterator<Item = char>) -> bool {
+    let mut rest = rest.peekable();
+    rest.next_if(|&c| c == '!' || c == '^');
+    rest.next(); // A `]` straight after the `[` is part of the class.
+    rest.any(|c| c == ']')
+}
 
-        // For each child in the source Qube, find the values which overlap and create a child in the result Qube
-        // We ignore values only_in_a and only_in_b, we only want the intersection
+impl Selector {
+    /// `lo <= value <= hi`.
+    pub fn between(lo: impl Into<CoordinateTypes>, hi: impl Into<CoordinateTypes>) -> Self {
+        Selector::Range { lo: lo.into(), hi: hi.into(), inclusive: true }
+    }
 
-        // Get the dimension of each chil
-        let span = source_node.child_dimensions();
+    /// `value > bound`.
+    pub fn gt(bound: impl Into<CoordinateTypes>) -> Self {
+        Selector::GreaterThan { value: bound.into(), inclusive: false }
+    }
 
-        for dimension in span {
-            let dimension_str = self.dimension_str(dimension).ok_or_else(|| {
-                format!("Dimension {:?} not found in key store. Should not happen.", dimension)
-            })?;
+    /// `value >= bound`.
+    pub fn ge(bound: impl Into<CoordinateTypes>) -> Self {
+        Selector::GreaterThan { value: bound.into(), inclusive: true }
+    }
 
-            if selection.contains_key(dimension_str) {
-                let selection_coordinates = selection.get(dimension_str).unwrap();
+    /// `value < bound`.
+    pub fn lt(bound: impl Into<CoordinateTypes>) -> Self {
+        Selector::LessThan { value: bound.into(), inclusive: false }
+    }
 
-                // Get children for this dimension
-                let source_children: Vec<_> = match source_node.children(*dimension) {
-                    Some(iter) => iter.collect(),
-                    None => continue, // Skip this dimension if no children
-                };
+    /// `value <= bound`.
+    pub fn le(bound: impl Into<CoordinateTypes>) -> Self {
+        Selector::LessThan { value: bound.into(), inclusive: true }
+    }
 
-                for child_id in source_children {
-                    let child_node = self
-                        .node(child_id)
-                        .ok_or_else(|| format!("Child node {:?} not found", child_id))?;
+    /// String values matching the glob `pattern`, e.g. `Selector::glob("0*")`.
+    pub fn glob(pattern: &str) -> Result<Self, String> {
+        Pattern::glob(pattern).map(Selector::Pattern)
+    }
 
-                    let coordinates = child_node.coordinates();
+    /// String values matching the anchored regular expression `pattern`.
+    pub fn regex(pattern: &str) -> Result<Self, String> {
+        Pattern::regex(pattern).map(Selector::Pattern)
+    }
 
-                    let intersection_result = coordinates.intersect(selection_coordinates);
-                    let intersection = intersection_result.intersection;
+    /// Values that `selector` does not match, e.g. `Selector::not(167)`.
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(selector: impl Into<Selector>) -> Self {
+        Selector::Not(Box::new(selector.into()))
+    }
 
-                    if intersection.is_empty() {
-                        continue;
-                    }
+    /// This selector as it applies to `dimension`.  For the dimensions in
+    /// [`DURATION_DIMENSIONS`], strings such as `6h` are read as durations,
+    /// as [`Coordinates::from_dimension_string`] reads them, so that
+    /// `("step", "6h")` selects a step stored as a duration.
+    pub(crate) fn for_dimension(self, dimension: &str) -> Selector {
+        if !DURATION_DIMENSIONS.contains(&dimension) {
+            return self;
+        }
+        match self {
+            Selector::Values(Coordinates::Strings(StringCoordinates::Set(strings))) => {
+                let mut coords = Coordinates::Empty;
+                for s in strings.iter() {
+                    coords.extend(&Coordinates::from_dimension_string(dimension, s.as_str()));
+                }
+                Selector::Values(coords)
+            }
+            Selector::Not(inner) => Selector::Not(Box::new(inner.for_dimension(dimension))),
+            Selector::AnyOf(selectors) => {
+                Selector::AnyOf(selectors.into_iter().map(|s| s.for_dimension(dimension)).collect())
+            }
+            other => other,
+        }
+    }
 
-                    let new_child = result.get_or_create_child(
-                        dimension_str,
-                        parents.right,
-                        Some(intersection),
-                    )?;
-
-                    // Propagate source-node metadata to the result node so that
-                    // location metadata (pushed down from root on append) is
-                    // preserved through the select.
-                    let child_meta = self.get_node_metadata(child_id).cloned().unwrap_or_default();
-                    if !child_meta.is_empty() {
-                        if let Some(node) = result.node_mut(new_child) {
-                            *node.metadata_mut() = child_meta;
+    /// One bit per value of `coords`, in the order of `Coordinates::values`,
+    /// set for the values this selects.
+    ///
+    /// Value lists, and ranges over integers or date-times, are intersected
+    /// with `coords` as a whole.  `Not` and `Pattern`, and ranges over other
+    /// types, are matched value by value.
+    pub(crate) fn mask(&self, coords: &Coordinates, tolerance: FloatTolerance) -> SmallBitVec {
+        match self {
+            Selector::Values(values) => intersection_mask(coords, values, tolerance),
+            Selector::Range { .. } | Selector::GreaterThan { .. } | Selector::LessThan { .. } => {
+                match self.range_within(coords) {
+                    Some(range) => intersection_mask(coords, &range, tolerance),
+                    None => coords.values().map(|v| self.matches(&v, tolerance)).collect(),
+                }
+            }
+            Selector::AnyOf(selectors) => {
+                let mut mask = SmallBitVec::from_elem(coords.len(), false);
+                for selector in selectors {
+                    for (i, selected) in selector.mask(coords, tolerance).iter().enumerate() {
+                        if selected {
+                            mask.set(i, true);
                         }
                     }
+                }
+                mask
+            }
+            Selector::Wildcard => SmallBitVec::from_elem(coords.len(), true),
+            Selector::Not(_) | Selector::Pattern(_) => {
+                coords.values().map(|v| self.matches(&v, tolerance)).collect()
+            }
+        }
+    }
 
-                    let new_parents = WalkPair { left: child_id, right: new_child };
-
-                    self.select_recurse(selection, result, new_parents)?;
-
-                    // If the newly created result node ended up with no children,
-                    // and the source node was NOT a leaf (i.e., had children of its
-                    // own), then no further selected dimensions matched anywhere
-                    // beneath it.  Remove the placeholder so it doesn't pollute the
-                    // result.  Leaf nodes (source_child_count == 0) are always kept.
-                    let source_child_count = self
-                        .node(child_id)
-                        .ok_or_else(|| format!("Source node {:?} not found", child_id))?
-                        .children_count();
-                    let result_child_count = result
-                        .node(new_child)
-                        .ok_or_else(|| format!("Result node {:?} not found", new_child))?
-                        .children_count();
-                    if source_child_count > 0 && result_child_count == 0 {
-                        result.remove_node(new_child).map_err(|e| {
-                            format!("Failed to remove result node {:?}: {:?}", new_child, e)
-                        })?;
-                    }
+    /// Every value between the bounds of a range selector, as coordinates of
+    /// the same type as `coords`, or `None` if that type has no such form.
+    /// Open ends stop at the extremes of `coords`, date-times go in steps of
+    /// one nanosecond so that none are missed.
+    fn range_within(&self, coords: &Coordinates) -> Option<Coordinates> {
+        let (lo, hi) = match self {
+            Selector::Range { lo, hi, inclusive } => {
+                (Some((lo, *inclusive)), Some((hi, *inclusive)))
+            }
+            Selector::GreaterThan { value, inclusive } => (Some((value, *inclusive)), None),
+            Selector::LessThan { value, inclusive } => (None, Some((value, *inclusive))),
+            _ => return None,
+        };
+
+        match coords {
+            Coordinates::Integers(_) => {
+                let bound =
+                    |bound: Option<(&CoordinateTypes, bool)>, open: i32, inward: i64| match bound {
+                        None => Some(open as i64),
+                        Some((CoordinateTypes::Integer(v), inclusive)) => {
+                            Some(*v as i64 + if inclusive { 0 } else { inward })
+                        }
+                        Some(_) => None,
+                    };
+                let (lo, hi) = (bound(lo, i32::MIN, 1)?, bound(hi, i32::MAX, -1)?);
+                // Past the ends of `i32`, an exclusive bound leaves nothing.
+                if lo > hi {
+                    return Some(Coordinates::Empty);
                 }
-            } else {
-                // Dimension not in selection, so we take all children.
-                // However, we must only keep a child in the result if the
-                // recursive call into it actually produced something — otherwise
-                // we end up with empty branches for nodes whose descendants
-                // contain none of the selected values.
-                let source_children: Vec<_> = match source_node.children(*dimension) {
-                    Some(iter) => iter.collect(),
-                    None => continue, // Skip this dimension if no children
+                Some(
+                    IntegerCoordinates::from_range(lo as i32, hi as i32, 1)
+                        .map_or(Coordinates::Empty, Coordinates::Integers),
+                )
+            }
+            Coordinates::DateTimes(datetimes) => {
+                let step = Duration::nanoseconds(1);
+                let bound = |bound: Option<(&CoordinateTypes, bool)>,
+                             open: Option<NaiveDateTime>,
+                             inward: Duration| match bound {
+                    None => open,
+                    Some((CoordinateTypes::DateTime(v), true)) => Some(*v),
+                    Some((CoordinateTypes::DateTime(v), false)) => v.checked_add_signed(inward),
+                    Some(_) => None,
                 };
+                let lo = bound(lo, datetimes.iter().next(), step)?;
+                let hi = bound(hi, datetimes.iter().next_back(), -step)?;
+                Some(
+                    DateTimeCoordinates::from_range(lo, hi, step)
+                        .map_or(Coordinates::Empty, Coordinates::DateTimes),
+                )
+            }
+            _ => None,
+        }
+    }
 
-                for child_id in source_children {
-                    let child_node = self
-                        .node(child_id)
-                        .ok_or_else(|| format!("Child node {:?} not found", child_id))?;
+    /// Whether `value` is selected.
+    pub fn matches(&self, value: &CoordinateTypes, tolerance: FloatTolerance) -> bool {
+        let above = |bound: &CoordinateTypes, inclusive: bool| match value.compare(bound) {
+            Some(Ordering::Greater) => true,
+            Some(Ordering::Equal) => inclusive,
+            _ => false,
+        };
+        let below = |bound: &CoordinateTypes, inclusive: bool| match value.compare(bound) {
+            Some(Ordering::Less) => true,
+            Some(Ordering::Equal) => inclusive,
+            _ => false,
+        };
+
+        match self {
+            Selector::Values(coords) => coords.contains_with_tolerance(value.clone(), tolerance),
+            Selector::Range { lo, hi, inclusive } => above(lo, *inclusive) && below(hi, *inclusive),
+            Selector::GreaterThan { value: bound, inclusive } => above(bound, *inclusive),
+            Selector::LessThan { value: bound, inclusive } => below(bound, *inclusive),
+            Selector::Not(inner) => !inner.matches(value, tolerance),
+            Selector::AnyOf(selectors) => selectors.iter().any(|s| s.matches(value, tolerance)),
+            Selector::Wildcard => true,
+            Selector::Pattern(pattern) => match value {
+                CoordinateTypes::String(s) => pattern.is_match(s),
+                _ => false,
+            },
+        }
+    }
+}
+
+/// A short human-readable form, e.g. `1/2/3`, `>=10`, `0..=6` or `not(od)`.
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let eq = |inclusive: bool| if inclusive { "=" } else { "" };
+        match self {
+            Selector::Values(coords) => write!(f, "{}", coords.to_string()),
+            Selector::Range { lo, hi, inclusive } => write!(f, "{}..{}{}", lo, eq(*inclusive), hi),
+            Selector::GreaterThan { value, inclusive } => write!(f, ">{}{}", eq(*inclusive), value),
+            Selector::LessThan { value, inclusive } => write!(f, "<{}{}", eq(*inclusive), value),
+            Selector::Not(inner) => write!(f, "not({})", inner),
+            Selector::AnyOf(selectors) => {
+                let parts: Vec<String> = selectors.iter().map(|s| s.to_string()).collect();
+                write!(f, "{}", parts.join(" | "))
+            }
+            Selector::Wildcard => write!(f, "*"),
+            Selector::Pattern(pattern) => write!(f, "{}", pattern.as_str()),
+        }
+    }
+}
 
-                    let coordinates = child_node.coordinates();
+impl<T: Into<Coordinates>> From<T> for Selector {
+    fn from(value: T) -> Self {
+        Selector::Values(value.into())
+    }
+}
 
-                    let new_child = result.get_or_create_child(
-                        dimension_str,
-                        parents.right,
-                        Some(coordinates.clone()),
-                    )?;
+impl Qube {
+    // Select takes a dictionary of key-vecvalues pairs and returns a new Qube
+    // It does not matter which order the keys are specified
 
-                    // Propagate source-node metadata to the result node.
-                    let child_meta = self.get_node_metadata(child_id).cloned().unwrap_or_default();
-                    if !child_meta.is_empty() {
-                        if let Some(node) = result.node_mut(new_child) {
-                            *node.metadata_mut() = child_meta;
-                        }
-                    }
+    pub fn select<C>(&self, selection: &[(&str, C)], mode: SelectMode) -> Result<Qube, String>
+    where
+        C: Into<Selector> + Clone,
+    {
+        Ok(self.select_view(selection, mode)?.materialize())
+    }
 
-                    let new_parents = WalkPair { left: child_id, right: new_child };
-
-                    self.select_recurse(selection, result, new_parents)?;
-
-                    // If the newly created result node ended up with no children,
-                    // and the source node was NOT a leaf (i.e., had children of
-                    // its own), then the subtree contained nothing matching the
-                    // selection.  Remove the placeholder so it doesn't pollute
-                    // the result.  Leaf nodes (source_child_count == 0) are
-                    // always kept — their coordinates are the payload.
-                    let source_child_count = self
-                        .node(child_id)
-                        .ok_or_else(|| format!("Source node {:?} not found", child_id))?
-                        .children_count();
-                    let result_child_count = result
-                        .node(new_child)
-                        .ok_or_else(|| format!("Result node {:?} not found", new_child))?
-                        .children_count();
-                    if source_child_count > 0 && result_child_count == 0 {
-                        result.remove_node(new_child).map_err(|e| {
-                            format!("Failed to remove result node {:?}: {:?}", new_child, e)
-                        })?;
-                    }
-                }
+    /// Like [`Qube::select`], but returns a [`QubeView`] of this Qube instead
+    /// of copying the selected identifiers into a new one.
+    pub fn select_view<C>(
+        &self,
+        selection: &[(&str, C)],
+        mode: SelectMode,
+    ) -> Result<QubeView<'_>, String>
+    where
+        C: Into<Selector> + Clone,
+    {
+        QubeView::new(self).select(selection, mode)
+    }
+
+    /// Keeps the identifiers allowed by `mask`: those that agree with at least
+    /// one of the mask's identifiers on every dimension they share.  A
+    /// dimension the mask leaves out allows any value, so a mask of
+    /// `class=od, param=1/2` and `class=rd, param=3` keeps every `expver`,
+    /// `date` and so on, but only the listed params for each class.
+    ///
+    /// With [`SelectMode::Prune`], an identifier must also have every
+    /// dimension of the mask identifier it matches.  [`SelectMode::Strict`]
+    /// does the same, and fails if the mask has a dimension this Qube lacks.
+    /// An empty mask allows nothing.  The result keeps this Qube's metadata.
+    pub fn select_qube(&self, mask: &Qube, mode: SelectMode) -> Result<Qube, String> {
+        let mut result = Qube::new();
+        result.copy_float_tolerances(self);
+
+        let root_meta = self.get_node_metadata(self.root()).cloned().unwrap_or_default();
+        if !root_meta.is_empty() {
+            let result_root = result.root();
+            if let Some(node) = result.node_mut(result_root) {
+                *node.metadata_mut() = root_meta;
             }
         }
 
-        Ok(())
+        if mask.is_empty() {
+            return Ok(result);
+        }
+
+        if mode == SelectMode::Strict {
+            let dims: Vec<Dimension> = mask.all_dim_ids();
+            self.check_dimensions_exist(dims.iter().filter_map(|d| mask.dimension_str(d)))?;
+        }
+
+        // Each identifier of the mask, as the values it allows per dimension.
+        let mut rules: Vec<HashMap<&str, &Coordinates>> = Vec::new();
+        for path in mask.leaf_node_ids_paths() {
+            let mut rule = HashMap::new();
+            for id in path.into_iter().skip(1) {
+                let node =
+                    mask.node_ref(id).ok_or_else(|| format!("Mask node {:?} not found", id))?;
+                let dim = mask.dimension_str(node.dim()).ok_or_else(|| {
+                    format!("Dimension of mask node {:?} not found in key store", id)
+                })?;
+                rule.insert(dim, node.coords());
+            }
+            rules.push(rule);
+        }
+
+        let alive: Vec<usize> = (0..rules.len()).collect();
+        let walk = MaskWalk { rules: &rules, mode };
+        let result_root = result.root();
+        walk.select(self, self.root(), &alive, &mut Vec::new(), &mut result, result_root)?;
+
+        Ok(result)
+    }
+
+    /// Fails with the sorted list of `keys` that are not the dimension of any
+    /// node, so a misspelt key in a [`SelectMode::Strict`] selection is
+    /// reported instead of silently matching everything.
+    pub(crate) fn check_dimensions_exist<'k>(
+        &self,
+        keys: impl IntoIterator<Item = &'k str>,
+    ) -> Result<(), String> {
+        let known: HashSet<&str> =
+            self.all_dim_ids().iter().filter_map(|d| self.dimension_str(d)).collect();
+        check_keys_known(&known, keys)
     }
 
     // TODO: "has_none_of" needs a better name. Or the whole method needs a better name
@@ -256,9 +501,112 @@ impl Qube {
     }
 }
 
+/// One bit per value of `coords`, set for those also in `selected`.
+fn intersection_mask(
+    coords: &Coordinates,
+    selected: &Coordinates,
+    tolerance: FloatTolerance,
+) -> SmallBitVec {
+    let both = coords.intersect_with_tolerance(selected, tolerance).intersection;
+    coords.values().map(|v| both.contains_with_tolerance(v, tolerance)).collect()
+}
+
+/// Walks a Qube for [`Qube::select_qube`], tracking which mask identifiers
+/// (`rules`) are still satisfied by the values chosen so far.
+struct MaskWalk<'m> {
+    rules: &'m [HashMap<&'m str, &'m Coordinates>],
+    mode: SelectMode,
+}
+
+impl MaskWalk<'_> {
+    /// Copies the children of `source` that some `alive` rule allows to
+    /// `target`.  Values of one node that leave different rules alive go to
+    /// separate nodes, since they allow different identifiers below.
+    fn select(
+        &self,
+        qube: &Qube,
+        source: NodeIdx,
+        alive: &[usize],
+        dims: &mut Vec<String>,
+        result: &mut Qube,
+        target: NodeIdx,
+    ) -> Result<(), String> {
+        let source_node =
+            qube.node(source).ok_or_else(|| format!("Node {:?} not found", source))?;
+
+        for child_id in source_node.all_children() {
+            let child = qube
+                .node(child_id)
+                .ok_or_else(|| format!("Child node {:?} not found", child_id))?;
+            let dim = child.dimension().ok_or_else(|| {
+                format!("Dimension of node {:?} not found in key store", child_id)
+            })?;
+            let tolerance = qube.float_tolerance(dim);
+            let is_leaf = child.children_count() == 0;
+            dims.push(dim.to_owned());
+
+            // Group the values by the rules that allow them.
+            let coords = child.coordinates();
+            let masks: Vec<Option<SmallBitVec>> = alive
+                .iter()
+                .map(|&rule| {
+                    self.rules[rule].get(dim).map(|c| intersection_mask(coords, c, tolerance))
+                })
+                .collect();
+            let mut groups: BTreeMap<Vec<usize>, Coordinates> = BTreeMap::new();
+            for (i, value) in coords.values().enumerate() {
+                let allowed: Vec<usize> = alive
+                    .iter()
+                    .zip(&masks)
+                    .filter(|(_, mask)| mask.as_ref().is_none_or(|mask| mask[i]))
+                    .map(|(&rule, _)| rule)
+                    .filter(|&rule| !is_leaf || self.is_complete(rule, dims))
+                    .collect();
+                if !allowed.is_empty() {
+                    groups.entry(allowed).or_default().append(value);
+                }
+            }
+
+            for (allowed, coords) in groups {
+                let new_child = result.get_or_create_child(dim, target, Some(coords))?;
+
+                let child_meta = child.metadata();
+                if !child_meta.is_empty()
+                    && let Some(node) = result.node_mut(new_child)
+                {
+                    *node.metadata_mut() = child_meta.clone();
+                }
+
+                self.select(qube, child_id, &allowed, dims, result, new_child)?;
+
+                // Drop branches with nothing left below them.
+                let result_child_count = result
+                    .node(new_child)
+                    .ok_or_else(|| format!("Result node {:?} not found", new_child))?
+                    .children_count();
+                if !is_leaf && result_child_count == 0 {
+                    result.remove_node(new_child)?;
+                }
+            }
+
+            dims.pop();
+        }
+
+        Ok(())
+    }
+
+    /// Whether an identifier with `dims` has every dimension `rule` constrains,
+    /// as [`SelectMode::Prune`] and [`SelectMode::Strict`] require.
+    fn is_complete(&self, rule: usize, dims: &[String]) -> bool {
+        self.mode == SelectMode::Default
+            || self.rules[rule].keys().all(|dim| dims.iter().any(|d| d == dim))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{FloatTolerance, MetadataValues};
 
     // TODO: improve test with a more complicated example. Build from a string first.
     #[test]
@@ -566,4 +914,414 @@ mod tests {
         );
         Ok(())
     }
+
+    #[test]
+    fn test_select_floats_within_dimension_tolerance() -> Result<(), String> {
+        let input = r#"root
+├── class=od
+│   └── levelist=0.25/0.5/0.75
+└── class=rd
+    └── levelist=0.9"#;
+        let mut qube = Qube::from_ascii(input)?;
+
+        let selection = [("levelist", &[0.26, 0.9][..])];
+        let exact = qube.select(&selection, SelectMode::Default)?;
+        let expected = Qube::from_ascii("root\n└── class=rd\n    └── levelist=0.9")?;
+        assert_eq!(exact.to_ascii(), expected.to_ascii());
+
+        qube.set_dimension_float_tolerance("levelist", FloatTolerance::new(0.02, 0.0));
+        assert_eq!(qube.float_tolerance("class"), FloatTolerance::default());
+        let near = qube.select(&selection, SelectMode::Default)?;
+        let expected = Qube::from_ascii(
+            "root\n├── class=od\n│   └── levelist=0.25\n└── class=rd\n    └── levelist=0.9",
+        )?;
+        assert_eq!(near.to_ascii(), expected.to_ascii());
+        assert_eq!(near.float_tolerance("levelist"), FloatTolerance::new(0.02, 0.0));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_select_with_comparisons_and_negation() -> Result<(), String> {
+        let input = r#"root
+├── class=od
+│   └── date=20231231/20240101/20240102
+│       └── step=0/24/48/72
+│           └── param=167/168
+└── class=rd
+    └── date=20231230
+        └── step=0
+            └── param=168"#;
+        let qube = Qube::from_ascii(input)?;
+
+        let selected = qube.select(
+            &[
+                ("date", Selector::ge(20240101)),
+                ("step", Selector::lt(48)),
+                ("param", Selector::not(167)),
+            ],
+            SelectMode::Default,
+        )?;
+
+        let expected = r#"root
+└── class=od
+    └── date=20240101/20240102
+        └── step=0/24
+            └── param=168"#;
+        assert_eq!(selected.to_ascii(), Qube::from_ascii(expected)?.to_ascii());
+        Ok(())
+    }
+
+    #[test]
+    fn test_selectors_across_types() {
+        let tol = FloatTolerance::default();
+        let v = |s: &str| Coordinates::from_string(s).values().next().unwrap();
+
+        let levels = Selector::Range {
+            lo: CoordinateTypes::Float(0.5),
+            hi: CoordinateTypes::Integer(2),
+            inclusive: false,
+        };
+        assert!(levels.matches(&v("1.5"), tol));
+        assert!(levels.matches(&v("1"), tol));
+        assert!(!levels.matches(&v("2"), tol));
+        assert!(Selector::between(0.5, 2).matches(&v("2"), tol));
+
+        assert!(Selector::lt("n").matches(&v("an"), tol));
+        assert!(!Selector::lt("n").matches(&v("od"), tol));
+        // A string cannot be compared with a number, so never matches a bound.
+        assert!(!Selector::lt(5).matches(&v("od"), tol));
+        assert!(Selector::not(Selector::lt(5)).matches(&v("od"), tol));
+
+        // Integer bounds on date-times are read as YYYYMMDD dates.
+        assert!(Selector::ge(20240101).matches(&v("2024-01-01T06:00:00"), tol));
+        assert!(!Selector::ge(20240101).matches(&v("2023-12-31T18:00:00"), tol));
+
+        let step = |s: &str| Coordinates::from_dimension_string("step", s).values().next().unwrap();
+        assert!(Selector::gt(step("6h")).matches(&step("12h"), tol));
+
+        let any = Selector::AnyOf(vec![Selector::from(&["od"]), Selector::gt(10)]);
+        assert!(any.matches(&v("od"), tol));
+        assert!(any.matches(&v("11"), tol));
+        assert!(!any.matches(&v("rd"), tol));
+        assert!(Selector::Wildcard.matches(&v("rd"), tol));
+    }
+
+    #[test]
+    fn test_masks_agree_with_matching_each_value() {
+        let tol = FloatTolerance::new(0.02, 0.0);
+        let dt =
+            |s: &str| CoordinateTypes::DateTime(DateTimeCoordinates::parse_from_str(s).unwrap());
+        let cases = [
+            ("1/to/100000", Selector::between(10, 99990)),
+            ("1/to/100000/by/7", Selector::gt(70)),
+            ("1/to/100000/by/7", Selector::lt(i32::MIN)),
+            ("1/2/3/10/20", Selector::Range { lo: 2.into(), hi: 20.into(), inclusive: false }),
+            ("1/2/3/10/20", Selector::from(&[2, 10, 11])),
+            ("0.25/0.5/0.75", Selector::from(&[0.26, 0.9])),
+            ("0.25/0.5/0.75", Selector::ge(0.5)),
+            ("20231231/20240101/20240102", Selector::ge(20240101)),
+            ("od/rd/rr", Selector::AnyOf(vec![Selector::from("od"), Selector::gt("r")])),
+            ("od/rd/rr", Selector::not("rd")),
+            ("1/2/3", Selector::Wildcard),
+        ];
+        for (values, selector) in cases {
+            let coords = Coordinates::from_string(values);
+            let expected: SmallBitVec =
+                coords.values().map(|v| selector.matches(&v, tol)).collect();
+            assert_eq!(selector.mask(&coords, tol), expected, "{} on {}", selector, values);
+        }
+
+        let mut hourly = Coordinates::Empty;
+        for hour in 0..24 * 365 {
+            let start = DateTimeCoordinates::parse_from_str("2024-01-01T00:00:00Z").unwrap();
+            hourly.append(start + Duration::hours(hour));
+        }
+        for selector in [
+            Selector::gt(dt("2024-03-01T00:00:00Z")),
+            Selector::le(dt("2024-03-01T00:30:00Z")),
+            Selector::Range {
+                lo: dt("2024-02-01T00:00:00Z"),
+                hi: dt("2024-02-02T00:00:00Z"),
+                inclusive: false,
+            },
+            Selector::ge(20240301),
+        ] {
+            let expected: SmallBitVec =
+                hourly.values().map(|v| selector.matches(&v, tol)).collect();
+            assert_eq!(selector.mask(&hourly, tol), expected, "{}", selector);
+        }
+    }
+
+    #[test]
+    fn test_select_range_from_long_integer_range() -> Result<(), String> {
+        let qube = Qube::from_ascii("root\n└── class=od\n    └── number=1/to/100000")?;
+
+        let selected =
+            qube.select(&[("number", Selector::between(11, 99989))], SelectMode::Default)?;
+        let expected = Qube::from_ascii("root\n└── class=od\n    └── number=11/to/99989")?;
+        assert_eq!(selected.to_ascii(), expected.to_ascii());
+
+        let view = qube.select_view(&[("number", Selector::gt(99998))], SelectMode::Default)?;
+        let expected = Qube::from_ascii("root\n└── class=od\n    └── number=99999/100000")?;
+        assert_eq!(view.materialize().to_ascii(), expected.to_ascii());
+        Ok(())
+    }
+
+    #[test]
+    fn test_select_with_anything_that_converts_to_coordinates() -> Result<(), String> {
+        let at = |hour| {
+            chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap().and_hms_opt(hour, 0, 0).unwrap()
+        };
+        let mut qube = Qube::new();
+        let root = qube.root();
+        let levels = Coordinates::from(&[0.5f32, 1.5, 2.5][..]);
+        let level = qube.get_or_create_child("levelist", root, Some(levels))?;
+        let times = Coordinates::from(&[at(0), at(6), at(12)][..]);
+        qube.get_or_create_child("time", level, Some(times))?;
+
+        let selected = qube.select(&[("levelist", 1.5f32)], SelectMode::Default)?;
+        let selected = selected.select(&[("time", &[at(6), at(12)])], SelectMode::Default)?;
+
+        let mut expected = Qube::new();
+        let root = expected.root();
+        let level = expected.get_or_create_child("levelist", root, Some(1.5f32.into()))?;
+        expected.get_or_create_child("time", level, Some((&[at(6), at(12)]).into()))?;
+        assert_eq!(selected.to_ascii(), expected.to_ascii());
+        Ok(())
+    }
+
+    #[test]
+    fn test_select_duration_steps_by_string() -> Result<(), String> {
+        let qube = Qube::from_ascii(
+            r#"root
+├── class=od
+│   └── step=0h/6h/12h
+└── class=rd
+    └── step=1h"#,
+        )?;
+        assert!(matches!(
+            qube.node(qube.root()).unwrap().all_children().next().map(|c| {
+                let child = qube.node(c).unwrap().all_children().next().unwrap();
+                qube.node(child).unwrap().coordinates().clone()
+            }),
+            Some(Coordinates::Durations(_))
+        ));
+
+        let expected = Qube::from_ascii("root\n└── class=od\n    └── step=6h")?;
+        let selected = qube.select(&[("step", "6h")], SelectMode::Default)?;
+        assert_eq!(selected.to_ascii(), expected.to_ascii());
+        let selected = qube.select(&[("step", &["6h", "1h"])], SelectMode::Prune)?;
+        assert_eq!(selected.to_ascii(), Qube::from_ascii(
+            "root\n├── class=od\n│   └── step=6h\n└── class=rd\n    └── step=1h",
+        )?.to_ascii());
+
+        let not_six = qube.select(&[("step", Selector::not("6h"))], SelectMode::Default)?;
+        let expected = Qube::from_ascii(
+            "root\n├── class=od\n│   └── step=0h/12h\n└── class=rd\n    └── step=1h",
+        )?;
+        assert_eq!(not_six.to_ascii(), expected.to_ascii());
+        Ok(())
+    }
+
+    #[test]
+    fn test_wildcard_requires_dimension_when_pruning() -> Result<(), String> {
+        let input = r#"root
+├── class=od
+│   └── levtype=sfc
+│       └── param=1
+└── class=rd
+    └── param=1"#;
+        let qube = Qube::from_ascii(input)?;
+
+        let all = qube.select(&[("levtype", Selector::Wildcard)], SelectMode::Default)?;
+        assert_eq!(all.to_ascii(), qube.to_ascii());
+
+        let pruned = qube.select(&[("levtype", Selector::Wildcard)], SelectMode::Prune)?;
+        let expected =
+            Qube::from_ascii("root\n└── class=od\n    └── levtype=sfc\n        └── param=1")?;
+        assert_eq!(pruned.to_ascii(), expected.to_ascii());
+        Ok(())
+    }
+
+    #[test]
+    fn test_strict_requires_every_dimension_on_the_leaf_path() -> Result<(), String> {
+        let input = r#"root
+├── class=od
+│   ├── levtype=sfc
+│   │   └── step=0
+│   └── param=1
+│       └── step=0
+└── class=rd
+    └── levtype=sfc
+        └── param=1/2"#;
+        let qube = Qube::from_ascii(input)?;
+        let selection = [("levtype", Selector::Wildcard), ("param", Selector::from(1))];
+
+        // Pruning keeps class=od, whose subtree has both dimensions, just not
+        // on the same branch.
+        let pruned = qube.select(&selection, SelectMode::Prune)?;
+        assert_eq!(pruned.datacube_count(), 2);
+
+        let strict = qube.select(&selection, SelectMode::Strict)?;
+        let expected =
+            Qube::from_ascii("root\n└── class=rd\n    └── levtype=sfc\n        └── param=1")?;
+        assert_eq!(strict.to_ascii(), expected.to_ascii());
+
+        let none = qube.select(&[("step", Selector::Wildcard)], SelectMode::Strict)?;
+        assert_eq!(none.datacube_count(), 2);
+        assert!(qube.select(&[("expver", 1)], SelectMode::Strict).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_strict_rejects_unknown_keys() -> Result<(), String> {
+        let qube = Qube::from_ascii("root\n└── class=od\n    └── param=1/2")?;
+
+        let err = qube
+            .select(&[("parm", 2), ("class", 1), ("levtpye", 1)], SelectMode::Strict)
+            .unwrap_err();
+        assert_eq!(err, "Selection keys not found in the Qube: levtpye, parm");
+
+        // Other modes still treat an unknown key as constraining nothing.
+        let selected = qube.select(&[("parm", 2)], SelectMode::Default)?;
+        assert_eq!(selected.to_ascii(), qube.to_ascii());
+
+        let mask = Qube::from_ascii("root\n└── parm=2")?;
+        assert!(qube.select_qube(&mask, SelectMode::Strict).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_glob_patterns() -> Result<(), String> {
+        let cases = [
+            ("0*", "0001", true),
+            ("0*", "1001", false),
+            ("00?1", "0011", true),
+            ("00?1", "00111", false),
+            ("[ab]c", "bc", true),
+            ("[!ab]c", "bc", false),
+            ("[!ab]c", "dc", true),
+            ("[0-2]x", "1x", true),
+            ("[]]", "]", true),
+            ("a.b", "axb", false),
+            ("a[b", "a[b", true),
+        ];
+        for (glob, value, expected) in cases {
+            assert_eq!(Pattern::glob(glob)?.is_match(value), expected, "{glob} ~ {value}");
+        }
+
+        let regex = Pattern::regex("00.1|x+")?;
+        assert!(regex.is_match("0001") && regex.is_match("xxx"));
+        assert!(!regex.is_match("00010") && !regex.is_match("axx"));
+        assert!(Pattern::regex("(").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_select_with_patterns_keeps_matching_subset() -> Result<(), String> {
+        let input = r#"root
+├── class=od
+│   └── expver=0001/0002/1001
+│       └── param=1
+└── class=rd
+    └── expver=1002/prod/test
+        └── param=1"#;
+        let qube = Qube::from_ascii(input)?;
+
+        let by_glob = qube.select(&[("expver", Selector::glob("0*")?)], SelectMode::Default)?;
+        let expected = r#"root
+└── class=od
+    └── expver=0001/0002
+        └── param=1"#;
+        assert_eq!(by_glob.to_ascii(), Qube::from_ascii(expected)?.to_ascii());
+
+        let by_regex =
+            qube.select(&[("expver", Selector::regex("[a-z]+")?)], SelectMode::Default)?;
+        let expected = r#"root
+└── class=rd
+    └── expver=prod/test
+        └── param=1"#;
+        assert_eq!(by_regex.to_ascii(), Qube::from_ascii(expected)?.to_ascii());
+
+        // Patterns only apply to string values.
+        let none = qube.select(&[("param", Selector::glob("*")?)], SelectMode::Default)?;
+        assert_eq!(none.datacube_count(), 1);
+        assert!(none.to_ascii().trim() == "root");
+        Ok(())
+    }
+
+    fn access_rules() -> Qube {
+        let mask = r#"root
+├── class=od
+│   └── param=1/2
+└── class=rd
+    └── param=3"#;
+        Qube::from_ascii(mask).unwrap()
+    }
+
+    #[test]
+    fn test_select_qube_applies_rules_per_branch() -> Result<(), String> {
+        let input = r#"root
+├── class=od
+│   └── expver=0001/0002
+│       └── param=1/2/3
+└── class=rd
+    └── expver=0001
+        └── param=1/2/3"#;
+        let qube = Qube::from_ascii(input)?;
+
+        let selected = qube.select_qube(&access_rules(), SelectMode::Default)?;
+        let expected = r#"root
+├── class=od
+│   └── expver=0001/0002
+│       └── param=1/2
+└── class=rd
+    └── expver=0001
+        └── param=3"#;
+        assert_eq!(selected.to_ascii(), Qube::from_ascii(expected)?.to_ascii());
+        Ok(())
+    }
+
+    #[test]
+    fn test_select_qube_splits_nodes_shared_by_rules() -> Result<(), String> {
+        let qube = Qube::from_ascii("root\n└── class=od/rd/rr\n    └── param=1/2/3")?;
+
+        let selected = qube.select_qube(&access_rules(), SelectMode::Default)?;
+        assert_eq!(selected.datacube_count(), 2);
+        let expected = r#"root
+├── class=od
+│   └── param=1/2
+└── class=rd
+    └── param=3"#;
+        assert_eq!(selected.to_ascii(), Qube::from_ascii(expected)?.to_ascii());
+
+        assert!(qube.select_qube(&Qube::new(), SelectMode::Default)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_select_qube_prune_and_metadata() -> Result<(), String> {
+        let input = r#"root
+├── class=od
+│   └── levtype=sfc
+│       └── param=1
+└── class=rd
+    └── param=3"#;
+        let mut qube = Qube::from_ascii(input)?;
+        let leaf = *qube.leaf_node_ids_paths()[0].last().unwrap();
+        qube.set_metadata(leaf, "path", MetadataValues::single_string("/data/od"))?;
+
+        let mask = Qube::from_ascii("root\n└── levtype=sfc/pl")?;
+        let all = qube.select_qube(&mask, SelectMode::Default)?;
+        assert_eq!(all.to_ascii(), qube.to_ascii());
+
+        let pruned = qube.select_qube(&mask, SelectMode::Prune)?;
+        assert_eq!(pruned.datacube_count(), 1);
+        let path = &pruned.leaf_node_ids_paths()[0];
+        assert!(path.iter().any(|&id| {
+            pruned.get_metadata(id, "path") == Some(&MetadataValues::single_string("/data/od"))
+        }));
+        Ok(())
+    }
 }
@@ -71,7 +71,7 @@ fn parse_children(
             .split_once("=")
             .ok_or(format!("Invalid node format: '{}', expected 'key=value'", content))?;
 
-        let coordinates = Coordinates::from_string(values);
+        let coordinates = Coordinates::from_dimension_string(key, values);
 
         let child = qube.get_or_create_child(key, parent, Some(coordinates))?;
 
@@ -225,4 +225,31 @@ mod tests {
         assert_eq!(input, serialized);
         assert_eq!(serialized, re_serialized);
     }
+
+    #[test]
+    fn test_ranges_roundtrip() {
+        let input = r#"root
+└── class=od
+    ├── date=20240101/to/20241231
+    │   └── step=0/to/360/by/6
+    └── time=2024-01-01T00:00:00/to/2024-01-03T00:00:00/by/12h
+"#;
+
+        let qube = Qube::from_ascii(input).unwrap();
+        assert_eq!(qube.to_ascii(), input);
+    }
+
+    #[test]
+    fn test_bare_dates_roundtrip() {
+        let input = r#"root
+├── date=2024-01-01
+│   └── time=2024-01-01T06:00:00
+└── date=2024-01-01/to/2024-01-03
+"#;
+
+        let qube = Qube::from_ascii(input).unwrap();
+        assert_eq!(qube.to_ascii(), input);
+        assert_eq!(Qube::from_yaml(&qube.to_yaml()).unwrap().to_ascii(), input);
+        assert_eq!(Qube::from_yaml(&qube.to_yaml_datacubes()).unwrap().to_ascii(), input);
+    }
 }
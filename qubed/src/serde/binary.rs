@@ -0,0 +1,1029 @@
+use std::collections::HashMap;
+use std::num::NonZeroU16;
+use std::ops::Range;
+
+use chrono::{DateTime, Duration, NaiveDateTime};
+use tiny_str::TinyString;
+use tiny_vec::TinyVec;
+
+use crate::coordinates::MixedCoordinates;
+use crate::coordinates::datetime::{DateTimeCoordinates, DateTimeRange};
+use crate::coordinates::durations::{DurationCoordinates, DurationValue};
+use crate::coordinates::floats::{FloatCoordinates, FloatTolerance};
+use crate::coordinates::integers::{IntegerCoordinates, IntegerRange};
+use crate::coordinates::strings::StringCoordinates;
+use crate::utils::tiny_ordered_set::TinyOrderedSet;
+use crate::{Coordinates, Dimension, Metadata, MetadataValues, NodeIdx, Qube};
+
+// ---------------- Layout ----------------
+//
+// Integers are LEB128 varints, zigzag encoded when signed; floats are 8 bytes
+// little-endian; strings are a length followed by UTF-8 bytes.
+//
+//   "QUBE", version byte
+//   float tolerances: default absolute and relative, then a count and
+//       (dimension name, absolute, relative) for each dimension
+//   dimension table: count, then each name, in interning order
+//   string dictionary: count, then each string
+//   node count, then the nodes depth-first from the root, each as its
+//       dimension index, coordinates, metadata and number of children
+//   index: the offset of each dictionary string, then the offset and
+//       number of descendants of each node, as 8-byte little-endian integers
+//   offset of the index, 8 bytes little-endian
+//   CRC-32 of everything before it, 4 bytes little-endian
+//
+// String coordinates, metadata keys and string metadata are indices into the
+// dictionary.  Sorted integers are stored as deltas and integer runs as
+// (start, step, count), so ranges stay small.  The index lets a `MappedQube`
+// find any node or string without reading what comes before it.
+
+const MAGIC: &[u8; 4] = b"QUBE";
+const VERSION: u8 = 1;
+
+const EMPTY: u8 = 0;
+const INTEGERS: u8 = 1;
+const FLOATS: u8 = 2;
+const STRINGS: u8 = 3;
+const DATETIMES: u8 = 4;
+const DURATIONS: u8 = 5;
+const MIXED: u8 = 6;
+
+/// How integer and date-time coordinates are stored: as a `LIST` of values,
+/// or as ranges, tagged `RANGES` for integers and `RANGE_SET` for date-times.
+const LIST: u8 = 0;
+const RANGES: u8 = 1;
+const RANGE_SET: u8 = 2;
+
+const META_EMPTY: u8 = 0;
+const META_INTEGERS: u8 = 1;
+const META_STRINGS: u8 = 2;
+const META_PER_COORD_STRINGS: u8 = 3;
+
+// ---------------- Binary Serialization ----------------
+
+impl Qube {
+    /// Serialises the Qube into a compact, versioned binary format that
+    /// [`Qube::from_bytes`] reads back, including metadata and float
+    /// tolerances.  Typically a third of the size of [`Qube::to_arena_json`], and
+    /// much faster to load for all but the smallest Qubes (see
+    /// `benches/bench_binary.rs`).
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut dims = self.all_dim_ids();
+        dims.sort();
+        let dim_index: HashMap<Dimension, u64> =
+            dims.iter().enumerate().map(|(i, &dim)| (dim, i as u64)).collect();
+
+        let mut nodes = Writer::default();
+        let mut offsets: Vec<usize> = Vec::new();
+        let mut child_counts: Vec<usize> = Vec::new();
+        let mut stack = vec![self.root()];
+        while let Some(id) = stack.pop() {
+            let node = self.node_ref(id).expect("valid node");
+            offsets.push(nodes.bytes.len());
+            nodes.varint(dim_index[node.dim()]);
+            nodes.coordinates(node.coords());
+            nodes.metadata(node.metadata());
+            let children = node.children().values().map(|ids| ids.len()).sum::<usize>();
+            nodes.varint(children as u64);
+            child_counts.push(children);
+            // Reversed, so the first child is popped next.
+            for ids in node.children().values().rev() {
+                stack.extend(ids.iter().rev());
+            }
+        }
+
+        let mut out = Writer::default();
+        out.bytes.extend_from_slice(MAGIC);
+        out.bytes.push(VERSION);
+
+        let (default_tolerance, dimension_tolerances) = self.float_tolerances();
+        out.float_tolerance(default_tolerance);
+        let mut dimension_tolerances: Vec<_> = dimension_tolerances.iter().collect();
+        dimension_tolerances.sort_by(|a, b| a.0.cmp(b.0));
+        out.varint(dimension_tolerances.len() as u64);
+        for (dim, &tolerance) in dimension_tolerances {
+            out.string(dim);
+            out.float_tolerance(tolerance);
+        }
+
+        out.varint(dims.len() as u64);
+        for dim in &dims {
+            out.string(self.dimension_str(dim).expect("dimension in key store"));
+        }
+
+        out.varint(nodes.dictionary.len() as u64);
+        let mut string_offsets = Vec::with_capacity(nodes.dictionary.len());
+        for s in &nodes.dictionary {
+            string_offsets.push(out.bytes.len());
+            out.string(s);
+        }
+
+        out.varint(offsets.len() as u64);
+        let nodes_start = out.bytes.len();
+        out.bytes.extend_from_slice(&nodes.bytes);
+
+        let index_start = out.bytes.len();
+        for offset in string_offsets {
+            out.bytes.extend_from_slice(&(offset as u64).to_le_bytes());
+        }
+        for (offset, descendants) in offsets.iter().zip(descendant_counts(&child_counts)) {
+            out.bytes.extend_from_slice(&((nodes_start + offset) as u64).to_le_bytes());
+            out.bytes.extend_from_slice(&(descendants as u64).to_le_bytes());
+        }
+        out.bytes.extend_from_slice(&(index_start as u64).to_le_bytes());
+
+        let checksum = crc32(&out.bytes);
+        out.bytes.extend_from_slice(&checksum.to_le_bytes());
+        out.bytes
+    }
+}
+
+/// The number of descendants of each node, given the number of children of
+/// each in depth-first order.
+fn descendant_counts(child_counts: &[usize]) -> Vec<usize> {
+    let mut descendants = vec![0; child_counts.len()];
+    // Walking backwards, the subtree sizes of a node's children are the last
+    // ones pushed.
+    let mut subtree_sizes: Vec<usize> = Vec::new();
+    for (i, &children) in child_counts.iter().enumerate().rev() {
+        let below: usize = subtree_sizes.drain(subtree_sizes.len() - children..).sum();
+        descendants[i] = below;
+        subtree_sizes.push(below + 1);
+    }
+    descendants
+}
+
+#[derive(Default)]
+struct Writer<'q> {
+    bytes: Vec<u8>,
+    dictionary: Vec<&'q str>,
+    dictionary_index: HashMap<&'q str, u64>,
+}
+
+impl<'q> Writer<'q> {
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.bytes.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.bytes.push(value as u8);
+    }
+
+    fn signed(&mut self, value: i64) {
+        self.varint(((value << 1) ^ (value >> 63)) as u64);
+    }
+
+    fn float(&mut self, value: f64) {
+        self.bytes.extend_from_slice(&value.to_bits().to_le_bytes());
+    }
+
+    fn string(&mut self, s: &str) {
+        self.varint(s.len() as u64);
+        self.bytes.extend_from_slice(s.as_bytes());
+    }
+
+    /// Writes the dictionary index of `s`, adding it if new.
+    fn interned(&mut self, s: &'q str) {
+        let next = self.dictionary.len() as u64;
+        let index = *self.dictionary_index.entry(s).or_insert_with(|| {
+            self.dictionary.push(s);
+            next
+        });
+        self.varint(index);
+    }
+
+    fn float_tolerance(&mut self, tolerance: FloatTolerance) {
+        self.float(tolerance.absolute);
+        self.float(tolerance.relative);
+    }
+
+    fn duration(&mut self, duration: Duration) {
+        self.signed(duration.num_seconds());
+        self.signed(duration.subsec_nanos() as i64);
+    }
+
+    /// Writes the seconds of `value` relative to `previous`, which is
+    /// updated.
+    fn datetime(&mut self, value: NaiveDateTime, previous: &mut i64) {
+        let utc = value.and_utc();
+        self.signed(utc.timestamp() - *previous);
+        self.varint(utc.timestamp_subsec_nanos() as u64);
+        *previous = utc.timestamp();
+    }
+
+    fn coordinates(&mut self, coords: &'q Coordinates) {
+        match coords {
+            Coordinates::Empty => self.bytes.push(EMPTY),
+            Coordinates::Integers(ints) => {
+                self.bytes.push(INTEGERS);
+                self.integers(ints);
+            }
+            Coordinates::Floats(floats) => {
+                self.bytes.push(FLOATS);
+                self.floats(floats);
+            }
+            Coordinates::Strings(strings) => {
+                self.bytes.push(STRINGS);
+                self.strings(strings);
+            }
+            Coordinates::DateTimes(datetimes) => {
+                self.bytes.push(DATETIMES);
+                self.datetimes(datetimes);
+            }
+            Coordinates::Durations(durations) => {
+                self.bytes.push(DURATIONS);
+                self.durations(durations);
+            }
+            Coordinates::Mixed(mixed) => {
+                self.bytes.push(MIXED);
+                self.integers(&mixed.integers);
+                self.floats(&mixed.floats);
+                self.strings(&mixed.strings);
+                self.datetimes(&mixed.datetimes);
+                self.durations(&mixed.durations);
+            }
+        }
+    }
+
+    fn integers(&mut self, ints: &IntegerCoordinates) {
+        match ints {
+            IntegerCoordinates::Set(set) => {
+                self.bytes.push(LIST);
+                self.varint(set.len() as u64);
+                let mut previous = 0i64;
+                for &v in set.iter() {
+                    self.signed(v as i64 - previous);
+                    previous = v as i64;
+                }
+            }
+            IntegerCoordinates::RangeSet(ranges) => {
+                self.bytes.push(RANGES);
+                self.varint(ranges.len() as u64);
+                let mut previous = 0i64;
+                for range in ranges.iter() {
+                    self.signed(range.start() as i64 - previous);
+                    self.varint(range.step().get() as u64);
+                    self.varint(range.len() as u64 - 1);
+                    previous = range.end() as i64;
+                }
+            }
+        }
+    }
+
+    fn floats(&mut self, floats: &FloatCoordinates) {
+        let FloatCoordinates::List(list) = floats;
+        self.varint(list.len() as u64);
+        for &v in list.iter() {
+            self.float(v);
+        }
+    }
+
+    fn strings(&mut self, strings: &'q StringCoordinates) {
+        let StringCoordinates::Set(set) = strings;
+        self.varint(set.len() as u64);
+        for s in set.iter() {
+            self.interned(s.as_str());
+        }
+    }
+
+    fn datetimes(&mut self, datetimes: &DateTimeCoordinates) {
+        let mut previous = 0i64;
+        match datetimes {
+            DateTimeCoordinates::List(list) => {
+                self.bytes.push(LIST);
+                self.varint(list.len() as u64);
+                for &v in list.iter() {
+                    self.datetime(v, &mut previous);
+                }
+            }
+            DateTimeCoordinates::RangeSet(ranges) => {
+                self.bytes.push(RANGE_SET);
+                self.varint(ranges.len() as u64);
+                for range in ranges.iter() {
+                    self.datetime(range.start(), &mut previous);
+                    self.datetime(range.end(), &mut previous);
+                    self.duration(range.step());
+                }
+            }
+        }
+    }
+
+    fn durations(&mut self, durations: &DurationCoordinates) {
+        let DurationCoordinates::Set(set) = durations;
+        self.varint(set.len() as u64);
+        for v in set.iter() {
+            self.duration(v.start());
+            match v.end() {
+                None => self.bytes.push(0),
+                Some(end) => {
+                    self.bytes.push(1);
+                    self.duration(end);
+                }
+            }
+        }
+    }
+
+    fn metadata(&mut self, metadata: &'q Metadata) {
+        // Sorted, so the same Qube always gives the same bytes.
+        let mut entries: Vec<(&'q String, &'q MetadataValues)> = metadata.iter().collect();
+        entries.sort_by_key(|(key, _)| key.as_str());
+        self.varint(entries.len() as u64);
+        for (key, values) in entries {
+            self.interned(key);
+            match values {
+                MetadataValues::Empty => self.bytes.push(META_EMPTY),
+                MetadataValues::Integers(set) => {
+                    self.bytes.push(META_INTEGERS);
+                    self.varint(set.len() as u64);
+                    let mut previous = 0i64;
+                    for &v in set.iter() {
+                        self.signed(v as i64 - previous);
+                        previous = v as i64;
+                    }
+                }
+                MetadataValues::Strings(set) => {
+                    self.bytes.push(META_STRINGS);
+                    self.varint(set.len() as u64);
+                    for s in set.iter() {
+                        self.interned(s.as_str());
+                    }
+                }
+                MetadataValues::PerCoordStrings(per_coord) => {
+                    self.bytes.push(META_PER_COORD_STRINGS);
+                    self.varint(per_coord.len() as u64);
+                    for strings in per_coord {
+                        self.varint(strings.len() as u64);
+                        for s in strings {
+                            self.interned(s);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+// ---------------- Binary Deserialization ----------------
+
+impl Qube {
+    /// Reconstructs a Qube from the output of [`Qube::to_bytes`].  Fails if
+    /// the data is not a binary Qube, has an unsupported version or does not
+    /// match its checksum.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Qube, String> {
+        if bytes.len() < MAGIC.len() + 1 + 4 || &bytes[..MAGIC.len()] != MAGIC {
+            return Err("Not a binary Qube: missing the 'QUBE' header".to_string());
+        }
+        let version = bytes[MAGIC.len()];
+        if version != VERSION {
+            return Err(format!("Unsupported binary Qube version: {}", version));
+        }
+        verify_checksum(bytes)?;
+        let body = &bytes[..bytes.len() - 4];
+        // The nodes run up to the index, which loading does not need.
+        let nodes_end = index_start(body)?;
+
+        let mut reader = Reader::new(body, MAGIC.len() + 1);
+        let mut qube = Qube::new();
+
+        qube.set_float_tolerance(reader.float_tolerance()?);
+        for _ in 0..reader.count()? {
+            let dim = reader.string()?;
+            let tolerance = reader.float_tolerance()?;
+            qube.set_dimension_float_tolerance(dim, tolerance);
+        }
+
+        let dims: Vec<Dimension> = (0..reader.count()?)
+            .map(|_| reader.string().map(|name| qube.get_or_intern_dim(name)))
+            .collect::<Result<_, _>>()?;
+        let mut dictionary = Vec::new();
+        for _ in 0..reader.count()? {
+            dictionary.push(reader.string()?);
+        }
+        reader.strings = Strings::Loaded(dictionary);
+
+        let count = reader.count()?;
+        if count == 0 {
+            return Err("Binary Qube has no root node".to_string());
+        }
+
+        // Every node takes at least a few bytes, so a corrupt count cannot
+        // reserve more than the data could hold.
+        qube.reserve_nodes(count.min(nodes_end.saturating_sub(reader.pos)).saturating_sub(1));
+
+        // The root's dimension is always "root", so it is read and ignored.
+        let root = qube.root();
+        let (_, coords, metadata, children) = reader.node(&dims)?;
+        if let Some(node) = qube.node_mut(root) {
+            *node.coords_mut() = coords;
+            *node.metadata_mut() = metadata;
+        }
+
+        let mut read = 1;
+        let mut stack: Vec<(NodeIdx, usize)> = vec![(root, children)];
+        while let Some((parent, remaining)) = stack.last_mut() {
+            if *remaining == 0 {
+                stack.pop();
+                continue;
+            }
+            *remaining -= 1;
+            let parent = *parent;
+
+            let (dim, coords, metadata, children) = reader.node(&dims)?;
+            let id = qube.insert_loaded_child(dim, parent, coords, metadata);
+            read += 1;
+            if children > 0 {
+                stack.push((id, children));
+            }
+        }
+
+        if read != count {
+            return Err(format!("Binary Qube declares {} nodes but holds {}", count, read));
+        }
+        if reader.pos != nodes_end {
+            return Err(format!(
+                "Binary Qube nodes end at byte {} instead of {}",
+                reader.pos, nodes_end
+            ));
+        }
+        Ok(qube)
+    }
+}
+
+/// Checks the trailing CRC-32 of `bytes`, which must be at least 4 long.
+pub(crate) fn verify_checksum(bytes: &[u8]) -> Result<(), String> {
+    let (body, checksum) = bytes.split_at(bytes.len() - 4);
+    if crc32(body) != u32::from_le_bytes(checksum.try_into().expect("4 bytes")) {
+        return Err("Binary Qube checksum mismatch: the data is corrupt".to_string());
+    }
+    Ok(())
+}
+
+/// Reads the offset of the index from the end of `body`, the data before the
+/// checksum.
+fn index_start(body: &[u8]) -> Result<usize, String> {
+    let footer = body
+        .len()
+        .checked_sub(8)
+        .ok_or_else(|| "Binary Qube truncated: missing the index offset".to_string())?;
+    let start = u64::from_le_bytes(body[footer..].try_into().expect("8 bytes"));
+    if start > footer as u64 {
+        return Err(format!("Binary Qube index offset {} is past the end", start));
+    }
+    Ok(start as usize)
+}
+
+/// The parts of a binary Qube that a [`crate::MappedQube`] reads
+/// up front.  Everything else is read on demand through the index.
+pub(crate) struct Sections {
+    pub(crate) float_tolerance: FloatTolerance,
+    pub(crate) dimension_tolerances: Vec<(String, FloatTolerance)>,
+    pub(crate) dimensions: Vec<String>,
+    pub(crate) string_offsets: Range<usize>,
+    pub(crate) node_index: Range<usize>,
+}
+
+/// Finds the sections of `bytes` without reading the nodes or verifying the
+/// checksum.
+pub(crate) fn sections(bytes: &[u8]) -> Result<Sections, String> {
+    if bytes.len() < MAGIC.len() + 1 + 4 || &bytes[..MAGIC.len()] != MAGIC {
+        return Err("Not a binary Qube: missing the 'QUBE' header".to_string());
+    }
+    let version = bytes[MAGIC.len()];
+    if version != VERSION {
+        return Err(format!("Unsupported binary Qube version: {}", version));
+    }
+    let body = &bytes[..bytes.len() - 4];
+    let index = index_start(body)?;
+
+    let mut reader = Reader::new(body, MAGIC.len() + 1);
+    let float_tolerance = reader.float_tolerance()?;
+    let dimension_tolerances = (0..reader.count()?)
+        .map(|_| Ok((reader.string()?.to_string(), reader.float_tolerance()?)))
+        .collect::<Result<_, String>>()?;
+    let dimensions = (0..reader.count()?)
+        .map(|_| reader.string().map(str::to_string))
+        .collect::<Result<_, _>>()?;
+    let string_count = reader.count()?;
+
+    let string_offsets = index..index + string_count * 8;
+    let node_index = string_offsets.end..body.len() - 8;
+    if string_offsets.end > node_index.end || node_index.len() % 16 != 0 || node_index.is_empty() {
+        return Err("Binary Qube index does not match its dictionary".to_string());
+    }
+
+    // Each subtree must end within the index, or walking it would run off
+    // the end.
+    let nodes = node_index.len() / 16;
+    for (id, entry) in body[node_index.clone()].chunks_exact(16).enumerate() {
+        let descendants = u64::from_le_bytes(entry[8..].try_into().expect("8 bytes"));
+        if descendants >= (nodes - id) as u64 {
+            return Err(format!(
+                "Binary Qube node {} claims {} descendants but only {} nodes follow it",
+                id,
+                descendants,
+                nodes - id - 1
+            ));
+        }
+    }
+    Ok(Sections { float_tolerance, dimension_tolerances, dimensions, string_offsets, node_index })
+}
+
+pub(crate) struct Reader<'b> {
+    bytes: &'b [u8],
+    pos: usize,
+    strings: Strings<'b>,
+}
+
+/// Where a [`Reader`] looks up dictionary strings.
+enum Strings<'b> {
+    Loaded(Vec<&'b str>),
+    /// The offsets section of the index, 8 bytes per string.
+    Indexed(&'b [u8]),
+}
+
+impl<'b> Reader<'b> {
+    fn new(bytes: &'b [u8], pos: usize) -> Self {
+        Reader { bytes, pos, strings: Strings::Loaded(Vec::new()) }
+    }
+
+    /// A reader at `pos` that finds strings through the index.
+    pub(crate) fn indexed(bytes: &'b [u8], pos: usize, string_offsets: Range<usize>) -> Self {
+        let offsets = bytes.get(string_offsets).unwrap_or_default();
+        Reader { bytes, pos, strings: Strings::Indexed(offsets) }
+    }
+
+    #[cold]
+    fn truncated(&self) -> String {
+        format!("Binary Qube truncated at byte {}", self.pos)
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        let byte = *self.bytes.get(self.pos).ok_or_else(|| self.truncated())?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'b [u8], String> {
+        let end = self.pos.checked_add(len).filter(|&end| end <= self.bytes.len());
+        let end = end.ok_or_else(|| self.truncated())?;
+        let taken = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(taken)
+    }
+
+    pub(crate) fn varint(&mut self) -> Result<u64, String> {
+        // Most counts, deltas and indices fit in one byte.
+        if let Some(&byte) = self.bytes.get(self.pos)
+            && byte & 0x80 == 0
+        {
+            self.pos += 1;
+            return Ok(byte as u64);
+        }
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(format!("Binary Qube has an overlong integer at byte {}", self.pos))
+    }
+
+    /// A number of items that follow, each of which takes at least a byte.
+    pub(crate) fn count(&mut self) -> Result<usize, String> {
+        let count = self.varint()?;
+        if count > (self.bytes.len() - self.pos) as u64 {
+            return Err(self.truncated());
+        }
+        Ok(count as usize)
+    }
+
+    fn signed(&mut self) -> Result<i64, String> {
+        let value = self.varint()?;
+        Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+
+    /// Adds a signed delta to `previous`, which must stay an `i32`.
+    fn delta(&mut self, previous: i64) -> Result<i64, String> {
+        let value = previous.saturating_add(self.signed()?);
+        if i32::try_from(value).is_err() {
+            return Err(format!("Binary Qube integer out of range at byte {}", self.pos));
+        }
+        Ok(value)
+    }
+
+    fn float(&mut self) -> Result<f64, String> {
+        let bytes = self.take(8)?;
+        Ok(f64::from_bits(u64::from_le_bytes(bytes.try_into().expect("8 bytes"))))
+    }
+
+    fn string(&mut self) -> Result<&'b str, String> {
+        let len = self.count()?;
+        let bytes = self.take(len)?;
+        std::str::from_utf8(bytes)
+            .map_err(|_| format!("Binary Qube has invalid UTF-8 before byte {}", self.pos))
+    }
+
+    fn interned(&mut self) -> Result<&'b str, String> {
+        let index = self.varint()?;
+        let found = match &self.strings {
+            Strings::Loaded(strings) => strings.get(index as usize).copied(),
+            Strings::Indexed(offsets) => (index as usize)
+                .checked_mul(8)
+                .and_then(|start| offsets.get(start..start + 8))
+                .and_then(|offset| {
+                    let offset = u64::from_le_bytes(offset.try_into().expect("8 bytes"));
+                    Reader::new(self.bytes, offset as usize).string().ok()
+                }),
+        };
+        found.ok_or_else(|| {
+            format!("Binary Qube string index {} out of range at byte {}", index, self.pos)
+        })
+    }
+
+    fn float_tolerance(&mut self) -> Result<FloatTolerance, String> {
+        Ok(FloatTolerance::new(self.float()?, self.float()?))
+    }
+
+    fn duration(&mut self) -> Result<Duration, String> {
+        let seconds = self.signed()?;
+        let nanos = self.signed()?;
+        Duration::try_seconds(seconds)
+            .and_then(|seconds| seconds.checked_add(&Duration::nanoseconds(nanos)))
+            .ok_or_else(|| format!("Binary Qube duration out of range at byte {}", self.pos))
+    }
+
+    fn datetime(&mut self, previous: &mut i64) -> Result<NaiveDateTime, String> {
+        let seconds = previous.saturating_add(self.signed()?);
+        let nanos = u32::try_from(self.varint()?).ok();
+        *previous = seconds;
+        nanos
+            .and_then(|nanos| DateTime::from_timestamp(seconds, nanos))
+            .map(|datetime| datetime.naive_utc())
+            .ok_or_else(|| format!("Binary Qube date-time out of range at byte {}", self.pos))
+    }
+
+    fn node(
+        &mut self,
+        dims: &[Dimension],
+    ) -> Result<(Dimension, Coordinates, Metadata, usize), String> {
+        let index = self.varint()?;
+        let dim = *dims.get(index as usize).ok_or_else(|| {
+            format!("Binary Qube dimension index {} out of range at byte {}", index, self.pos)
+        })?;
+        let coords = self.coordinates()?;
+        let metadata = self.metadata()?;
+        let children = self.count()?;
+        Ok((dim, coords, metadata, children))
+    }
+
+    pub(crate) fn coordinates(&mut self) -> Result<Coordinates, String> {
+        Ok(match self.byte()? {
+            EMPTY => Coordinates::Empty,
+            INTEGERS => Coordinates::Integers(self.integers()?),
+            FLOATS => Coordinates::Floats(self.floats()?),
+            STRINGS => Coordinates::Strings(self.strings()?),
+            DATETIMES => Coordinates::DateTimes(self.datetimes()?),
+            DURATIONS => Coordinates::Durations(self.durations()?),
+            MIXED => Coordinates::Mixed(Box::new(MixedCoordinates {
+                integers: self.integers()?,
+                floats: self.floats()?,
+                strings: self.strings()?,
+                datetimes: self.datetimes()?,
+                durations: self.durations()?,
+            })),
+            tag => return Err(self.unknown("coordinate", tag)),
+        })
+    }
+
+    fn unknown(&self, what: &str, tag: u8) -> String {
+        format!("Binary Qube has unknown {} tag {} at byte {}", what, tag, self.pos - 1)
+    }
+
+    fn integers(&mut self) -> Result<IntegerCoordinates, String> {
+        match self.byte()? {
+            LIST => {
+                let mut set = TinyOrderedSet::new();
+                let mut previous = 0i64;
+                for _ in 0..self.count()? {
+                    previous = self.delta(previous)?;
+                    set.insert(previous as i32);
+                }
+                Ok(IntegerCoordinates::Set(set))
+            }
+            RANGES => {
+                let mut ranges = TinyVec::new();
+                let mut previous = 0i64;
+                for _ in 0..self.count()? {
+                    let start = self.delta(previous)?;
+                    let step = u16::try_from(self.varint()?).ok().and_then(NonZeroU16::new);
+                    let steps = self.varint()?;
+                    let range = step.and_then(|step| {
+                        let end = (steps as i128) * step.get() as i128 + start as i128;
+                        let end = i32::try_from(end).ok()?;
+                        IntegerRange::new(start as i32, end, step)
+                    });
+                    let range = range.ok_or_else(|| {
+                        format!("Binary Qube has an invalid integer range at byte {}", self.pos)
+                    })?;
+                    previous = range.end() as i64;
+                    ranges.push(range);
+                }
+                Ok(IntegerCoordinates::RangeSet(ranges))
+            }
+            tag => Err(self.unknown("integer layout", tag)),
+        }
+    }
+
+    fn floats(&mut self) -> Result<FloatCoordinates, String> {
+        let mut list = TinyVec::new();
+        for _ in 0..self.count()? {
+            list.push(self.float()?);
+        }
+        Ok(FloatCoordinates::List(list))
+    }
+
+    fn strings(&mut self) -> Result<StringCoordinates, String> {
+        let mut set = TinyOrderedSet::new();
+        for _ in 0..self.count()? {
+            set.insert(TinyString::from(self.interned()?));
+        }
+        Ok(StringCoordinates::Set(set))
+    }
+
+    fn datetimes(&mut self) -> Result<DateTimeCoordinates, String> {
+        let mut previous = 0i64;
+        match self.byte()? {
+            LIST => {
+                let mut list = TinyVec::new();
+                for _ in 0..self.count()? {
+                    list.push(self.datetime(&mut previous)?);
+                }
+                Ok(DateTimeCoordinates::List(list))
+            }
+            RANGE_SET => {
+                let mut ranges = TinyVec::new();
+                for _ in 0..self.count()? {
+                    let start = self.datetime(&mut previous)?;
+                    let end = self.datetime(&mut previous)?;
+                    let step = self.duration()?;
+                    let range = DateTimeRange::new(start, end, step).ok_or_else(|| {
+                        format!("Binary Qube has an invalid date-time range at byte {}", self.pos)
+                    })?;
+                    ranges.push(range);
+                }
+                Ok(DateTimeCoordinates::RangeSet(ranges))
+            }
+            tag => Err(self.unknown("date-time layout", tag)),
+        }
+    }
+
+    fn durations(&mut self) -> Result<DurationCoordinates, String> {
+        let mut set = TinyOrderedSet::new();
+        for _ in 0..self.count()? {
+            let start = self.duration()?;
+            let value = match self.byte()? {
+                0 => Some(DurationValue::offset(start)),
+                1 => DurationValue::interval(start, self.duration()?),
+                tag => return Err(self.unknown("duration", tag)),
+            };
+            let value = value.ok_or_else(|| {
+                format!("Binary Qube has an interval ending before it starts at byte {}", self.pos)
+            })?;
+            set.insert(value);
+        }
+        Ok(DurationCoordinates::Set(set))
+    }
+
+    pub(crate) fn metadata(&mut self) -> Result<Metadata, String> {
+        let mut metadata = Metadata::new();
+        for _ in 0..self.count()? {
+            let key = self.interned()?;
+            let values = match self.byte()? {
+                META_EMPTY => MetadataValues::Empty,
+                META_INTEGERS => {
+                    let mut set = TinyOrderedSet::new();
+                    let mut previous = 0i64;
+                    for _ in 0..self.count()? {
+                        previous = self.delta(previous)?;
+                        set.insert(previous as i32);
+                    }
+                    MetadataValues::Integers(set)
+                }
+                META_STRINGS => {
+                    let mut set = TinyOrderedSet::new();
+                    for _ in 0..self.count()? {
+                        set.insert(TinyString::from(self.interned()?));
+                    }
+                    MetadataValues::Strings(set)
+                }
+                META_PER_COORD_STRINGS => {
+                    let mut per_coord = Vec::new();
+                    for _ in 0..self.count()? {
+                        let strings = (0..self.count()?)
+                            .map(|_| self.interned().map(str::to_string))
+                            .collect::<Result<_, _>>()?;
+                        per_coord.push(strings);
+                    }
+                    MetadataValues::PerCoordStrings(per_coord)
+                }
+                tag => return Err(self.unknown("metadata", tag)),
+            };
+            metadata.set(key.to_string(), values);
+        }
+        Ok(metadata)
+    }
+}
+
+// -------- Checksum --------
+
+/// `CRC32_TABLES[0]` is the usual byte-at-a-time table; `CRC32_TABLES[k]`
+/// gives the effect of a byte followed by `k` zero bytes, so that eight bytes
+/// can be folded in at once.
+const CRC32_TABLES: [[u32; 256]; 8] = {
+    let mut tables = [[0u32; 256]; 8];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 { 0xEDB8_8320 ^ (crc >> 1) } else { crc >> 1 };
+            bit += 1;
+        }
+        tables[0][i] = crc;
+        i += 1;
+    }
+    let mut k = 1;
+    while k < 8 {
+        let mut i = 0;
+        while i < 256 {
+            let previous = tables[k - 1][i];
+            tables[k][i] = tables[0][(previous & 0xff) as usize] ^ (previous >> 8);
+            i += 1;
+        }
+        k += 1;
+    }
+    tables
+};
+
+/// The CRC-32 (IEEE) of `bytes`.
+fn crc32(bytes: &[u8]) -> u32 {
+    let t = &CRC32_TABLES;
+    let mut chunks = bytes.chunks_exact(8);
+    let mut crc = !0u32;
+    for chunk in &mut chunks {
+        let low = crc ^ u32::from_le_bytes(chunk[..4].try_into().expect("4 bytes"));
+        let high = u32::from_le_bytes(chunk[4..].try_into().expect("4 bytes"));
+        crc = t[7][(low & 0xff) as usize]
+            ^ t[6][((low >> 8) & 0xff) as usize]
+            ^ t[5][((low >> 16) & 0xff) as usize]
+            ^ t[4][(low >> 24) as usize]
+            ^ t[3][(high & 0xff) as usize]
+            ^ t[2][((high >> 8) & 0xff) as usize]
+            ^ t[1][((high >> 16) & 0xff) as usize]
+            ^ t[0][(high >> 24) as usize];
+    }
+    !chunks
+        .remainder()
+        .iter()
+        .fold(crc, |crc, &b| t[0][((crc ^ b as u32) & 0xff) as usize] ^ (crc >> 8))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn sample_qube() -> Qube {
+        let mut qube = Qube::from_ascii(
+            r#"root
+├── class=od
+│   ├── expver=0001
+│   │   └── param=1/2/130/to/200/by/2
+│   └── expver=0002
+│       └── step=0h/6h/0-24
+└── class=rd
+    └── levelist=0.5/850.25
+        └── time=2024-01-01T00:00:00/to/2024-01-03T00:00:00/by/6h"#,
+        )
+        .unwrap();
+        let mut mixed = Coordinates::from_string("1/2");
+        mixed.append("x".to_string());
+        mixed.append(NaiveDate::from_ymd_opt(2024, 5, 1).unwrap().and_hms_opt(12, 0, 0).unwrap());
+        qube.get_or_create_child("mixed", qube.root(), Some(mixed)).unwrap();
+
+        let leaf = qube.leaf_node_ids_paths()[0][3];
+        qube.set_metadata(leaf, "path", MetadataValues::single_string("/data/od")).unwrap();
+        qube.set_metadata(leaf, "offset", MetadataValues::from_integers(&[-4, 16, 1024])).unwrap();
+        let class = qube.leaf_node_ids_paths()[0][1];
+        let node = qube.node_mut(class).unwrap();
+        node.metadata_mut().set(
+            "host".to_string(),
+            MetadataValues::PerCoordStrings(vec![vec!["a".to_string(), "b".to_string()]]),
+        );
+        qube.set_float_tolerance(FloatTolerance::new(1e-6, 0.0));
+        qube.set_dimension_float_tolerance("levelist", FloatTolerance::EXACT);
+        qube
+    }
+
+    /// Every node's dimension, coordinates and metadata, depth-first.
+    fn nodes(qube: &Qube) -> Vec<(String, Coordinates, Metadata)> {
+        let mut out = Vec::new();
+        let mut stack = vec![qube.root()];
+        while let Some(id) = stack.pop() {
+            let node = qube.node(id).unwrap();
+            out.push((
+                node.dimension().unwrap().to_string(),
+                node.coordinates().clone(),
+                node.metadata().clone(),
+            ));
+            let children: Vec<NodeIdx> = node.all_children().collect();
+            stack.extend(children.into_iter().rev());
+        }
+        out
+    }
+
+    #[test]
+    fn test_crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn test_binary_roundtrip_keeps_nodes_metadata_and_tolerances() {
+        let qube = sample_qube();
+        let bytes = qube.to_bytes();
+        let decoded = Qube::from_bytes(&bytes).unwrap();
+
+        assert_eq!(nodes(&decoded), nodes(&qube));
+        assert_eq!(decoded.to_ascii(), qube.to_ascii());
+        assert_eq!(decoded.float_tolerance("param"), FloatTolerance::new(1e-6, 0.0));
+        assert_eq!(decoded.float_tolerance("levelist"), FloatTolerance::EXACT);
+        assert_eq!(decoded.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_binary_is_smaller_than_arena_json() {
+        let qube = Qube::from_ascii(
+            r#"root
+└── class=od/rd
+    └── date=20240101/to/20241231
+        └── param=1/to/500"#,
+        )
+        .unwrap();
+        let bytes = qube.to_bytes();
+        assert!(bytes.len() < qube.to_arena_json().to_string().len());
+        assert_eq!(nodes(&Qube::from_bytes(&bytes).unwrap()), nodes(&qube));
+    }
+
+    #[test]
+    fn test_binary_rejects_data_without_index() {
+        let bytes = sample_qube().to_bytes();
+        let body = &bytes[..bytes.len() - 4];
+        let mut unindexed = body[..index_start(body).unwrap()].to_vec();
+        let checksum = crc32(&unindexed);
+        unindexed.extend_from_slice(&checksum.to_le_bytes());
+
+        assert!(Qube::from_bytes(&unindexed).is_err());
+    }
+
+    #[test]
+    fn test_descendant_counts() {
+        // root -> (a -> (b, c), d)
+        assert_eq!(descendant_counts(&[2, 2, 0, 0, 0]), vec![4, 2, 0, 0, 0]);
+        assert_eq!(descendant_counts(&[0]), vec![0]);
+    }
+
+    #[test]
+    fn test_binary_roundtrip_empty_qube() {
+        let qube = Qube::new();
+        let decoded = Qube::from_bytes(&qube.to_bytes()).unwrap();
+        assert!(decoded.is_empty());
+    }
+
+    #[test]
+    fn test_binary_rejects_bad_input() {
+        let bytes = sample_qube().to_bytes();
+
+        let err = Qube::from_bytes(b"{\"version\": \"1\"}").unwrap_err();
+        assert!(err.contains("Not a binary Qube"), "{}", err);
+
+        for version in [0, 2] {
+            let mut other = bytes.clone();
+            other[4] = version;
+            let err = Qube::from_bytes(&other).unwrap_err();
+            assert!(err.contains(&format!("Unsupported binary Qube version: {version}")), "{err}");
+        }
+
+        let mut corrupt = bytes.clone();
+        corrupt[bytes.len() / 2] ^= 0x40;
+        let err = Qube::from_bytes(&corrupt).unwrap_err();
+        assert!(err.contains("checksum mismatch"), "{}", err);
+
+        // A truncated body with a valid checksum still fails cleanly.
+        let mut truncated = bytes[..bytes.len() - 10].to_vec();
+        let checksum = crc32(&truncated);
+        truncated.extend_from_slice(&checksum.to_le_bytes());
+        assert!(Qube::from_bytes(&truncated).is_err());
+    }
+}
@@ -1,5 +1,10 @@
 use crate::{Coordinates, MetadataValues, NodeIdx, Qube};
+use chrono::NaiveDateTime;
+use serde::de::{self, Deserializer};
 use serde_json::{Map, Value};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::io::{BufReader, BufWriter, Read, Write};
 
 // ---------------- JSON Deserialization ----------------
 
@@ -28,7 +33,7 @@ fn parse_json_object(
             .split_once('=')
             .ok_or_else(|| format!("Invalid node format: '{}', expected 'key=value'", key_value))?;
 
-        let values = Coordinates::from_string(values_str);
+        let values = Coordinates::from_dimension_string(key, values_str);
         let child = qube.get_or_create_child(key, parent, Some(values))?;
 
         if let Value::Object(child_map) = child_value {
@@ -54,113 +59,60 @@ impl Qube {
     /// string, and the index of the parent node (or null for the root). The
     /// nodes are emitted in BFS order so parents always precede children.
     pub fn to_arena_json(&self) -> Value {
-        use std::collections::{HashMap, VecDeque};
+        let nodes_json: Vec<Value> = self.arena_nodes().collect();
 
-        let mut order: Vec<NodeIdx> = Vec::new();
-        let mut q: VecDeque<NodeIdx> = VecDeque::new();
-        q.push_back(self.root());
+        // Wrap the arena array with a versioned envelope so format changes
+        // can be detected by consumers.
+        let mut root_map = Map::new();
+        root_map.insert("version".to_string(), Value::String(ARENA_JSON_VERSION.to_string()));
+        root_map.insert("qube".to_string(), Value::Array(nodes_json));
+        Value::Object(root_map)
+    }
 
-        while let Some(id) = q.pop_front() {
-            order.push(id);
-            if let Some(nref) = self.node(id) {
-                for child in nref.all_children() {
-                    q.push_back(child);
-                }
+    /// Write the arena JSON layout of `to_arena_json` to `writer` one node at
+    /// a time, without building the whole document in memory.  The version is
+    /// written before the nodes so a streaming reader can check it first.
+    pub fn to_arena_json_writer(&self, writer: impl Write) -> Result<(), String> {
+        let write_err = |e: std::io::Error| format!("Failed to write arena JSON: {e}");
+        let mut writer = BufWriter::new(writer);
+        write!(writer, "{{\"version\":\"{ARENA_JSON_VERSION}\",\"qube\":[").map_err(write_err)?;
+        for (i, node) in self.arena_nodes().enumerate() {
+            if i > 0 {
+                writer.write_all(b",").map_err(write_err)?;
             }
+            serde_json::to_writer(&mut writer, &node)
+                .map_err(|e| format!("Failed to write arena JSON: {e}"))?;
         }
+        writer.write_all(b"]}").map_err(write_err)?;
+        writer.flush().map_err(write_err)
+    }
 
-        let mut idx_map: HashMap<NodeIdx, usize> = HashMap::new();
-        for (i, id) in order.iter().enumerate() {
-            idx_map.insert(*id, i);
-        }
-
-        let mut nodes_json: Vec<Value> = Vec::with_capacity(order.len());
-        for id in order.iter() {
-            let nref = self.node(*id).expect("valid node");
+    /// The arena records in BFS order.  The children of each node are
+    /// numbered consecutively after those of the nodes before it in the
+    /// queue, so indices are known without a first pass over the tree.
+    fn arena_nodes(&self) -> impl Iterator<Item = Value> + '_ {
+        let mut queue: VecDeque<(NodeIdx, Option<usize>)> = VecDeque::from([(self.root(), None)]);
+        let mut index = 0;
+        let mut next_child_index = 1;
+
+        std::iter::from_fn(move || {
+            let (id, parent_idx) = queue.pop_front()?;
+            let nref = self.node(id).expect("valid node");
             let dim = nref.dimension().unwrap_or("root").to_string();
-            // Build coords object with explicit type tags so consumers know the
-            // coordinate type without guessing. Examples:
-            // { "ints": [1,2,3] }, { "strings": ["od"] }, { "floats": [...] }, or mixed object.
-            let coords_value = {
-                use serde_json::{Map, Value};
-                let mut map = Map::new();
-
-                // Use the public Coordinates -> JSON helper which returns a
-                // native serde_json::Value (array/string/object/null).
-                let native = nref.coordinates().to_json_value();
-
-                match nref.coordinates() {
-                    // Represent empty coordinates as JSON null so they round-trip as `Empty`,
-                    // not as `Mixed(empty)` (which is how an empty object `{}` would be read).
-                    crate::Coordinates::Empty => Value::Null,
-                    crate::Coordinates::Integers(_) => match native {
-                        Value::Array(arr) => {
-                            map.insert("ints".to_string(), Value::Array(arr));
-                            Value::Object(map)
-                        }
-                        Value::String(s) => {
-                            // RangeSet or other textual form – preserve as string under "ints_text"
-                            map.insert("ints_text".to_string(), Value::String(s));
-                            Value::Object(map)
-                        }
-                        other => {
-                            map.insert("ints".to_string(), other);
-                            Value::Object(map)
-                        }
-                    },
-                    crate::Coordinates::Floats(_) => match native {
-                        Value::Array(arr) => {
-                            map.insert("floats".to_string(), Value::Array(arr));
-                            Value::Object(map)
-                        }
-                        other => {
-                            map.insert("floats".to_string(), other);
-                            Value::Object(map)
-                        }
-                    },
-                    crate::Coordinates::Strings(_) => match native {
-                        Value::Array(arr) => {
-                            map.insert("strings".to_string(), Value::Array(arr));
-                            Value::Object(map)
-                        }
-                        other => {
-                            map.insert("strings".to_string(), other);
-                            Value::Object(map)
-                        }
-                    },
-                    crate::Coordinates::DateTimes(_) => {
-                        // Fallback to whatever the generic serializer produces (not implemented elsewhere yet)
-                        let v = nref.coordinates().to_json_value();
-                        match v {
-                            Value::Array(arr) => {
-                                map.insert("datetimes".to_string(), Value::Array(arr));
-                                Value::Object(map)
-                            }
-                            other => Value::Object(map),
-                        }
-                    }
-                    crate::Coordinates::Mixed(_) => {
-                        // Mixed already produces an object with keys like ints/floats/strings
-                        nref.coordinates().to_json_value()
-                    }
-                }
-            };
-
-            let parent_idx = nref.parent().map(|p| idx_map.get(&p).copied().unwrap());
 
-            let children_indices: Vec<Value> = nref
-                .all_children()
-                .map(|c| Value::Number(serde_json::Number::from(*idx_map.get(&c).unwrap() as u64)))
-                .collect();
+            let mut children_indices = Vec::new();
+            for child in nref.all_children() {
+                children_indices.push(Value::from(next_child_index as u64));
+                queue.push_back((child, Some(index)));
+                next_child_index += 1;
+            }
+            index += 1;
 
             let mut map = Map::new();
             map.insert("dim".to_string(), Value::String(dim));
-            map.insert("coords".to_string(), coords_value);
+            map.insert("coords".to_string(), arena_coords_json(nref.coordinates()));
             match parent_idx {
-                Some(pi) => map.insert(
-                    "parent".to_string(),
-                    Value::Number(serde_json::Number::from(pi as u64)),
-                ),
+                Some(pi) => map.insert("parent".to_string(), Value::from(pi as u64)),
                 None => map.insert("parent".to_string(), Value::Null),
             };
             map.insert("children".to_string(), Value::Array(children_indices));
@@ -185,163 +137,383 @@ impl Qube {
                 }
             }
 
-            nodes_json.push(Value::Object(map));
-        }
-
-        // Wrap the arena array with a versioned envelope so format changes
-        // can be detected by consumers.
-        let mut root_map = Map::new();
-        root_map.insert("version".to_string(), Value::String("1".to_string()));
-        root_map.insert("qube".to_string(), Value::Array(nodes_json));
-        Value::Object(root_map)
+            Some(Value::Object(map))
+        })
     }
 
     /// Reconstruct a Qube from an arena JSON layout created by `to_arena_json`.
+    ///
+    /// Records are read as they always have been: the first is the root, any
+    /// other without a parent hangs from the root, and `children` lists are
+    /// not checked against the parents.  [`Qube::from_arena_json_reader`] is
+    /// stricter.
     pub fn from_arena_json(value: Value) -> Result<Qube, String> {
-        use std::collections::HashMap;
-
         // Expect a versioned envelope with structure { "version": "1", "qube": [ ... ] }
         let arr = match value {
-            Value::Object(map) => {
-                // check version
+            Value::Object(mut map) => {
                 let version_val = map
                     .get("version")
                     .ok_or_else(|| "Arena JSON missing 'version' field".to_string())?;
-                let ok = match version_val {
-                    Value::String(s) => s == "1",
-                    Value::Number(n) => n.as_u64().map(|v| v == 1).unwrap_or(false),
-                    _ => false,
-                };
-                if !ok {
-                    return Err(format!("Unsupported arena JSON version: {:?}", version_val));
-                }
+                check_arena_version(version_val)?;
 
-                // extract qube array
-                match map.get("qube") {
-                    Some(Value::Array(a)) => a.clone(),
+                match map.remove("qube") {
+                    Some(Value::Array(a)) => a,
                     _ => return Err("Arena JSON missing 'qube' array".to_string()),
                 }
             }
             _ => return Err("Expected JSON object envelope for arena layout".to_string()),
         };
 
-        // We will create nodes in the same order. Start with a fresh Qube which
-        // already contains a root node.
-        let mut qube = Qube::new();
-        let mut index_to_node: HashMap<usize, NodeIdx> = HashMap::new();
-
-        for (i, item) in arr.into_iter().enumerate() {
-            let obj =
-                item.as_object().ok_or_else(|| format!("Arena entry {} is not an object", i))?;
-            let dim = obj
-                .get("dim")
-                .and_then(|v| v.as_str())
-                .ok_or_else(|| format!("Arena entry {} missing dim", i))?;
-            let coords_value =
-                obj.get("coords").ok_or_else(|| format!("Arena entry {} missing coords", i))?;
-
-            // Determine parent: if null or 0 -> root
-            let parent_idx_opt = match obj.get("parent") {
-                Some(Value::Null) | None => None,
-                Some(v) => v.as_u64().map(|n| n as usize),
-            };
+        let mut builder = ArenaBuilder::lenient();
+        for item in &arr {
+            builder.push(item)?;
+        }
+        builder.finish()
+    }
+
+    /// Reconstruct a Qube from arena JSON read from `reader`.  Nodes are
+    /// decoded and attached one at a time, so the whole document is never
+    /// held as a `serde_json::Value`.
+    ///
+    /// Unlike [`Qube::from_arena_json`], every node but the root must name a
+    /// parent that came before it and lists it among its `children`.
+    pub fn from_arena_json_reader(reader: impl Read) -> Result<Qube, String> {
+        let mut deserializer = serde_json::Deserializer::from_reader(BufReader::new(reader));
+        let qube = deserializer
+            .deserialize_map(ArenaVisitor)
+            .map_err(|e| format!("Invalid arena JSON: {e}"))?;
+        deserializer.end().map_err(|e| format!("Invalid arena JSON: {e}"))?;
+        Ok(qube)
+    }
+}
 
-            let parent_node = if let Some(pi) = parent_idx_opt {
-                // parent should have been created earlier
-                *index_to_node.get(&pi).ok_or_else(|| format!("Parent index {} not found", pi))?
+const ARENA_JSON_VERSION: &str = "1";
+
+fn check_arena_version(version_val: &Value) -> Result<(), String> {
+    let ok = match version_val {
+        Value::String(s) => s == ARENA_JSON_VERSION,
+        Value::Number(n) => n.as_u64().map(|v| v == 1).unwrap_or(false),
+        _ => false,
+    };
+    if !ok {
+        return Err(format!("Unsupported arena JSON version: {:?}", version_val));
+    }
+    Ok(())
+}
+
+/// Build the coords object with explicit type tags so consumers know the
+/// coordinate type without guessing. Examples:
+/// `{ "ints": [1,2,3] }`, `{ "strings": ["od"] }`, `{ "floats": [...] }`, or a mixed object.
+fn arena_coords_json(coords: &Coordinates) -> Value {
+    let mut map = Map::new();
+
+    // Use the public Coordinates -> JSON helper which returns a
+    // native serde_json::Value (array/string/object/null).
+    let native = coords.to_json_value();
+
+    match coords {
+        // Represent empty coordinates as JSON null so they round-trip as `Empty`,
+        // not as `Mixed(empty)` (which is how an empty object `{}` would be read).
+        Coordinates::Empty => Value::Null,
+        Coordinates::Integers(_) => match native {
+            Value::Array(arr) => {
+                map.insert("ints".to_string(), Value::Array(arr));
+                Value::Object(map)
+            }
+            Value::String(s) => {
+                // RangeSet or other textual form – preserve as string under "ints_text"
+                map.insert("ints_text".to_string(), Value::String(s));
+                Value::Object(map)
+            }
+            other => {
+                map.insert("ints".to_string(), other);
+                Value::Object(map)
+            }
+        },
+        Coordinates::Floats(_) => match native {
+            Value::Array(arr) => {
+                map.insert("floats".to_string(), Value::Array(arr));
+                Value::Object(map)
+            }
+            other => {
+                map.insert("floats".to_string(), other);
+                Value::Object(map)
+            }
+        },
+        Coordinates::Strings(_) => match native {
+            Value::Array(arr) => {
+                map.insert("strings".to_string(), Value::Array(arr));
+                Value::Object(map)
+            }
+            other => {
+                map.insert("strings".to_string(), other);
+                Value::Object(map)
+            }
+        },
+        Coordinates::DateTimes(_) => {
+            if let Value::Array(arr) = native {
+                map.insert("datetimes".to_string(), Value::Array(arr));
+            }
+            Value::Object(map)
+        }
+        Coordinates::Durations(_) => {
+            map.insert("durations".to_string(), native);
+            Value::Object(map)
+        }
+        // Mixed already produces an object with keys like ints/floats/strings
+        Coordinates::Mixed(_) => native,
+    }
+}
+
+/// Interpret a typed coords object so we deserialize into the most specific
+/// `Coordinates` variant (Integers, Strings, Floats) rather than always
+/// producing a Mixed variant. If the coords object contains a single typed
+/// key (e.g. `ints`, `strings`, `floats`) we'll pass the underlying
+/// array/string to `from_json_value`. If it contains multiple keys we pass
+/// the whole object to obtain a `Mixed` coordinates value.
+fn parse_arena_coords(coords_value: &Value) -> Result<Coordinates, String> {
+    // Build a Value suitable for Coordinates::from_json_value
+    let coords_for_parse: Value = match coords_value {
+        Value::Object(map) => {
+            // Detect typed keys
+            let has_ints = map.get("ints").is_some();
+            let has_ints_text = map.get("ints_text").is_some();
+            let has_strings = map.get("strings").is_some();
+            let has_floats = map.get("floats").is_some();
+            let has_datetimes = map.get("datetimes").is_some();
+            let has_durations = map.get("durations").is_some();
+
+            let typed_key_count =
+                [has_ints, has_ints_text, has_strings, has_floats, has_datetimes, has_durations]
+                    .iter()
+                    .filter(|&&b| b)
+                    .count();
+
+            if has_ints_text && typed_key_count == 1 {
+                // textual integer representation -> parse as string
+                map.get("ints_text").cloned().unwrap_or(Value::Null)
+            } else if has_ints && typed_key_count == 1 {
+                // ints as native array -> pass array so `from_json_value`
+                // returns `Coordinates::Integers` where possible
+                map.get("ints").cloned().unwrap_or(Value::Null)
+            } else if has_strings && typed_key_count == 1 {
+                map.get("strings").cloned().unwrap_or(Value::Null)
+            } else if has_floats && typed_key_count == 1 {
+                map.get("floats").cloned().unwrap_or(Value::Null)
+            } else if has_datetimes && typed_key_count == 1 {
+                map.get("datetimes").cloned().unwrap_or(Value::Null)
             } else {
-                qube.root()
-            };
+                // Mixed or unknown: pass the whole object so
+                // `from_json_value` can create a MixedCoordinates
+                Value::Object(map.clone())
+            }
+        }
+        other => other.clone(),
+    };
 
-            // create child under parent
-            // Interpret typed coords object if present so we deserialize into
-            // the most specific `Coordinates` variant (Integers, Strings,
-            // Floats) rather than always producing a Mixed variant. If the
-            // coords object contains a single typed key (e.g. `ints`,
-            // `strings`, `floats`) we'll pass the underlying array/string to
-            // `from_json_value`. If it contains multiple keys we pass the
-            // whole object to obtain a `Mixed` coordinates value.
-            let coords_parsed = {
-                use serde_json::Value;
-
-                // Build a Value suitable for Coordinates::from_json_value
-                let coords_for_parse: Value = match coords_value {
-                    Value::Object(map) => {
-                        // Detect typed keys
-                        let has_ints = map.get("ints").is_some();
-                        let has_ints_text = map.get("ints_text").is_some();
-                        let has_strings = map.get("strings").is_some();
-                        let has_floats = map.get("floats").is_some();
-                        let has_datetimes = map.get("datetimes").is_some();
-
-                        let typed_key_count =
-                            [has_ints, has_ints_text, has_strings, has_floats, has_datetimes]
-                                .iter()
-                                .filter(|&&b| b)
-                                .count();
-
-                        if has_ints_text && typed_key_count == 1 {
-                            // textual integer representation -> parse as string
-                            map.get("ints_text").cloned().unwrap_or(Value::Null)
-                        } else if has_ints && typed_key_count == 1 {
-                            // ints as native array -> pass array so `from_json_value`
-                            // returns `Coordinates::Integers` where possible
-                            map.get("ints").cloned().unwrap_or(Value::Null)
-                        } else if has_strings && typed_key_count == 1 {
-                            map.get("strings").cloned().unwrap_or(Value::Null)
-                        } else if has_floats && typed_key_count == 1 {
-                            map.get("floats").cloned().unwrap_or(Value::Null)
-                        } else if has_datetimes && typed_key_count == 1 {
-                            map.get("datetimes").cloned().unwrap_or(Value::Null)
-                        } else {
-                            // Mixed or unknown: pass the whole object so
-                            // `from_json_value` can create a MixedCoordinates
-                            Value::Object(map.clone())
-                        }
-                    }
-                    other => other.clone(),
-                };
+    let value_for_parse = match coords_value {
+        Value::Object(map) if map.len() == 1 && map.contains_key("datetimes") => {
+            coords_value.clone()
+        }
+        _ => coords_for_parse,
+    };
 
-                let value_for_parse = match coords_value {
-                    Value::Object(map) if map.len() == 1 && map.contains_key("datetimes") => {
-                        coords_value.clone()
-                    }
-                    _ => coords_for_parse,
-                };
+    Coordinates::from_json_value(&value_for_parse)
+}
 
-                Coordinates::from_json_value(&value_for_parse)?
-            };
-            let created = if i == 0 {
-                // first entry corresponds to root; update root coords if provided
-                // skip creating a new node; optionally set coords on root
-                index_to_node.insert(0, qube.root());
-                if !coords_parsed.is_empty() {
-                    // mutate root node coords
-                    if let Some(root_node) = qube.node_mut(qube.root()) {
-                        *root_node.coords_mut() = coords_parsed.clone();
-                    }
+/// Rebuilds a Qube from arena records in order.
+struct ArenaBuilder {
+    qube: Qube,
+    index_to_node: Vec<NodeIdx>,
+    /// Whether every node's parent must have come earlier and listed it among
+    /// its children.  Otherwise `children` is ignored and a node without a
+    /// parent is attached to the root.
+    strict: bool,
+    /// Parent index of every child that has been listed but not yet read.
+    pending: HashMap<usize, usize>,
+}
+
+impl ArenaBuilder {
+    fn lenient() -> Self {
+        ArenaBuilder {
+            qube: Qube::new(),
+            index_to_node: Vec::new(),
+            strict: false,
+            pending: HashMap::new(),
+        }
+    }
+
+    fn strict() -> Self {
+        ArenaBuilder { strict: true, ..Self::lenient() }
+    }
+
+    fn push(&mut self, item: &Value) -> Result<(), String> {
+        let i = self.index_to_node.len();
+        let obj = item.as_object().ok_or_else(|| format!("Arena entry {} is not an object", i))?;
+        let dim = obj
+            .get("dim")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| format!("Arena entry {} missing dim", i))?;
+        let coords_value =
+            obj.get("coords").ok_or_else(|| format!("Arena entry {} missing coords", i))?;
+
+        let parent_idx_opt = match obj.get("parent") {
+            Some(Value::Null) | None => None,
+            Some(v) => match v.as_u64() {
+                Some(pi) => Some(pi as usize),
+                None if !self.strict => None,
+                None => return Err(format!("Arena entry {} has an invalid parent {}", i, v)),
+            },
+        };
+
+        // The first entry is the root; every other node must have been
+        // announced by a parent that came before it.
+        let parent_node = match (i, parent_idx_opt) {
+            (0, _) if !self.strict => None,
+            (_, None) if !self.strict => Some(self.qube.root()),
+            (_, Some(pi)) if !self.strict => Some(
+                *self
+                    .index_to_node
+                    .get(pi)
+                    .ok_or_else(|| format!("Parent index {} not found", pi))?,
+            ),
+            (0, None) => None,
+            (0, Some(pi)) => {
+                return Err(format!("Arena entry 0 is the root but has parent {}", pi));
+            }
+            (_, None) => return Err(format!("Arena entry {} has no parent", i)),
+            (_, Some(pi)) => {
+                if self.pending.get(&i) != Some(&pi) {
+                    return Err(format!(
+                        "Arena entry {} has parent {}, which does not list it as a child",
+                        i, pi
+                    ));
                 }
-                qube.root()
-            } else {
-                qube.get_or_create_child(dim, parent_node, Some(coords_parsed))?
-            };
+                self.pending.remove(&i);
+                Some(self.index_to_node[pi])
+            }
+        };
 
-            index_to_node.insert(i, created);
+        if let Some(children) = obj.get("children").filter(|_| self.strict) {
+            let children = children
+                .as_array()
+                .ok_or_else(|| format!("Arena entry {} has invalid children", i))?;
+            for child in children {
+                let ci = child
+                    .as_u64()
+                    .ok_or_else(|| format!("Arena entry {} has an invalid child {}", i, child))?
+                    as usize;
+                if ci <= i {
+                    return Err(format!("Arena entry {} lists child {} before itself", i, ci));
+                }
+                if let Some(other) = self.pending.insert(ci, i) {
+                    return Err(format!(
+                        "Arena entry {} is listed as a child of both {} and {}",
+                        ci, other, i
+                    ));
+                }
+            }
+        }
 
-            // Restore metadata directly on the node, bypassing set_metadata's
-            // consolidation logic so that the exact serialised state is reproduced.
-            if let Some(Value::Object(meta_map)) = obj.get("metadata") {
-                for (key, meta_val) in meta_map {
-                    if let Some(values) = deserialize_metadata_values(meta_val) {
-                        if let Some(node) = qube.node_mut(created) {
-                            node.metadata_mut().set(key.clone(), values);
-                        }
-                    }
+        let coords_parsed = parse_arena_coords(coords_value)?;
+        let created = match parent_node {
+            Some(parent) => self.qube.get_or_create_child(dim, parent, Some(coords_parsed))?,
+            None => {
+                // The root keeps its coords if any were written.
+                let root = self.qube.root();
+                if !coords_parsed.is_empty()
+                    && let Some(root_node) = self.qube.node_mut(root)
+                {
+                    *root_node.coords_mut() = coords_parsed;
+                }
+                root
+            }
+        };
+        self.index_to_node.push(created);
+
+        // Restore metadata directly on the node, bypassing set_metadata's
+        // consolidation logic so that the exact serialised state is reproduced.
+        if let Some(Value::Object(meta_map)) = obj.get("metadata") {
+            for (key, meta_val) in meta_map {
+                if let Some(values) = deserialize_metadata_values(meta_val)
+                    && let Some(node) = self.qube.node_mut(created)
+                {
+                    node.metadata_mut().set(key.clone(), values);
                 }
             }
         }
 
-        Ok(qube)
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Qube, String> {
+        if let Some((child, parent)) = self.pending.iter().min() {
+            return Err(format!("Arena entry {} lists child {}, which is missing", parent, child));
+        }
+        Ok(self.qube)
+    }
+}
+
+/// Reads the `{"version": ..., "qube": [...]}` envelope, accepting the keys in
+/// either order since `to_arena_json` sorts them.
+struct ArenaVisitor;
+
+impl<'de> de::Visitor<'de> for ArenaVisitor {
+    type Value = Qube;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an arena JSON object")
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Qube, A::Error> {
+        let mut version_seen = false;
+        let mut builder = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "version" => {
+                    let version_val: Value = map.next_value()?;
+                    check_arena_version(&version_val).map_err(de::Error::custom)?;
+                    version_seen = true;
+                }
+                "qube" => builder = Some(map.next_value_seed(ArenaNodes)?),
+                _ => {
+                    map.next_value::<de::IgnoredAny>()?;
+                }
+            }
+        }
+        if !version_seen {
+            return Err(de::Error::custom("Arena JSON missing 'version' field"));
+        }
+        let builder =
+            builder.ok_or_else(|| de::Error::custom("Arena JSON missing 'qube' array"))?;
+        builder.finish().map_err(de::Error::custom)
+    }
+}
+
+/// Feeds the records of the `qube` array to an `ArenaBuilder` as they are read.
+struct ArenaNodes;
+
+impl<'de> de::DeserializeSeed<'de> for ArenaNodes {
+    type Value = ArenaBuilder;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<ArenaBuilder, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> de::Visitor<'de> for ArenaNodes {
+    type Value = ArenaBuilder;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an array of arena nodes")
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<ArenaBuilder, A::Error> {
+        let mut builder = ArenaBuilder::strict();
+        while let Some(item) = seq.next_element::<Value>()? {
+            builder.push(&item).map_err(de::Error::custom)?;
+        }
+        Ok(builder)
     }
 }
 
@@ -441,12 +613,16 @@ fn serialize_children_json(qube: &Qube, parent_id: NodeIdx, output: &mut Map<Str
 
 // ---------------- Tree JSON (key/values/metadata/children) ----------------
 
+pub(super) const TREE_JSON_VERSION: &str = "1";
+
 impl Qube {
     /// Serialize the Qube into a recursive tree JSON layout where each node has:
-    /// `{ "key": <dim>, "values": { "type": "enum", "dtype": <type>, "values": [...] }, "metadata": {}, "children": [...] }`
+    /// `{ "key": <dim>, "values": { "type": "enum", "dtype": <type>, "values": [...] }, "metadata": {...}, "children": [...] }`
+    ///
+    /// Metadata is written in the typed form of `to_arena_json`.
     pub fn to_tree_json(&self) -> Value {
         let mut envelope = Map::new();
-        envelope.insert("version".to_string(), Value::String("1".to_string()));
+        envelope.insert("version".to_string(), Value::String(TREE_JSON_VERSION.to_string()));
         envelope.insert("tree".to_string(), serialize_tree_node(self, self.root()));
         Value::Object(envelope)
     }
@@ -459,7 +635,7 @@ impl Qube {
             .get("version")
             .and_then(|v| v.as_str())
             .ok_or("Missing 'version' field in tree JSON")?;
-        if version != "1" {
+        if version != TREE_JSON_VERSION {
             return Err(format!("Unsupported tree JSON version: {version:?}"));
         }
 
@@ -467,18 +643,30 @@ impl Qube {
 
         let mut qube = Qube::new();
         let root = qube.root();
+        if let Some(root_obj) = root_value.as_object() {
+            restore_tree_metadata(&mut qube, root, root_obj);
+        }
         parse_tree_node(&mut qube, root, root_value)?;
         Ok(qube)
     }
 }
 
-fn coords_dtype(coords: &Coordinates) -> &'static str {
+/// Parses a tree JSON date-time, either with seconds (`2024-01-01T06:00:00`)
+/// or in the compact form `to_tree_json` writes (`20240101T0600`).
+pub(super) fn parse_tree_datetime(s: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S")
+        .or_else(|_| NaiveDateTime::parse_from_str(s, "%Y%m%dT%H%M"))
+        .ok()
+}
+
+pub(super) fn coords_dtype(coords: &Coordinates) -> &'static str {
     match coords {
         Coordinates::Empty => "str",
         Coordinates::Integers(_) => "int64",
         Coordinates::Floats(_) => "float64",
         Coordinates::Strings(_) => "str",
         Coordinates::DateTimes(_) => "datetime",
+        Coordinates::Durations(_) => "duration",
         Coordinates::Mixed(_) => "mixed",
     }
 }
@@ -489,7 +677,11 @@ fn serialize_tree_node(qube: &Qube, node_id: NodeIdx) -> Value {
     let coords = node.coordinates();
 
     let dtype = coords_dtype(coords);
-    let values_array = coords.to_json_value();
+    // The tree layout always lists every value, so integer ranges are expanded.
+    let values_array = match coords {
+        Coordinates::Integers(ints) => ints.iter().map(Value::from).collect(),
+        _ => coords.to_json_value(),
+    };
 
     let mut values_obj = Map::new();
     values_obj.insert("type".to_string(), Value::String("enum".to_string()));
@@ -508,12 +700,27 @@ fn serialize_tree_node(qube: &Qube, node_id: NodeIdx) -> Value {
     let mut map = Map::new();
     map.insert("key".to_string(), Value::String(key));
     map.insert("values".to_string(), Value::Object(values_obj));
-    map.insert("metadata".to_string(), Value::Object(Map::new()));
+    let metadata: Map<String, Value> =
+        node.metadata().iter().map(|(k, v)| (k.clone(), serialize_metadata_values(v))).collect();
+    map.insert("metadata".to_string(), Value::Object(metadata));
     map.insert("children".to_string(), Value::Array(children));
 
     Value::Object(map)
 }
 
+/// Restore the metadata of a tree JSON node exactly, as `from_arena_json` does.
+fn restore_tree_metadata(qube: &mut Qube, node_id: NodeIdx, obj: &Map<String, Value>) {
+    if let Some(Value::Object(meta_map)) = obj.get("metadata")
+        && let Some(node) = qube.node_mut(node_id)
+    {
+        for (key, meta_val) in meta_map {
+            if let Some(values) = deserialize_metadata_values(meta_val) {
+                node.metadata_mut().set(key.clone(), values);
+            }
+        }
+    }
+}
+
 fn parse_tree_node(qube: &mut Qube, parent: NodeIdx, value: &Value) -> Result<(), String> {
     let obj = value.as_object().ok_or("Expected JSON object for tree node")?;
 
@@ -534,9 +741,33 @@ fn parse_tree_node(qube: &mut Qube, parent: NodeIdx, value: &Value) -> Result<()
         let values_array =
             values_obj.get("values").ok_or("Missing 'values' array in values object")?;
 
-        let coords = Coordinates::from_json_value(values_array)?;
+        // Durations are listed as strings, so only the dtype tells them apart.
+        let coords = match values_obj.get("dtype").and_then(|v| v.as_str()) {
+            Some("duration") => {
+                let mut typed = Map::new();
+                typed.insert("durations".to_string(), values_array.clone());
+                Coordinates::from_json_value(&Value::Object(typed))?
+            }
+            // Mixed values are written as a one-element list of typed lists.
+            Some("mixed") => match values_array.as_array().map(Vec::as_slice) {
+                Some([typed]) => Coordinates::from_json_value(typed)?,
+                _ => return Err("Expected one object in mixed 'values'".to_string()),
+            },
+            Some("datetime") => {
+                let mut coords = Coordinates::Empty;
+                for v in values_array.as_array().ok_or("Expected 'values' array")? {
+                    let s = v.as_str().ok_or("Expected string in datetime values")?;
+                    coords.append(
+                        parse_tree_datetime(s).ok_or_else(|| format!("Invalid datetime {s:?}"))?,
+                    );
+                }
+                coords
+            }
+            _ => Coordinates::from_json_value(values_array)?,
+        };
 
         let child_node = qube.get_or_create_child(key, parent, Some(coords))?;
+        restore_tree_metadata(qube, child_node, child_obj);
         parse_tree_node(qube, child_node, child_value)?;
     }
 
@@ -676,6 +907,121 @@ mod json_tests {
         assert_eq!(qube.to_json(), reconstructed.to_json());
     }
 
+    fn arena_sample_qube() -> Qube {
+        let mut qube = Qube::from_ascii(
+            r#"root
+├── class=od
+│   ├── expver=0001
+│   │   └── param=1/2/130/to/200/by/2
+│   └── expver=0002
+│       └── step=0h/6h/0-24
+└── class=rd
+    └── levelist=0.5/850.25
+        └── type=cf/fc"#,
+        )
+        .unwrap();
+        let leaf = qube.leaf_node_ids_paths()[0][3];
+        qube.set_metadata(leaf, "path", MetadataValues::single_string("/data/od")).unwrap();
+        qube.set_metadata(leaf, "offset", MetadataValues::from_integers(&[-4, 16])).unwrap();
+        qube
+    }
+
+    #[test]
+    fn test_arena_json_writer_matches_to_arena_json() {
+        let qube = arena_sample_qube();
+        let mut buf = Vec::new();
+        qube.to_arena_json_writer(&mut buf).unwrap();
+        assert!(buf.starts_with(b"{\"version\":\"1\",\"qube\":["));
+        let written: Value = serde_json::from_slice(&buf).unwrap();
+        assert_eq!(written, qube.to_arena_json());
+    }
+
+    #[test]
+    fn test_arena_json_reader_roundtrip() {
+        let qube = arena_sample_qube();
+        let mut buf = Vec::new();
+        qube.to_arena_json_writer(&mut buf).unwrap();
+        let decoded = Qube::from_arena_json_reader(buf.as_slice()).unwrap();
+        assert_eq!(decoded, qube);
+        assert_eq!(decoded.to_arena_json(), qube.to_arena_json());
+
+        // `to_arena_json` sorts its keys, so the nodes come before the version.
+        let text = qube.to_arena_json().to_string();
+        assert!(text.find("\"qube\"") < text.find("\"version\""));
+        assert_eq!(Qube::from_arena_json_reader(text.as_bytes()).unwrap(), qube);
+    }
+
+    #[test]
+    fn test_arena_json_reader_rejects_bad_indices() {
+        let read = |nodes: Value| {
+            let text = json!({"version": "1", "qube": nodes}).to_string();
+            Qube::from_arena_json_reader(text.as_bytes()).unwrap_err()
+        };
+        let node = |dim: &str, parent: Value, children: Value| json!({"dim": dim, "coords": {"strings": ["x"]}, "parent": parent, "children": children});
+
+        let err = read(json!([node("root", json!(1), json!([]))]));
+        assert!(err.contains("root but has parent"), "{err}");
+        let err =
+            read(json!([node("root", Value::Null, json!([1])), node("a", json!(2), json!([]))]));
+        assert!(err.contains("does not list it as a child"), "{err}");
+        let err = read(json!([node("root", Value::Null, json!([0]))]));
+        assert!(err.contains("before itself"), "{err}");
+        let err = read(json!([
+            node("root", Value::Null, json!([1, 2])),
+            node("a", json!(0), json!([2])),
+        ]));
+        assert!(err.contains("child of both 0 and 1"), "{err}");
+        let err = read(json!([node("root", Value::Null, json!([1]))]));
+        assert!(err.contains("child 1, which is missing"), "{err}");
+        let err =
+            read(json!([node("root", Value::Null, json!([])), node("a", Value::Null, json!([]))]));
+        assert!(err.contains("has no parent"), "{err}");
+    }
+
+    #[test]
+    fn test_arena_json_reads_older_files_leniently() {
+        let read = |nodes: Value| Qube::from_arena_json(json!({"version": 1, "qube": nodes}));
+        let expected = |ascii: &str| Qube::from_ascii(ascii).unwrap().to_ascii();
+
+        // Records without `children`, attached through `parent` alone.
+        let qube = read(json!([
+            {"dim": "root", "coords": null},
+            {"dim": "class", "coords": {"strings": ["od"]}, "parent": 0},
+            {"dim": "param", "coords": {"ints": [1, 2]}, "parent": 1},
+        ]))
+        .unwrap();
+        assert_eq!(qube.to_ascii(), expected("root\n└── class=od\n    └── param=1/2"));
+
+        // A missing or null parent hangs the node from the root, and
+        // `children` lists that disagree with the parents are ignored.
+        let qube = read(json!([
+            {"dim": "root", "coords": null, "children": [2]},
+            {"dim": "class", "coords": {"strings": ["od"]}, "parent": null, "children": []},
+            {"dim": "class", "coords": {"strings": ["rd"]}, "children": [5]},
+            {"dim": "param", "coords": {"ints": [1]}, "parent": 2},
+        ]))
+        .unwrap();
+        assert_eq!(qube.to_ascii(), expected("root\n├── class=od\n└── class=rd\n    └── param=1"));
+
+        // A parent that has not been read yet is still an error.
+        let err = read(json!([
+            {"dim": "root", "coords": null},
+            {"dim": "class", "coords": {"strings": ["od"]}, "parent": 2},
+        ]))
+        .unwrap_err();
+        assert!(err.contains("Parent index 2 not found"), "{err}");
+    }
+
+    #[test]
+    fn test_arena_json_reader_rejects_bad_envelope() {
+        let read = |text: &str| Qube::from_arena_json_reader(text.as_bytes()).unwrap_err();
+        assert!(read(r#"{"version": "2", "qube": []}"#).contains("Unsupported arena JSON version"));
+        assert!(read(r#"{"qube": []}"#).contains("missing 'version'"));
+        assert!(read(r#"{"version": "1"}"#).contains("missing 'qube'"));
+        assert!(read(r#"{"version": "1", "qube": []} []"#).contains("trailing"));
+        assert!(read("[]").contains("Invalid arena JSON"));
+    }
+
     // ---------------- Tree JSON tests ----------------
 
     fn simple_qube() -> Qube {
@@ -735,6 +1081,47 @@ mod json_tests {
         assert_eq!(qube.to_json(), decoded.to_json());
     }
 
+    #[test]
+    fn test_ranges_roundtrip_in_every_layout() {
+        let qube = Qube::from_json(json!({
+            "class=od": {
+                "date=20240101/to/20240131": {
+                    "step=0/to/240/by/6": {}
+                }
+            }
+        }))
+        .unwrap();
+
+        let json = qube.to_json();
+        assert!(json.get("class=od").unwrap().get("date=20240101/to/20240131").is_some());
+        assert_eq!(Qube::from_json(json).unwrap(), qube);
+        assert_eq!(Qube::from_arena_json(qube.to_arena_json()).unwrap(), qube);
+        assert_eq!(Qube::from_tree_json(qube.to_tree_json()).unwrap(), qube);
+    }
+
+    #[test]
+    fn test_durations_roundtrip_in_every_layout() {
+        let qube = Qube::from_json(json!({
+            "class=od": {
+                "step=0h/6h/12h/0-24": {}
+            }
+        }))
+        .unwrap();
+
+        let arena = qube.to_arena_json();
+        assert!(arena.to_string().contains("\"durations\""));
+        for decoded in [
+            Qube::from_json(qube.to_json()).unwrap(),
+            Qube::from_arena_json(arena).unwrap(),
+            Qube::from_tree_json(qube.to_tree_json()).unwrap(),
+        ] {
+            assert_eq!(decoded, qube);
+            let step = decoded.node(decoded.root()).unwrap().all_children().next().unwrap();
+            let step = decoded.node(step).unwrap().all_children().next().unwrap();
+            assert!(matches!(decoded.node(step).unwrap().coordinates(), Coordinates::Durations(_)));
+        }
+    }
+
     #[test]
     fn test_tree_json_rejects_unknown_version() {
         let bad = json!({"version": "99", "tree": {}});
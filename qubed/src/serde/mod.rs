@@ -1,2 +1,6 @@
 pub mod ascii;
+pub mod binary;
 pub mod json;
+#[cfg(feature = "serde")]
+mod traits;
+pub mod yaml;
@@ -0,0 +1,482 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use ::serde::de::{self, DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use ::serde::ser::SerializeStruct;
+use ::serde::{Deserialize, Deserializer, Serialize, Serializer};
+use chrono::NaiveDateTime;
+
+use super::json::{TREE_JSON_VERSION, coords_dtype, parse_tree_datetime};
+use crate::coordinates::MixedCoordinates;
+use crate::coordinates::durations::DurationValue;
+use crate::coordinates::strings::StringCoordinates;
+use crate::{Coordinates, Datacube, Metadata, MetadataValues, NodeIdx, Qube};
+
+// ---------------- Layout ----------------
+//
+// The layout of `Qube::to_tree_json`, so a Qube serialised to JSON reads back
+// with `Qube::from_tree_json` and the other way round:
+//
+//   Qube:         { "version": "1", "tree": <node> }
+//   node:         { "key", "values": <coordinates>, "metadata", "children": [<node>] }
+//   Coordinates:  { "type": "enum", "dtype", "values": [...] }
+//   Metadata:     { <key>: <metadata values> }
+//   MetadataValues: { "ints": [...] }, { "strings": [...] },
+//                 { "per_coord_strings": [[...]] }, or null when empty
+//   Datacube:     { <dimension>: <coordinates> }
+//
+// The type of "values" depends on "dtype", so formats without a schema, such
+// as bincode, can read it back.  Date-times are written with seconds, where
+// `to_tree_json` drops them, and mixed coordinates as one object listing
+// each type.  Float tolerances are not part of the layout and are left at
+// their defaults.
+
+const DATETIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+const DTYPES: &[&str] = &["int64", "float64", "str", "datetime", "duration", "mixed"];
+
+// ---------------- Coordinates ----------------
+
+/// The values of `Mixed` coordinates.  Every type is always written, so the
+/// layout does not depend on which are present.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct MixedValues {
+    ints: Vec<i32>,
+    floats: Vec<f64>,
+    strings: Vec<String>,
+    datetimes: Vec<String>,
+    durations: Vec<String>,
+}
+
+impl Serialize for Coordinates {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Coordinates", 3)?;
+        state.serialize_field("type", "enum")?;
+        state.serialize_field("dtype", coords_dtype(self))?;
+        match self {
+            Coordinates::Integers(ints) => {
+                state.serialize_field("values", &ints.iter().collect::<Vec<i32>>())?
+            }
+            Coordinates::Floats(floats) => {
+                state.serialize_field("values", &floats.iter().collect::<Vec<f64>>())?
+            }
+            Coordinates::Mixed(mixed) => {
+                let StringCoordinates::Set(strings) = &mixed.strings;
+                let values = MixedValues {
+                    ints: mixed.integers.iter().collect(),
+                    floats: mixed.floats.iter().collect(),
+                    strings: strings.iter().map(|s| s.to_string()).collect(),
+                    datetimes: mixed
+                        .datetimes
+                        .iter()
+                        .map(|dt| dt.format(DATETIME_FORMAT).to_string())
+                        .collect(),
+                    durations: mixed.durations.iter().map(|d| d.to_string()).collect(),
+                };
+                state.serialize_field("values", &[values])?
+            }
+            // Strings, date-times and durations are written as they display.
+            _ => state.serialize_field(
+                "values",
+                &self.values().map(|v| v.to_string()).collect::<Vec<String>>(),
+            )?,
+        }
+        state.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Coordinates {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_struct(
+            "Coordinates",
+            &["type", "dtype", "values"],
+            CoordinatesVisitor,
+        )
+    }
+}
+
+struct CoordinatesVisitor;
+
+impl<'de> Visitor<'de> for CoordinatesVisitor {
+    type Value = Coordinates;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("coordinates as {type, dtype, values}")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Coordinates, A::Error> {
+        let _: IgnoredAny =
+            seq.next_element()?.ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let dtype: String =
+            seq.next_element()?.ok_or_else(|| de::Error::invalid_length(1, &self))?;
+        seq.next_element_seed(ValuesOf(&dtype))?.ok_or_else(|| de::Error::invalid_length(2, &self))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Coordinates, A::Error> {
+        let mut dtype: Option<String> = None;
+        let mut coords = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "dtype" => dtype = Some(map.next_value()?),
+                "values" => {
+                    // Only the dtype says how to read the values.
+                    let dtype = dtype.as_deref().ok_or_else(|| {
+                        de::Error::custom("coordinates 'dtype' must come before 'values'")
+                    })?;
+                    coords = Some(map.next_value_seed(ValuesOf(dtype))?);
+                }
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        coords.ok_or_else(|| de::Error::missing_field("values"))
+    }
+}
+
+/// Reads the "values" of coordinates with the given dtype.
+struct ValuesOf<'a>(&'a str);
+
+impl<'de> DeserializeSeed<'de> for ValuesOf<'_> {
+    type Value = Coordinates;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Coordinates, D::Error> {
+        let mut coords = Coordinates::Empty;
+        match self.0 {
+            "int64" => {
+                Vec::<i32>::deserialize(deserializer)?.into_iter().for_each(|v| coords.append(v))
+            }
+            "float64" => {
+                Vec::<f64>::deserialize(deserializer)?.into_iter().for_each(|v| coords.append(v))
+            }
+            "str" => {
+                Vec::<String>::deserialize(deserializer)?.into_iter().for_each(|v| coords.append(v))
+            }
+            "datetime" => {
+                for v in Vec::<String>::deserialize(deserializer)? {
+                    coords.append(parse_datetime(&v)?);
+                }
+            }
+            "duration" => {
+                for v in Vec::<String>::deserialize(deserializer)? {
+                    coords.append(parse_duration(&v)?);
+                }
+            }
+            "mixed" => {
+                let [values] = <[MixedValues; 1]>::deserialize(deserializer)?;
+                let mut mixed = MixedCoordinates::default();
+                values.ints.into_iter().for_each(|v| mixed.integers.append(v));
+                values.floats.into_iter().for_each(|v| mixed.floats.append(v));
+                values.strings.into_iter().for_each(|v| mixed.strings.append(v));
+                for v in values.datetimes {
+                    mixed.datetimes.append(parse_datetime(&v)?);
+                }
+                for v in values.durations {
+                    mixed.durations.append(parse_duration(&v)?);
+                }
+                coords = Coordinates::Mixed(Box::new(mixed));
+            }
+            other => return Err(de::Error::unknown_variant(other, DTYPES)),
+        }
+        Ok(coords)
+    }
+}
+
+fn parse_datetime<E: de::Error>(s: &str) -> Result<NaiveDateTime, E> {
+    parse_tree_datetime(s).ok_or_else(|| E::custom(format!("Invalid datetime {s:?}")))
+}
+
+fn parse_duration<E: de::Error>(s: &str) -> Result<DurationValue, E> {
+    DurationValue::parse(s).ok_or_else(|| E::custom(format!("Invalid duration {s:?}")))
+}
+
+// ---------------- Metadata ----------------
+
+/// `MetadataValues` other than `Empty`, tagged like `serialize_metadata_values`.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum TaggedValues {
+    Ints(Vec<i32>),
+    Strings(Vec<String>),
+    PerCoordStrings(Vec<Vec<String>>),
+}
+
+impl Serialize for MetadataValues {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let tagged = match self {
+            MetadataValues::Empty => None,
+            MetadataValues::Integers(set) => {
+                Some(TaggedValues::Ints(set.iter().copied().collect()))
+            }
+            MetadataValues::Strings(set) => {
+                Some(TaggedValues::Strings(set.iter().map(|s| s.to_string()).collect()))
+            }
+            MetadataValues::PerCoordStrings(strings) => {
+                Some(TaggedValues::PerCoordStrings(strings.clone()))
+            }
+        };
+        tagged.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for MetadataValues {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(match Option::<TaggedValues>::deserialize(deserializer)? {
+            None => MetadataValues::Empty,
+            Some(TaggedValues::Ints(ints)) => MetadataValues::from_integers(&ints),
+            Some(TaggedValues::Strings(strings)) => MetadataValues::from_strings(
+                &strings.iter().map(String::as_str).collect::<Vec<_>>(),
+            ),
+            Some(TaggedValues::PerCoordStrings(strings)) => {
+                MetadataValues::PerCoordStrings(strings)
+            }
+        })
+    }
+}
+
+impl Serialize for Metadata {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // Sorted, so the same metadata always serialises the same way.
+        serializer.collect_map(self.iter().collect::<BTreeMap<_, _>>())
+    }
+}
+
+impl<'de> Deserialize<'de> for Metadata {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut metadata = Metadata::new();
+        for (key, values) in HashMap::<String, MetadataValues>::deserialize(deserializer)? {
+            metadata.set(key, values);
+        }
+        Ok(metadata)
+    }
+}
+
+// ---------------- Datacube ----------------
+
+impl Serialize for Datacube {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.coordinates().iter().collect::<BTreeMap<_, _>>())
+    }
+}
+
+impl<'de> Deserialize<'de> for Datacube {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut datacube = Datacube::new();
+        for (dim, coords) in HashMap::<String, Coordinates>::deserialize(deserializer)? {
+            datacube.add_coordinate(&dim, coords);
+        }
+        Ok(datacube)
+    }
+}
+
+// ---------------- Qube ----------------
+
+impl Serialize for Qube {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Qube", 2)?;
+        state.serialize_field("version", TREE_JSON_VERSION)?;
+        state.serialize_field("tree", &TreeNode { qube: self, id: self.root() })?;
+        state.end()
+    }
+}
+
+struct TreeNode<'q> {
+    qube: &'q Qube,
+    id: NodeIdx,
+}
+
+impl Serialize for TreeNode<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let node = self.qube.node(self.id).expect("valid node");
+        let children: Vec<TreeNode> =
+            node.all_children().map(|id| TreeNode { qube: self.qube, id }).collect();
+
+        let mut state = serializer.serialize_struct("Node", 4)?;
+        state.serialize_field("key", node.dimension().unwrap_or("root"))?;
+        state.serialize_field("values", node.coordinates())?;
+        state.serialize_field("metadata", node.metadata())?;
+        state.serialize_field("children", &children)?;
+        state.end()
+    }
+}
+
+#[derive(Deserialize)]
+struct OwnedTree {
+    version: String,
+    tree: OwnedNode,
+}
+
+#[derive(Deserialize)]
+struct OwnedNode {
+    key: String,
+    values: Coordinates,
+    #[serde(default)]
+    metadata: Metadata,
+    children: Vec<OwnedNode>,
+}
+
+impl<'de> Deserialize<'de> for Qube {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let OwnedTree { version, tree } = OwnedTree::deserialize(deserializer)?;
+        if version != TREE_JSON_VERSION {
+            return Err(de::Error::custom(format!("Unsupported tree JSON version: {version:?}")));
+        }
+
+        // The root's key is always "root", so it is ignored.
+        let mut qube = Qube::new();
+        let root = qube.root();
+        if let Some(node) = qube.node_mut(root) {
+            *node.coords_mut() = tree.values;
+            *node.metadata_mut() = tree.metadata;
+        }
+
+        let mut stack: Vec<(NodeIdx, OwnedNode)> =
+            tree.children.into_iter().rev().map(|child| (root, child)).collect();
+        while let Some((parent, child)) = stack.pop() {
+            let dim = qube.get_or_intern_dim(&child.key);
+            let id = qube.insert_child(dim, parent, child.values);
+            if let Some(node) = qube.node_mut(id) {
+                *node.metadata_mut() = child.metadata;
+            }
+            stack.extend(child.children.into_iter().rev().map(|grandchild| (id, grandchild)));
+        }
+        Ok(qube)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use serde_json::json;
+
+    fn sample_qube() -> Qube {
+        let mut qube = Qube::from_ascii(
+            r#"root
+├── class=od
+│   ├── expver=0001
+│   │   └── param=1/2/130/to/200/by/2
+│   └── expver=0002
+│       └── step=0h/6h/0-24
+└── class=rd
+    └── levelist=0.5/850.25
+        └── time=2024-01-01T00:00:00/to/2024-01-03T00:00:00/by/6h"#,
+        )
+        .unwrap();
+        let mut mixed = Coordinates::from_string("1/2");
+        mixed.append("x".to_string());
+        mixed.append(NaiveDate::from_ymd_opt(2024, 5, 1).unwrap().and_hms_opt(12, 0, 30).unwrap());
+        qube.get_or_create_child("mixed", qube.root(), Some(mixed)).unwrap();
+
+        let leaf = qube.leaf_node_ids_paths()[0][3];
+        qube.set_metadata(leaf, "path", MetadataValues::single_string("/data/od")).unwrap();
+        qube.set_metadata(leaf, "offset", MetadataValues::from_integers(&[-4, 16, 1024])).unwrap();
+        let class = qube.leaf_node_ids_paths()[0][1];
+        qube.node_mut(class).unwrap().metadata_mut().set(
+            "host".to_string(),
+            MetadataValues::PerCoordStrings(vec![vec!["a".to_string(), "b".to_string()]]),
+        );
+        qube
+    }
+
+    fn nodes(qube: &Qube) -> Vec<(String, String, Metadata)> {
+        qube.leaf_node_ids_paths()
+            .iter()
+            .flatten()
+            .map(|&id| {
+                let node = qube.node(id).unwrap();
+                let dim = node.dimension().unwrap_or("root").to_string();
+                (dim, node.coordinates().to_string(), node.metadata().clone())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_serde_json_roundtrip() {
+        let qube = sample_qube();
+        let json = serde_json::to_string(&qube).unwrap();
+        let decoded: Qube = serde_json::from_str(&json).unwrap();
+        assert_eq!(nodes(&decoded), nodes(&qube));
+    }
+
+    #[test]
+    fn test_serde_messagepack_roundtrip() {
+        // MessagePack writes structs as arrays, without field names.
+        let qube = sample_qube();
+        let bytes = rmp_serde::to_vec(&qube).unwrap();
+        let decoded: Qube = rmp_serde::from_slice(&bytes).unwrap();
+        assert_eq!(nodes(&decoded), nodes(&qube));
+    }
+
+    #[test]
+    fn test_serde_json_matches_tree_json() {
+        let mut qube = Qube::from_ascii(
+            r#"root
+├── class=od
+│   └── param=1/2/3
+└── class=rd
+    └── levelist=0.5/850.25"#,
+        )
+        .unwrap();
+        let leaf = qube.leaf_node_ids_paths()[0][2];
+        qube.set_metadata(leaf, "path", MetadataValues::single_string("/data/od")).unwrap();
+
+        assert_eq!(serde_json::to_value(&qube).unwrap(), qube.to_tree_json());
+        let decoded: Qube = serde_json::from_value(qube.to_tree_json()).unwrap();
+        assert_eq!(nodes(&decoded), nodes(&qube));
+        let from_tree = Qube::from_tree_json(serde_json::to_value(&qube).unwrap()).unwrap();
+        assert_eq!(nodes(&from_tree), nodes(&qube));
+
+        // Date-times read back either way, whole minutes surviving `to_tree_json`.
+        let qube =
+            Qube::from_ascii("root\n└── time=2024-01-01T00:00:00/2024-01-01T06:00:00").unwrap();
+        assert_eq!(qube.to_tree_json()["tree"]["children"][0]["values"]["dtype"], "datetime");
+        let decoded: Qube = serde_json::from_value(qube.to_tree_json()).unwrap();
+        assert_eq!(nodes(&decoded), nodes(&qube));
+        let from_tree = Qube::from_tree_json(serde_json::to_value(&qube).unwrap()).unwrap();
+        assert_eq!(nodes(&from_tree), nodes(&qube));
+    }
+
+    #[test]
+    fn test_serde_coordinates_metadata_and_datacube() {
+        let coords = Coordinates::from_dimension_string("step", "6h/12h/0-24");
+        let value = serde_json::to_value(&coords).unwrap();
+        assert_eq!(
+            value,
+            json!({"type": "enum", "dtype": "duration", "values": ["0-24", "6h", "12h"]})
+        );
+        assert_eq!(serde_json::from_value::<Coordinates>(value).unwrap(), coords);
+
+        let metadata = Metadata::single_key("offset", MetadataValues::from_integers(&[4, 8]));
+        let value = serde_json::to_value(&metadata).unwrap();
+        assert_eq!(value, json!({"offset": {"ints": [4, 8]}}));
+        assert_eq!(serde_json::from_value::<Metadata>(value).unwrap(), metadata);
+        assert_eq!(serde_json::to_value(MetadataValues::Empty).unwrap(), json!(null));
+
+        let mut datacube = Datacube::new();
+        datacube.add_coordinate("param", Coordinates::from_string("1/2"));
+        let decoded: Datacube =
+            rmp_serde::from_slice(&rmp_serde::to_vec(&datacube).unwrap()).unwrap();
+        assert_eq!(decoded.coordinates(), datacube.coordinates());
+    }
+
+    #[test]
+    fn test_serde_rejects_bad_input() {
+        let mut tree = sample_qube().to_tree_json();
+        tree["version"] = json!("2");
+        let err = serde_json::from_value::<Qube>(tree).err().unwrap();
+        assert!(err.to_string().contains("Unsupported tree JSON version"), "{}", err);
+
+        let err = serde_json::from_value::<Coordinates>(
+            json!({"type": "enum", "dtype": "colour", "values": []}),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("colour"), "{}", err);
+
+        let err = serde_json::from_value::<Coordinates>(
+            json!({"type": "enum", "dtype": "datetime", "values": ["tomorrow"]}),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("Invalid datetime"), "{}", err);
+    }
+}
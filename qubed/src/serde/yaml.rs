@@ -0,0 +1,226 @@
+use serde_yaml::{Mapping, Value};
+
+use crate::coordinates::integers::IntegerCoordinates;
+use crate::{Coordinates, Datacube, Qube};
+
+// ---------------- YAML ----------------
+//
+// Two forms are read and written:
+//
+//   tree:       the layout of `to_tree_json`, values tagged with their dtype
+//   datacubes:  a list of mappings from dimension to values, in nesting order
+//
+//       - {class: od, expver: "0001", param: [1, 2]}
+//       - {class: rd, param: 0/to/240/by/6}
+//
+// In the datacube form a list keeps the type of each element, while a single
+// string is parsed like the values of `from_ascii`, so MARS-style ranges,
+// date-times and durations can be written compactly.
+
+impl Qube {
+    /// Serialize the Qube as YAML in the tree layout of `to_tree_json`.
+    pub fn to_yaml(&self) -> String {
+        serde_yaml::to_string(&self.to_tree_json()).expect("tree JSON is valid YAML")
+    }
+
+    /// Serialize the Qube as a YAML list of datacubes, one per leaf path, with
+    /// dimensions in nesting order.  Metadata is not written.
+    pub fn to_yaml_datacubes(&self) -> String {
+        let datacubes: Vec<Value> = self
+            .iter_leaf_paths()
+            .filter(|path| path.len() > 1)
+            .map(|path| {
+                let mut mapping = Mapping::new();
+                // The first node of every path is the root.
+                for &id in &path[1..] {
+                    let node = self.node(id).expect("valid node");
+                    let dim = node.dimension().unwrap_or("root").to_string();
+                    let coords = coords_to_yaml(&dim, node.coordinates());
+                    mapping.insert(Value::String(dim), coords);
+                }
+                Value::Mapping(mapping)
+            })
+            .collect();
+        serde_yaml::to_string(&datacubes).expect("datacubes are valid YAML")
+    }
+
+    /// Reconstruct a Qube from YAML in either the tree form written by
+    /// `to_yaml` or the datacube form written by `to_yaml_datacubes`.
+    pub fn from_yaml(input: &str) -> Result<Qube, String> {
+        let value: Value = serde_yaml::from_str(input).map_err(|e| format!("Invalid YAML: {e}"))?;
+        match value {
+            Value::Sequence(datacubes) => {
+                let mut qube = Qube::new();
+                for entry in &datacubes {
+                    let (datacube, order) = datacube_from_yaml(entry)?;
+                    qube.append_datacube(datacube, Some(&order), false);
+                }
+                Ok(qube)
+            }
+            Value::Mapping(_) => {
+                let json = serde_json::to_value(&value)
+                    .map_err(|e| format!("YAML tree is not valid tree JSON: {e}"))?;
+                Qube::from_tree_json(json)
+            }
+            _ => Err("Expected a YAML tree or a list of datacubes".to_string()),
+        }
+    }
+}
+
+/// Writes integers, floats and strings as a scalar or list of scalars, and
+/// other coordinates in their compact string form.
+fn coords_to_yaml(dim: &str, coords: &Coordinates) -> Value {
+    let values: Vec<Value> = match coords {
+        Coordinates::Integers(ints @ IntegerCoordinates::Set(_)) => {
+            ints.iter().map(Value::from).collect()
+        }
+        Coordinates::Floats(floats) => floats.iter().map(Value::from).collect(),
+        Coordinates::Strings(_) => {
+            let strings: Vec<String> = coords.values().map(|v| v.to_string()).collect();
+            // A lone string that would parse as something else stays in a list.
+            if let [s] = strings.as_slice()
+                && !matches!(Coordinates::from_dimension_string(dim, s), Coordinates::Strings(_))
+            {
+                return Value::Sequence(vec![Value::String(s.clone())]);
+            }
+            strings.into_iter().map(Value::String).collect()
+        }
+        _ => return Value::String(coords.to_string()),
+    };
+    match <[Value; 1]>::try_from(values) {
+        Ok([value]) => value,
+        Err(values) => Value::Sequence(values),
+    }
+}
+
+/// Reads one datacube of the list form, with its dimensions in order.
+fn datacube_from_yaml(entry: &Value) -> Result<(Datacube, Vec<String>), String> {
+    let mapping = entry.as_mapping().ok_or("Expected each datacube to be a YAML mapping")?;
+    let mut datacube = Datacube::new();
+    let mut order = Vec::with_capacity(mapping.len());
+    for (key, value) in mapping {
+        let key = key.as_str().ok_or_else(|| format!("Datacube keys must be strings: {key:?}"))?;
+        let coords = match value {
+            Value::Sequence(items) => {
+                let mut coords = Coordinates::new();
+                for item in items {
+                    append_yaml_scalar(&mut coords, key, item)?;
+                }
+                coords
+            }
+            Value::String(s) => Coordinates::from_dimension_string(key, s),
+            scalar => {
+                let mut coords = Coordinates::new();
+                append_yaml_scalar(&mut coords, key, scalar)?;
+                coords
+            }
+        };
+        datacube.add_coordinate(key, coords);
+        order.push(key.to_string());
+    }
+    Ok((datacube, order))
+}
+
+fn append_yaml_scalar(coords: &mut Coordinates, key: &str, value: &Value) -> Result<(), String> {
+    match value {
+        Value::Number(n) if n.is_i64() || n.is_u64() => {
+            let int = n.as_i64().and_then(|v| i32::try_from(v).ok());
+            coords.append(int.ok_or_else(|| format!("Integer {n} of '{key}' is out of range"))?);
+        }
+        Value::Number(n) => coords.append(n.as_f64().expect("YAML numbers are i64, u64 or f64")),
+        Value::String(s) => coords.append(s.clone()),
+        other => return Err(format!("Unsupported value for '{key}': {other:?}")),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_qube() -> Qube {
+        Qube::from_ascii(
+            r#"root
+├── class=od
+│   ├── expver=0001
+│   │   └── param=1/2/130/to/200/by/2
+│   └── expver=0002
+│       └── step=0h/6h/0-24
+└── class=rd
+    ├── levelist=0.5/850.25
+    │   └── time=2024-01-01T00:00:00/to/2024-01-03T00:00:00/by/6h
+    └── type=1/fc"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_yaml_tree_roundtrip() {
+        let qube = sample_qube();
+        let yaml = qube.to_yaml();
+        assert!(yaml.contains("dtype: duration"), "{}", yaml);
+        assert_eq!(Qube::from_yaml(&yaml).unwrap().to_ascii(), qube.to_ascii());
+    }
+
+    #[test]
+    fn test_yaml_datacubes_roundtrip() {
+        let qube = sample_qube();
+        let yaml = qube.to_yaml_datacubes();
+        assert!(yaml.contains("param: 1/2/130/to/200/by/2"), "{}", yaml);
+        assert_eq!(Qube::from_yaml(&yaml).unwrap().to_ascii(), qube.to_ascii());
+    }
+
+    #[test]
+    fn test_from_yaml_datacube_list() {
+        let qube = Qube::from_yaml(
+            r#"
+- {class: od, expver: "0001", param: [1, 2]}
+- class: rd
+  expver: "0001"
+  param: 0/to/12/by/6
+  levelist: [0.5, 850]
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            qube.to_ascii(),
+            Qube::from_ascii(
+                r#"root
+├── class=od
+│   └── expver=0001
+│       └── param=1/2
+└── class=rd
+    └── expver=0001
+        └── param=0/6/12
+            └── levelist=0.5/850"#
+            )
+            .unwrap()
+            .to_ascii()
+        );
+    }
+
+    #[test]
+    fn test_yaml_datacubes_keep_duration_like_params() {
+        let qube = Qube::from_yaml(
+            "- {class: od, param: 2d, step: 6h}\n- {class: od, param: 10m, step: 0-24}",
+        )
+        .unwrap();
+        let ascii = qube.to_ascii();
+        assert!(ascii.contains("param=2d") && ascii.contains("param=10m"), "{ascii}");
+        assert!(ascii.contains("step=6h") && ascii.contains("step=0-24"), "{ascii}");
+
+        let yaml = qube.to_yaml_datacubes();
+        assert!(yaml.contains("param: 2d"), "{yaml}");
+        assert_eq!(Qube::from_yaml(&yaml).unwrap().to_ascii(), ascii);
+    }
+
+    #[test]
+    fn test_from_yaml_rejects_bad_input() {
+        assert!(Qube::from_yaml("class: [").unwrap_err().starts_with("Invalid YAML"));
+        assert!(Qube::from_yaml("42").is_err());
+        assert!(Qube::from_yaml("- [1, 2]").unwrap_err().contains("mapping"));
+        assert!(Qube::from_yaml("- {param: [true]}").unwrap_err().contains("param"));
+        assert!(Qube::from_yaml("- {param: 3000000000}").unwrap_err().contains("out of range"));
+        assert!(Qube::from_yaml("version: '2'\ntree: {}").unwrap_err().contains("version"));
+    }
+}
@@ -57,6 +57,20 @@ impl<T, const CAP: usize> TinyOrderedSet<T, CAP> {
         }
     }
 
+    /// Iterates, in order, over the elements that fall within `range`.
+    pub fn range<R>(&self, range: R) -> impl DoubleEndedIterator<Item = &T> + '_
+    where
+        T: Ord,
+        R: std::ops::RangeBounds<T> + 'static,
+    {
+        match self {
+            TinyOrderedSet::Vec(vec) => {
+                itertools::Either::Left(vec.iter().filter(move |v| range.contains(*v)))
+            }
+            TinyOrderedSet::BTreeSet(set) => itertools::Either::Right(set.range(range)),
+        }
+    }
+
     pub fn len(&self) -> usize {
         match self {
             TinyOrderedSet::Vec(vec) => vec.len(),
@@ -362,4 +376,20 @@ mod tests {
         assert!(!set.contains(&0)); // Before first
         assert!(!set.contains(&25)); // After last
     }
+
+    #[test]
+    fn test_range_in_both_variants() {
+        for cap_exceeded in [false, true] {
+            let mut set: TinyOrderedSet<i32, 4> = TinyOrderedSet::new();
+            let values: &[i32] = if cap_exceeded { &[1, 3, 5, 7, 9, 11] } else { &[1, 3, 5, 7] };
+            for &v in values {
+                set.insert(v);
+            }
+
+            let below: Vec<_> = set.range(..5).rev().copied().collect();
+            assert_eq!(below, vec![3, 1]);
+            let from: Vec<_> = set.range(5..).copied().collect();
+            assert_eq!(from, values[2..].to_vec());
+        }
+    }
 }
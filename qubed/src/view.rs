@@ -1,138 +1,586 @@
-// use std::collections::HashMap;
-
-// use crate::{
-//     Dimension,
-//     qube::{Qube, NodeIdx},
-// };
-
-// use smallbitvec::SmallBitVec;
-
-// pub struct QubeView<'a> {
-//     qube: &'a Qube,
-
-//     /// Mapping from QubeNodeId to QubeViewNode
-//     masks: HashMap<NodeIdx, QubeNodeMask>,
-// }
-
-// struct QubeNodeMask {
-//     /// The ID of the node in the original Qube
-//     _node_id: NodeIdx,
-//     _values_mask: SmallBitVec,
-//     children_mask: SmallBitVec,
-// }
-
-// impl QubeView<'_> {
-//     pub fn new(qube: &Qube) -> QubeView {
-//         let mut view = QubeView {
-//             qube,
-//             masks: HashMap::new(),
-//         };
-//         // Always add the root node with empty masks
-//         let root_id = qube.root();
-//         let mask = view.create_mask(root_id).unwrap();
-//         view.masks.insert(root_id, mask);
-//         view
-//     }
-
-//     pub fn add_to_view(
-//         &mut self,
-//         node_id: NodeIdx,
-//         _values_mask: SmallBitVec,
-//         _children_mask: SmallBitVec,
-//     ) -> Result<(), String> {
-//         let mask = self.create_mask(node_id)?;
-
-//         // Check the parent has already been added. We don't allow floating nodes.
-//         if let Some(node) = self.qube.get_node(node_id) {
-//             if let Some(parent_id) = node.parent() {
-//                 if !self.masks.contains_key(&parent_id) && parent_id != self.qube.root() {
-//                     return Err(format!(
-//                         "Parent node {:?} has not been added to the view yet",
-//                         parent_id
-//                     ));
-//                 }
-//             }
-//         } else {
-//             return Err(format!("Node {:?} not found in the original Qube", node_id));
-//         }
-
-//         self.masks.insert(node_id, mask);
-
-//         Ok(())
-//     }
-
-//     fn create_mask(&mut self, node_id: NodeIdx) -> Result<QubeNodeMask, String> {
-//         let node = self
-//             .qube
-//             .get_node(node_id)
-//             .ok_or(format!("Node {:?} not found in the original Qube", node_id))?;
-//         let num_values = node.values_count();
-//         let num_children = node.children_count();
-
-//         let mask = QubeNodeMask {
-//             _node_id: node_id,
-//             _values_mask: SmallBitVec::from_elem(num_values, false),
-//             children_mask: SmallBitVec::from_elem(num_children, false),
-//         };
-//         Ok(mask)
-//     }
-
-//     fn get_mask(&self, node_id: NodeIdx) -> Result<&QubeNodeMask, String> {
-//         self.masks
-//             .get(&node_id)
-//             .ok_or(format!("No mask found for node id {:?}", node_id))
-//     }
-
-//     fn get_node(&self, node_id: NodeIdx) -> Result<&crate::node::Node, String> {
-//         self.qube
-//             .get_node(node_id)
-//             .ok_or(format!("No node found for id {:?}", node_id))
-//     }
-
-//     pub fn get_all_children_of(
-//         &self,
-//         node_id: NodeIdx,
-//     ) -> Result<impl Iterator<Item = &NodeIdx> + '_, String> {
-//         let mask = self.get_mask(node_id)?;
-//         let node = self.get_node(node_id)?;
-//         let mut filtered_children = Vec::new();
-
-//         // the mask covers the entire BTreeMap<MiniSpur, TinyVec<QubeNodeId, 4>> so we need to iterate over it
-//         let mut i = 0;
-//         for (_child_key, children) in node.children().iter() {
-//             for child_id in children.iter() {
-//                 i += 1;
-//                 if mask.children_mask.get(i).unwrap_or(false) {
-//                     filtered_children.push(child_id);
-//                 }
-//             }
-//         }
-//         Ok(filtered_children.into_iter())
-//     }
-
-//     pub fn get_children_of(
-//         &self,
-//         node_id: NodeIdx,
-//         key: Dimension,
-//     ) -> Result<impl Iterator<Item = &NodeIdx> + '_, String> {
-//         let mask = self.get_mask(node_id)?;
-//         let node = self.get_node(node_id)?;
-//         let mut filtered_children = Vec::new();
-
-//         // the mask covers the entire BTreeMap<MiniSpur, TinyVec<QubeNodeId, 4>> so we need to iterate over it
-//         let mut i = 0;
-//         for (child_key, children) in node.children().iter() {
-//             if key != *child_key {
-//                 i += node.children()[child_key].len();
-//                 continue;
-//             }
-//             for child_id in children.iter() {
-//                 i += 1;
-//                 if mask.children_mask.get(i).unwrap_or(false) {
-//                     filtered_children.push(child_id);
-//                 }
-//             }
-//         }
-//         Ok(filtered_children.into_iter())
-//     }
-// }
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+
+use smallbitvec::SmallBitVec;
+
+use crate::metadata::Metadata;
+use crate::qube::Node;
+use crate::select::{SelectMode, Selector};
+use crate::{Coordinates, Datacube, NodeIdx, Qube};
+
+/// A read-only selection of a [`Qube`]: the same tree, with some of its values
+/// and branches masked out.
+///
+/// Nothing is copied out of the Qube.  A node that has been narrowed down has
+/// a mask over its values and one over its children; a node without masks is
+/// seen whole, together with everything below it.  Views can be selected from
+/// again, and [`QubeView::materialize`] turns one into a Qube of its own.
+#[derive(Debug, Clone)]
+pub struct QubeView<'a> {
+    qube: &'a Qube,
+    masks: HashMap<NodeIdx, QubeNodeMask>,
+}
+
+#[derive(Debug, Clone)]
+struct QubeNodeMask {
+    /// One bit per value, in the order of `Coordinates::values`.
+    values: SmallBitVec,
+    /// One bit per child, in the order of `NodeRef::all_children`.
+    children: SmallBitVec,
+}
+
+/// A node seen through a [`QubeView`].
+pub struct QubeViewNode<'v, 'a> {
+    view: &'v QubeView<'a>,
+    id: NodeIdx,
+    node: &'a Node,
+}
+
+impl<'a> QubeView<'a> {
+    /// A view of the whole of `qube`.
+    pub fn new(qube: &'a Qube) -> Self {
+        QubeView { qube, masks: HashMap::new() }
+    }
+
+    /// The Qube this is a view of.
+    pub fn qube(&self) -> &'a Qube {
+        self.qube
+    }
+
+    pub fn root(&self) -> NodeIdx {
+        self.qube.root()
+    }
+
+    /// Get a node, or `None` if it is not part of the view.
+    pub fn node(&self, id: NodeIdx) -> Option<QubeViewNode<'_, 'a>> {
+        if !self.is_visible(id) {
+            return None;
+        }
+        let node = self.qube.node_ref(id)?;
+        Some(QubeViewNode { view: self, id, node })
+    }
+
+    /// The children of `id` that are part of the view.
+    pub fn children(&self, id: NodeIdx) -> impl Iterator<Item = NodeIdx> + '_ {
+        let mask = self.masks.get(&id).map(|mask| &mask.children);
+        self.qube
+            .node_ref(id)
+            .into_iter()
+            .flat_map(|node| node.children().values().flatten().copied())
+            .enumerate()
+            .filter(move |&(i, _)| mask.is_none_or(|mask| mask[i]))
+            .map(|(_, child)| child)
+    }
+
+    /// The values of `id` that are part of the view.
+    pub fn coordinates(&self, id: NodeIdx) -> Option<Cow<'a, Coordinates>> {
+        let coords = self.qube.node_ref(id)?.coords();
+        Some(match self.masks.get(&id) {
+            Some(mask) if !mask.values.all_true() => Cow::Owned(masked(coords, &mask.values)),
+            _ => Cow::Borrowed(coords),
+        })
+    }
+
+    fn is_visible(&self, id: NodeIdx) -> bool {
+        if id == self.root() {
+            return true;
+        }
+        let Some(parent) = self.qube.node_ref(id).and_then(|node| *node.parent()) else {
+            return false;
+        };
+        self.is_visible(parent) && self.children(parent).any(|child| child == id)
+    }
+
+    /// Narrows the view to the identifiers matching `selection`, in the same
+    /// way as [`Qube::select`].
+    pub fn select<C>(
+        &self,
+        selection: &[(&str, C)],
+        mode: SelectMode,
+    ) -> Result<QubeView<'a>, String>
+    where
+        C: Into<Selector> + Clone,
+    {
+        let selection: HashMap<&str, Selector> =
+            selection.iter().map(|(k, v)| (*k, v.clone().into().for_dimension(k))).collect();
+
+        if mode == SelectMode::Strict {
+            self.qube.check_dimensions_exist(selection.keys().copied())?;
+        }
+
+        let mut view = QubeView { qube: self.qube, masks: HashMap::new() };
+        let root_values = self.masks.get(&self.root()).map(|mask| mask.values.clone());
+        self.select_children(self.root(), root_values, &selection, &mut view.masks)?;
+
+        // Prune any branches which do not have all selected dimensions
+        if mode == SelectMode::Prune {
+            let required: HashSet<&str> = selection.keys().copied().collect();
+            let mut hidden = Vec::new();
+            view.find_unpruned(view.root(), &required, &mut hidden);
+            for id in hidden {
+                view.hide(id);
+            }
+        }
+
+        // Drop every leaf path that misses one of the selected dimensions
+        if mode == SelectMode::Strict {
+            let required: HashSet<&str> = selection.keys().copied().collect();
+            let mut hidden = Vec::new();
+            if !view.find_incomplete(view.root(), &required, &mut hidden) {
+                hidden = vec![view.root()];
+            }
+            for id in hidden {
+                view.hide(id);
+            }
+        }
+
+        Ok(view)
+    }
+
+    /// Narrows the children of `id` to `selection`, writing the masks of `id`
+    /// and its descendants to `masks`.  `values` is the value mask `id` ends up
+    /// with, or `None` if it keeps all of its values.
+    ///
+    /// Returns whether any child of `id` is kept.
+    fn select_children(
+        &self,
+        id: NodeIdx,
+        values: Option<SmallBitVec>,
+        selection: &HashMap<&str, Selector>,
+        masks: &mut HashMap<NodeIdx, QubeNodeMask>,
+    ) -> Result<bool, String> {
+        let node = self.qube.node_ref(id).ok_or_else(|| format!("Node {:?} not found", id))?;
+        let current = self.masks.get(&id).map(|mask| &mask.children);
+
+        let mut children = SmallBitVec::new();
+        for (i, &child_id) in node.children().values().flatten().enumerate() {
+            if current.is_some_and(|mask| !mask[i]) {
+                children.push(false);
+                continue;
+            }
+
+            let child = self
+                .qube
+                .node_ref(child_id)
+                .ok_or_else(|| format!("Child node {:?} not found", child_id))?;
+            let dimension = self.qube.dimension_str(child.dim()).ok_or_else(|| {
+                format!("Dimension {:?} not found in key store. Should not happen.", child.dim())
+            })?;
+            let old_values = self.masks.get(&child_id).map(|mask| &mask.values);
+
+            // Keep only the values that are both already in the view and selected.
+            let new_values = match selection.get(dimension) {
+                Some(selected) => {
+                    let tolerance = self.qube.float_tolerance(dimension);
+                    let mut mask = selected.mask(child.coords(), tolerance);
+                    if let Some(old_values) = old_values {
+                        for (j, kept) in old_values.iter().enumerate() {
+                            if !kept {
+                                mask.set(j, false);
+                            }
+                        }
+                    }
+                    if mask.all_false() {
+                        children.push(false);
+                        continue;
+                    }
+                    Some(mask)
+                }
+                None => old_values.cloned(),
+            };
+
+            // A branch with nothing left below it is dropped; leaves are
+            // always kept, their coordinates are the payload.
+            let had_children = self.children(child_id).next().is_some();
+            let kept = self.select_children(child_id, new_values, selection, masks)?;
+            children.push(kept || !had_children);
+        }
+
+        let kept = !children.all_false();
+        let values = values.filter(|values| !values.all_true());
+        if values.is_some() || !children.all_true() {
+            let values =
+                values.unwrap_or_else(|| SmallBitVec::from_elem(node.coords().len(), true));
+            masks.insert(id, QubeNodeMask { values, children });
+        }
+        Ok(kept)
+    }
+
+    /// Collects the nodes below `id` whose subtree lacks one of the `required`
+    /// dimensions, as [`Qube::prune`] would remove them.
+    fn find_unpruned(&self, id: NodeIdx, required: &HashSet<&str>, hidden: &mut Vec<NodeIdx>) {
+        let span = self.span(id);
+        if !required.iter().all(|dim| span.contains(dim)) {
+            hidden.push(id);
+            return;
+        }
+        for child in self.children(id) {
+            let mut required = required.clone();
+            if let Some(dim) = self.dimension_of(child) {
+                required.remove(dim);
+            }
+            self.find_unpruned(child, &required, hidden);
+        }
+    }
+
+    /// Collects the nodes below `id` that lead to no leaf path with all of the
+    /// `required` dimensions.  Unlike [`Self::find_unpruned`], a branch is only
+    /// kept if one of its own leaves has every dimension, not just some node of
+    /// its subtree.
+    ///
+    /// Returns whether any leaf path through `id` is complete.
+    fn find_incomplete(
+        &self,
+        id: NodeIdx,
+        required: &HashSet<&str>,
+        hidden: &mut Vec<NodeIdx>,
+    ) -> bool {
+        let mut required = required.clone();
+        if id != self.root()
+            && let Some(dim) = self.dimension_of(id)
+        {
+            required.remove(dim);
+        }
+
+        let mut children = self.children(id).peekable();
+        if children.peek().is_none() {
+            return required.is_empty();
+        }
+
+        let mut kept = false;
+        for child in children {
+            if self.find_incomplete(child, &required, hidden) {
+                kept = true;
+            } else {
+                hidden.push(child);
+            }
+        }
+        kept
+    }
+
+    /// The dimensions of every node below `id` in the view.
+    fn span(&self, id: NodeIdx) -> HashSet<&'a str> {
+        let mut dims = HashSet::new();
+        let mut stack: Vec<NodeIdx> = self.children(id).collect();
+        while let Some(child) = stack.pop() {
+            dims.extend(self.dimension_of(child));
+            stack.extend(self.children(child));
+        }
+        dims
+    }
+
+    fn dimension_of(&self, id: NodeIdx) -> Option<&'a str> {
+        self.qube.dimension_str(self.qube.node_ref(id)?.dim())
+    }
+
+    /// Removes `id` from the view.  Hiding the root hides all of its children.
+    fn hide(&mut self, id: NodeIdx) {
+        let (target, position) = match self.qube.node_ref(id).and_then(|node| *node.parent()) {
+            Some(parent) => {
+                let Some(node) = self.qube.node_ref(parent) else { return };
+                match node.children().values().flatten().position(|&c| c == id) {
+                    Some(i) => (parent, Some(i)),
+                    None => return,
+                }
+            }
+            None => (id, None),
+        };
+
+        let node = self.qube.node_ref(target).expect("valid node");
+        let mask = self.masks.entry(target).or_insert_with(|| QubeNodeMask {
+            values: SmallBitVec::from_elem(node.coords().len(), true),
+            children: SmallBitVec::from_elem(node.children().values().flatten().count(), true),
+        });
+        match position {
+            Some(i) => mask.children.set(i, false),
+            None => mask.children = SmallBitVec::from_elem(mask.children.len(), false),
+        }
+    }
+
+    /// Copies the view into a new Qube, keeping the metadata and float
+    /// tolerances of the original.
+    pub fn materialize(&self) -> Qube {
+        let mut result = Qube::new();
+        result.copy_float_tolerances(self.qube);
+
+        let root_meta = self.qube.get_node_metadata(self.root()).cloned().unwrap_or_default();
+        if !root_meta.is_empty() {
+            let result_root = result.root();
+            if let Some(node) = result.node_mut(result_root) {
+                *node.metadata_mut() = root_meta;
+            }
+        }
+
+        let result_root = result.root();
+        self.materialize_children(self.root(), &mut result, result_root);
+        result
+    }
+
+    fn materialize_children(&self, id: NodeIdx, result: &mut Qube, result_id: NodeIdx) {
+        for child in self.children(id) {
+            let dim = self.dimension_of(child).unwrap();
+            let coords = self.coordinates(child).unwrap().into_owned();
+            let new_child = result.get_or_create_child(dim, result_id, Some(coords)).unwrap();
+
+            let child_meta = self.qube.get_node_metadata(child).cloned().unwrap_or_default();
+            if !child_meta.is_empty() {
+                *result.node_mut(new_child).unwrap().metadata_mut() = child_meta;
+            }
+
+            self.materialize_children(child, result, new_child);
+        }
+    }
+
+    pub fn to_datacubes(&self) -> Vec<Datacube> {
+        fn traverse(
+            view: &QubeView,
+            id: NodeIdx,
+            datacube: &mut Vec<NodeIdx>,
+            datacubes: &mut Vec<Datacube>,
+        ) {
+            datacube.push(id);
+            let mut children = view.children(id).peekable();
+            if children.peek().is_none() {
+                let mut cube = Datacube::new();
+                for &node_id in datacube.iter() {
+                    if let (Some(dim), Some(coords)) =
+                        (view.dimension_of(node_id), view.coordinates(node_id))
+                    {
+                        cube.add_coordinate(dim, coords.into_owned());
+                    }
+                }
+                datacubes.push(cube);
+            } else {
+                for child in children {
+                    traverse(view, child, datacube, datacubes);
+                }
+            }
+            datacube.pop();
+        }
+
+        let mut datacubes = Vec::new();
+        traverse(self, self.root(), &mut Vec::new(), &mut datacubes);
+        datacubes
+    }
+
+    pub fn datacube_count(&self) -> usize {
+        fn count_leaves(view: &QubeView, id: NodeIdx) -> usize {
+            let mut children = view.children(id).peekable();
+            if children.peek().is_none() {
+                return 1;
+            }
+            children.map(|child| count_leaves(view, child)).sum()
+        }
+
+        count_leaves(self, self.root())
+    }
+
+    pub fn to_ascii(&self) -> String {
+        fn serialize_children(
+            view: &QubeView,
+            parent_id: NodeIdx,
+            prefix: &str,
+            output: &mut String,
+        ) {
+            let children_ids: Vec<_> = view.children(parent_id).collect();
+
+            for (i, &child_id) in children_ids.iter().enumerate() {
+                let is_last = i == children_ids.len() - 1;
+                let branch = if is_last { "└──" } else { "├──" };
+
+                let key = view.dimension_of(child_id).unwrap_or("unknown");
+                let values_str =
+                    view.coordinates(child_id).map(|c| c.to_string()).unwrap_or_default();
+
+                output.push_str(prefix);
+                output.push_str(branch);
+                output.push(' ');
+                output.push_str(&format!("{}={}", key, values_str));
+                output.push('\n');
+
+                let next_prefix =
+                    if is_last { format!("{}    ", prefix) } else { format!("{}│   ", prefix) };
+                serialize_children(view, child_id, &next_prefix, output);
+            }
+        }
+
+        let mut output = String::new();
+        output.push_str("root\n");
+        serialize_children(self, self.root(), "", &mut output);
+        output
+    }
+}
+
+impl<'v, 'a> QubeViewNode<'v, 'a> {
+    pub fn id(&self) -> NodeIdx {
+        self.id
+    }
+
+    pub fn dimension(&self) -> Option<&'a str> {
+        self.view.qube.dimension_str(self.node.dim())
+    }
+
+    /// The values of this node that are part of the view.
+    pub fn coordinates(&self) -> Cow<'a, Coordinates> {
+        match self.view.masks.get(&self.id) {
+            Some(mask) if !mask.values.all_true() => {
+                Cow::Owned(masked(self.node.coords(), &mask.values))
+            }
+            _ => Cow::Borrowed(self.node.coords()),
+        }
+    }
+
+    /// The children of this node that are part of the view.
+    pub fn children(&self) -> impl Iterator<Item = NodeIdx> + 'v {
+        self.view.children(self.id)
+    }
+
+    pub fn children_count(&self) -> usize {
+        self.children().count()
+    }
+
+    pub fn parent(&self) -> Option<NodeIdx> {
+        *self.node.parent()
+    }
+
+    pub fn metadata(&self) -> &'a Metadata {
+        self.node.metadata()
+    }
+}
+
+/// The values of `coords` whose bit is set in `mask`.
+fn masked(coords: &Coordinates, mask: &SmallBitVec) -> Coordinates {
+    let mut result = Coordinates::Empty;
+    for (value, keep) in coords.values().zip(mask.iter()) {
+        if keep {
+            result.append(value);
+        }
+    }
+    result
+}
+
+// -------------------------
+// Qube
+// -------------------------
+
+impl Qube {
+    /// A view of the whole Qube.
+    pub fn view(&self) -> QubeView<'_> {
+        QubeView::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example() -> Qube {
+        let input = r#"root
+├── class=od
+│   ├── expver=0001
+│   │   ├── param=1/2/3
+│   │   └── levtype=sfc
+│   │       └── param=4/5
+│   └── expver=0002
+│       └── param=1/2
+└── class=rd
+    └── expver=0001
+        └── param=2/3"#;
+        Qube::from_ascii(input).unwrap()
+    }
+
+    #[test]
+    fn test_view_of_whole_qube() {
+        let qube = example();
+        let view = qube.view();
+        assert_eq!(view.to_ascii(), qube.to_ascii());
+        assert_eq!(view.datacube_count(), qube.datacube_count());
+        assert_eq!(view.to_datacubes().len(), qube.to_datacubes().len());
+        assert_eq!(view.materialize().to_ascii(), qube.to_ascii());
+    }
+
+    #[test]
+    fn test_select_view_matches_select() -> Result<(), String> {
+        let qube = example();
+        let selection = [("param", &[2, 4])];
+        let expected = Qube::from_ascii(
+            r#"root
+├── class=od
+│   ├── expver=0001
+│   │   ├── param=2
+│   │   └── levtype=sfc
+│   │       └── param=4
+│   └── expver=0002
+│       └── param=2
+└── class=rd
+    └── expver=0001
+        └── param=2"#,
+        )?;
+        for mode in [SelectMode::Default, SelectMode::Prune] {
+            let view = qube.select_view(&selection, mode)?;
+            assert_eq!(view.to_ascii(), expected.to_ascii());
+            assert_eq!(view.datacube_count(), 4);
+            assert_eq!(view.materialize().to_ascii(), expected.to_ascii());
+            assert_eq!(qube.select(&selection, mode)?.to_ascii(), expected.to_ascii());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_view_nodes_and_children() -> Result<(), String> {
+        let qube = example();
+        let view = qube.select_view(&[("class", &["rd"])], SelectMode::Default)?;
+
+        let root = view.node(view.root()).unwrap();
+        let classes: Vec<_> = root.children().collect();
+        assert_eq!(classes.len(), 1);
+
+        let class = view.node(classes[0]).unwrap();
+        assert_eq!(class.dimension(), Some("class"));
+        assert_eq!(class.coordinates().to_string(), "rd");
+        assert!(matches!(class.coordinates(), Cow::Borrowed(_)));
+
+        // Nodes masked out of the view cannot be reached.
+        let od = qube.node(qube.root()).unwrap().all_children().find(|&c| c != classes[0]);
+        assert!(view.node(od.unwrap()).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_chained_views() -> Result<(), String> {
+        let qube = example();
+        let view = qube
+            .select_view(&[("class", &["od"])], SelectMode::Default)?
+            .select(&[("param", &[1, 5])], SelectMode::Default)?;
+
+        let expected = r#"root
+└── class=od
+    ├── expver=0001
+    │   ├── param=1
+    │   └── levtype=sfc
+    │       └── param=5
+    └── expver=0002
+        └── param=1"#;
+        assert_eq!(view.to_ascii(), Qube::from_ascii(expected)?.to_ascii());
+        assert_eq!(view.datacube_count(), 3);
+
+        // Narrowing further never brings back values the first view dropped.
+        let narrower = view.select(&[("param", &[2, 5])], SelectMode::Default)?;
+        assert_eq!(narrower.datacube_count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_prune_view() -> Result<(), String> {
+        let qube = example();
+        let view = qube.select_view(&[("levtype", &["sfc"])], SelectMode::Prune)?;
+        let expected = r#"root
+└── class=od
+    └── expver=0001
+        └── levtype=sfc
+            └── param=4/5"#;
+        assert_eq!(view.to_ascii(), Qube::from_ascii(expected)?.to_ascii());
+        assert_eq!(view.datacube_count(), 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,131 @@
+use qubed::Qube;
+
+fn q(ascii: &str) -> Qube {
+    Qube::from_ascii(ascii).unwrap()
+}
+
+// ---------------------------------------------------------------------------
+// Subset must respect the tree structure, not just the flattened axes
+// ---------------------------------------------------------------------------
+#[test]
+fn subset_is_structural_not_per_axis() {
+    let a = q(r#"root
+├── class=od
+│   └── param=1
+└── class=rd
+    └── param=2"#);
+    let cross = q(r#"root
+└── class=od
+    └── param=2"#);
+
+    assert!(!cross.is_subset_of(&a), "class=od,param=2 is not in A even though both axes are");
+    assert!(!a.is_superset_of(&cross));
+    assert!(cross.is_disjoint(&a));
+
+    let member = q(r#"root
+└── class=rd
+    └── param=2"#);
+    assert!(member.is_subset_of(&a));
+    assert!(a.is_superset_of(&member));
+    assert!(!member.is_disjoint(&a));
+}
+
+// ---------------------------------------------------------------------------
+// Empty Qubes
+// ---------------------------------------------------------------------------
+#[test]
+fn empty_qube_relations() {
+    let a = q(r#"root
+└── class=1
+    └── param=1/2"#);
+    let empty = Qube::new();
+
+    assert!(empty.is_subset_of(&a));
+    assert!(!a.is_subset_of(&empty));
+    assert!(empty.is_disjoint(&a));
+    assert!(a.is_disjoint(&empty));
+    assert!(empty == Qube::new());
+}
+
+// ---------------------------------------------------------------------------
+// Coverage split across several sibling nodes of the other Qube
+// ---------------------------------------------------------------------------
+#[test]
+fn subset_covered_by_several_siblings() {
+    let a = q(r#"root
+└── class=1/2
+    └── param=1/2"#);
+    let b = q(r#"root
+├── class=1
+│   ├── param=1
+│   └── param=2
+├── class=2
+│   └── param=1/2/3
+└── class=3
+    └── param=1"#);
+
+    assert!(a.is_subset_of(&b));
+    assert!(!b.is_subset_of(&a));
+    assert_eq!(b.subset_counter_example(&a).unwrap().coordinates().len(), 2);
+}
+
+// ---------------------------------------------------------------------------
+// Different schema depth: a leaf never covers a deeper path and vice versa
+// ---------------------------------------------------------------------------
+#[test]
+fn different_depths_are_not_related() {
+    let shallow = q(r#"root
+└── class=1"#);
+    let deep = q(r#"root
+└── class=1
+    └── param=1"#);
+
+    assert!(!shallow.is_subset_of(&deep));
+    assert!(!deep.is_subset_of(&shallow));
+    assert!(shallow.is_disjoint(&deep));
+}
+
+// ---------------------------------------------------------------------------
+// Equality ignores tree shape, compression and dimension interning order
+// ---------------------------------------------------------------------------
+#[test]
+fn equality_is_set_equality() {
+    let compressed = q(r#"root
+└── class=1
+    └── expver=0001/0002
+        └── param=1/2"#);
+    let expanded = q(r#"root
+└── class=1
+    ├── expver=0001
+    │   ├── param=1
+    │   └── param=2
+    └── expver=0002
+        └── param=1/2"#);
+    let different = q(r#"root
+└── class=1
+    └── expver=0001/0002
+        └── param=1"#);
+
+    assert!(compressed == expanded);
+    assert!(expanded == compressed);
+    assert!(compressed != different);
+    assert_eq!(&different | &compressed, compressed);
+    assert_eq!(&compressed & &different, different);
+}
+
+// ---------------------------------------------------------------------------
+// Counter-examples
+// ---------------------------------------------------------------------------
+#[test]
+fn counter_examples_are_none_when_relation_holds() {
+    let a = q(r#"root
+└── class=1
+    └── param=1"#);
+    let b = q(r#"root
+└── class=1
+    └── param=1/2"#);
+
+    assert!(a.subset_counter_example(&b).is_none());
+    assert!(b.subset_counter_example(&a).is_some());
+    assert!(a.disjoint_counter_example(&q("root\n└── class=2\n    └── param=1")).is_none());
+}
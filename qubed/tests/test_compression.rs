@@ -44,3 +44,34 @@ fn compress_uncompressed_tree() {
 
     assert_eq!(Qube::to_ascii(&qube_a), compressed_input_a, "identical compressed trees");
 }
+
+#[test]
+fn compress_coalesces_integer_runs_into_ranges() {
+    use qubed::{Coordinates, IntegerCoordinates};
+
+    let join =
+        |r: std::ops::RangeInclusive<i32>| r.map(|v| v.to_string()).collect::<Vec<_>>().join("/");
+    let input = format!(
+        "root\n├── class=od\n│   └── step={}\n└── class=od\n    └── step={}",
+        join(0..=180),
+        join(181..=360)
+    );
+
+    let mut qube = Qube::from_ascii(&input).unwrap();
+    qube.compress();
+
+    let class = qube.node(qube.root()).unwrap().all_children().collect::<Vec<_>>();
+    assert_eq!(class.len(), 1, "the two class=od branches should merge");
+    let step_id = qube.node(class[0]).unwrap().all_children().next().unwrap();
+    let steps = qube.node(step_id).unwrap().coordinates().clone();
+
+    match &steps {
+        Coordinates::Integers(IntegerCoordinates::RangeSet(ranges)) => {
+            assert_eq!(ranges.len(), 1, "0..=360 should be one range: {:?}", ranges);
+        }
+        other => panic!("expected a RangeSet, got {:?}", other),
+    }
+    assert_eq!(steps.len(), 361);
+    assert_eq!(steps.to_string(), "0/to/360");
+    assert_eq!(steps.to_value_strings().join("/"), join(0..=360));
+}
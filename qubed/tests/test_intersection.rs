@@ -0,0 +1,263 @@
+use qubed::{MetadataValues, Qube};
+
+// ---------------------------------------------------------------------------
+// Helper: build a Qube from ASCII and assert it equals expected ASCII
+// ---------------------------------------------------------------------------
+fn assert_ascii_eq(result: &Qube, expected: &str, msg: &str) {
+    let expected_qube = Qube::from_ascii(expected).unwrap();
+    assert_eq!(result.to_ascii(), expected_qube.to_ascii(), "{}", msg);
+}
+
+fn assert_empty(q: &Qube, msg: &str) {
+    assert!(q.is_empty(), "{msg}: expected empty Qube, got:\n{}", q.to_ascii());
+}
+
+// ---------------------------------------------------------------------------
+// Basic: A ∩ A = A
+// ---------------------------------------------------------------------------
+#[test]
+fn intersect_identical_qubes_returns_self() {
+    let a = Qube::from_ascii(
+        r#"root
+└── class=1
+    ├── expver=0001
+    │   └── param=1/2
+    └── expver=0002
+        └── param=1"#,
+    )
+    .unwrap();
+
+    let result = a.intersect(&a);
+    let mut expected = a.clone();
+    expected.compress();
+    assert_eq!(result.to_ascii(), expected.to_ascii(), "A ∩ A should equal A");
+}
+
+// ---------------------------------------------------------------------------
+// Basic: empty operands
+// ---------------------------------------------------------------------------
+#[test]
+fn intersect_with_empty_returns_empty() {
+    let a = Qube::from_ascii(
+        r#"root
+└── class=1
+    └── param=1/2"#,
+    )
+    .unwrap();
+    let empty = Qube::new();
+
+    assert_empty(&a.intersect(&empty), "A ∩ empty");
+    assert_empty(&empty.intersect(&a), "empty ∩ A");
+}
+
+// ---------------------------------------------------------------------------
+// Basic: completely disjoint qubes → empty
+// ---------------------------------------------------------------------------
+#[test]
+fn intersect_disjoint_returns_empty() {
+    let a = Qube::from_ascii(
+        r#"root
+└── class=1
+    └── param=1/2"#,
+    )
+    .unwrap();
+
+    let b = Qube::from_ascii(
+        r#"root
+└── class=2
+    └── param=1/2"#,
+    )
+    .unwrap();
+
+    assert_empty(&a.intersect(&b), "disjoint A ∩ B");
+}
+
+// ---------------------------------------------------------------------------
+// Partial overlap at several levels
+// ---------------------------------------------------------------------------
+#[test]
+fn intersect_partial_overlap() {
+    let a = Qube::from_ascii(
+        r#"root
+├── class=od
+│   └── param=1/2/3
+└── class=rd
+    └── param=1"#,
+    )
+    .unwrap();
+
+    let b = Qube::from_ascii(
+        r#"root
+└── class=od/rd
+    └── param=2/3/4"#,
+    )
+    .unwrap();
+
+    assert_ascii_eq(
+        &a.intersect(&b),
+        r#"root
+└── class=od
+    └── param=2/3"#,
+        "only class=od has params in common",
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Branches with no shared leaves are dropped entirely
+// ---------------------------------------------------------------------------
+#[test]
+fn intersect_drops_branches_without_common_leaves() {
+    let a = Qube::from_ascii(
+        r#"root
+└── class=1/2
+    └── expver=0001
+        └── param=1"#,
+    )
+    .unwrap();
+
+    let b = Qube::from_ascii(
+        r#"root
+├── class=1
+│   └── expver=0001
+│       └── param=1
+└── class=2
+    └── expver=0001
+        └── param=2"#,
+    )
+    .unwrap();
+
+    assert_ascii_eq(
+        &a.intersect(&b),
+        r#"root
+└── class=1
+    └── expver=0001
+        └── param=1"#,
+        "class=2 shares expver but no param",
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Different schema depth: a leaf on one side does not match deeper paths
+// ---------------------------------------------------------------------------
+#[test]
+fn intersect_different_depth_is_empty() {
+    let a = Qube::from_ascii(
+        r#"root
+└── class=1
+    └── param=1"#,
+    )
+    .unwrap();
+
+    let b = Qube::from_ascii(
+        r#"root
+└── class=1"#,
+    )
+    .unwrap();
+
+    assert_empty(&a.intersect(&b), "A ∩ B with different depth");
+    assert_empty(&b.intersect(&a), "B ∩ A with different depth");
+}
+
+// ---------------------------------------------------------------------------
+// Commutativity and consistency with subtract
+// ---------------------------------------------------------------------------
+#[test]
+fn intersect_is_commutative_and_matches_double_subtract() {
+    let a = Qube::from_ascii(
+        r#"root
+├── class=od
+│   ├── expver=0001
+│   │   └── param=1/2
+│   └── expver=0002
+│       └── param=1/2/3
+└── class=rd
+    └── expver=0001
+        └── param=1"#,
+    )
+    .unwrap();
+
+    let b = Qube::from_ascii(
+        r#"root
+├── class=od
+│   └── expver=0002
+│       └── param=2/3/4
+└── class=rd
+    └── expver=0001/0002
+        └── param=1/2"#,
+    )
+    .unwrap();
+
+    let ab = a.intersect(&b);
+    let ba = b.intersect(&a);
+    let emulated = a.subtract(&a.subtract(&b));
+
+    assert_eq!(ab.to_ascii(), ba.to_ascii(), "A ∩ B should equal B ∩ A");
+    assert_eq!(ab.to_ascii(), emulated.to_ascii(), "A ∩ B should equal A − (A − B)");
+}
+
+// ---------------------------------------------------------------------------
+// Operands are not modified; operator sugar
+// ---------------------------------------------------------------------------
+#[test]
+fn intersect_does_not_modify_inputs_and_operator_equals_method() {
+    let a = Qube::from_ascii(
+        r#"root
+└── class=1/2
+    └── param=1/2"#,
+    )
+    .unwrap();
+    let b = Qube::from_ascii(
+        r#"root
+└── class=2/3
+    └── param=2/3"#,
+    )
+    .unwrap();
+
+    let a_before = a.to_ascii();
+    let b_before = b.to_ascii();
+
+    let by_method = a.intersect(&b);
+    let by_operator = &a & &b;
+
+    assert_eq!(a.to_ascii(), a_before, "A must be unchanged");
+    assert_eq!(b.to_ascii(), b_before, "B must be unchanged");
+    assert_eq!(by_method.to_ascii(), by_operator.to_ascii());
+    assert_ascii_eq(
+        &by_operator,
+        r#"root
+└── class=2
+    └── param=2"#,
+        "single common identifier",
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Metadata: both sides are kept and merged
+// ---------------------------------------------------------------------------
+#[test]
+fn intersect_merges_metadata_from_both_sides() {
+    let mut a = Qube::from_ascii(
+        r#"root
+└── class=1
+    └── param=1/2"#,
+    )
+    .unwrap();
+    let mut b = Qube::from_ascii(
+        r#"root
+└── class=1
+    └── param=2/3"#,
+    )
+    .unwrap();
+
+    let a_root = a.root();
+    a.set_metadata(a_root, "source", MetadataValues::single_string("lumi")).unwrap();
+    let b_root = b.root();
+    b.set_metadata(b_root, "size", MetadataValues::single_integer(7)).unwrap();
+
+    let result = &a & &b;
+    let leaf = result.leaf_node_ids_paths()[0].last().copied().unwrap();
+    let meta = result.resolve_all_metadata(leaf, &Default::default());
+
+    assert!(meta.get("source").unwrap().contains_string("lumi"), "A's metadata lost");
+    assert!(meta.get("size").unwrap().contains_integer(7), "B's metadata lost");
+}
@@ -0,0 +1,251 @@
+use qubed::{MetadataValues, PROVENANCE_LEFT, PROVENANCE_RIGHT, Qube};
+use std::collections::HashMap;
+
+// ---------------------------------------------------------------------------
+// Helper: build a Qube from ASCII and assert it equals expected ASCII
+// ---------------------------------------------------------------------------
+fn assert_ascii_eq(result: &Qube, expected: &str, msg: &str) {
+    let expected_qube = Qube::from_ascii(expected).unwrap();
+    assert_eq!(result.to_ascii(), expected_qube.to_ascii(), "{}", msg);
+}
+
+fn assert_empty(q: &Qube, msg: &str) {
+    assert!(q.is_empty(), "{msg}: expected empty Qube, got:\n{}", q.to_ascii());
+}
+
+/// Resolve the provenance tag of the leaf reached by `path` (dimension → value).
+fn provenance_at(q: &Qube, key: &str, path: &[(&str, &str)]) -> Option<String> {
+    let path_map: HashMap<String, String> =
+        path.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+    q.leaf_node_ids_paths().into_iter().find_map(|ids| {
+        let matches = ids.iter().all(|&id| {
+            let node = q.node(id).unwrap();
+            match node.dimension().and_then(|d| path_map.get(d)) {
+                Some(v) => node.coordinates().coord_index_of(v).is_some(),
+                None => node.dimension() == Some("root"),
+            }
+        });
+        if !matches || ids.len() != path.len() + 1 {
+            return None;
+        }
+        let leaf = *ids.last().unwrap();
+        q.resolve_all_metadata(leaf, &path_map).get(key).map(|v| v.as_string_vec().join(","))
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Basic: A △ A = empty, A △ empty = A
+// ---------------------------------------------------------------------------
+#[test]
+fn symmetric_difference_identical_returns_empty() {
+    let a = Qube::from_ascii(
+        r#"root
+└── class=1
+    ├── expver=0001
+    │   └── param=1/2
+    └── expver=0002
+        └── param=1"#,
+    )
+    .unwrap();
+
+    assert_empty(&a.symmetric_difference(&a), "A △ A");
+}
+
+#[test]
+fn symmetric_difference_with_empty_returns_other() {
+    let a = Qube::from_ascii(
+        r#"root
+└── class=1
+    └── param=1/2"#,
+    )
+    .unwrap();
+    let empty = Qube::new();
+
+    assert_ascii_eq(&a.symmetric_difference(&empty), &a.to_ascii(), "A △ empty");
+    assert_ascii_eq(&empty.symmetric_difference(&a), &a.to_ascii(), "empty △ A");
+}
+
+// ---------------------------------------------------------------------------
+// Partial overlap at the leaves
+// ---------------------------------------------------------------------------
+#[test]
+fn symmetric_difference_partial_overlap() {
+    let a = Qube::from_ascii(
+        r#"root
+└── class=od
+    └── param=1/2/3"#,
+    )
+    .unwrap();
+    let b = Qube::from_ascii(
+        r#"root
+├── class=od
+│   └── param=2/3/4
+└── class=rd
+    └── param=1"#,
+    )
+    .unwrap();
+
+    assert_ascii_eq(
+        &a.symmetric_difference(&b),
+        r#"root
+├── class=od
+│   └── param=1/4
+└── class=rd
+    └── param=1"#,
+        "only the shared params disappear",
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Consistency with subtract + union, commutativity, operator sugar
+// ---------------------------------------------------------------------------
+#[test]
+fn symmetric_difference_matches_subtract_and_append() {
+    let a = Qube::from_ascii(
+        r#"root
+├── class=od
+│   ├── expver=0001
+│   │   └── param=1/2
+│   └── expver=0002
+│       └── param=1/2/3
+└── class=rd
+    └── expver=0001
+        └── param=1"#,
+    )
+    .unwrap();
+    let b = Qube::from_ascii(
+        r#"root
+├── class=od
+│   └── expver=0002
+│       └── param=2/3/4
+└── class=rd
+    └── expver=0001/0002
+        └── param=1/2"#,
+    )
+    .unwrap();
+
+    let mut expected = a.subtract(&b);
+    let mut b_only = b.subtract(&a);
+    expected.append(&mut b_only);
+
+    let ab = a.symmetric_difference(&b);
+    let ba = &b ^ &a;
+
+    assert_eq!(ab.to_ascii(), expected.to_ascii(), "A △ B should equal (A − B) ∪ (B − A)");
+    // Sibling order follows the left operand, so compare as sets.
+    assert!((&ab - &ba).is_empty() && (&ba - &ab).is_empty(), "A △ B should equal B △ A");
+}
+
+// ---------------------------------------------------------------------------
+// Different schema depth: both sides survive
+// ---------------------------------------------------------------------------
+#[test]
+fn symmetric_difference_different_depth_keeps_both() {
+    let a = Qube::from_ascii(
+        r#"root
+└── class=1
+    └── param=1"#,
+    )
+    .unwrap();
+    let b = Qube::from_ascii(
+        r#"root
+└── class=1"#,
+    )
+    .unwrap();
+
+    let result = a.symmetric_difference(&b);
+    assert_eq!(result.datacube_count(), 2, "got:\n{}", result.to_ascii());
+}
+
+// ---------------------------------------------------------------------------
+// Operands are not modified
+// ---------------------------------------------------------------------------
+#[test]
+fn symmetric_difference_does_not_modify_inputs() {
+    let a = Qube::from_ascii(
+        r#"root
+└── class=1/2
+    └── param=1/2"#,
+    )
+    .unwrap();
+    let b = Qube::from_ascii(
+        r#"root
+└── class=2/3
+    └── param=2/3"#,
+    )
+    .unwrap();
+
+    let a_before = a.to_ascii();
+    let b_before = b.to_ascii();
+    let _ = &a ^ &b;
+
+    assert_eq!(a.to_ascii(), a_before, "A must be unchanged");
+    assert_eq!(b.to_ascii(), b_before, "B must be unchanged");
+}
+
+// ---------------------------------------------------------------------------
+// Provenance tagging
+// ---------------------------------------------------------------------------
+#[test]
+fn symmetric_difference_tags_provenance() {
+    let a = Qube::from_ascii(
+        r#"root
+└── class=od
+    └── param=1/2/3"#,
+    )
+    .unwrap();
+    let b = Qube::from_ascii(
+        r#"root
+├── class=od
+│   └── param=2/3/4
+└── class=rd
+    └── param=1"#,
+    )
+    .unwrap();
+
+    let result = a.symmetric_difference_with_provenance(&b, "side");
+
+    assert_eq!(
+        provenance_at(&result, "side", &[("class", "od"), ("param", "1")]).as_deref(),
+        Some(PROVENANCE_LEFT)
+    );
+    assert_eq!(
+        provenance_at(&result, "side", &[("class", "od"), ("param", "4")]).as_deref(),
+        Some(PROVENANCE_RIGHT)
+    );
+    assert_eq!(
+        provenance_at(&result, "side", &[("class", "rd"), ("param", "1")]).as_deref(),
+        Some(PROVENANCE_RIGHT)
+    );
+}
+
+#[test]
+fn symmetric_difference_keeps_each_sides_root_metadata() {
+    let mut a = Qube::from_ascii(
+        r#"root
+└── class=od
+    └── param=1/2"#,
+    )
+    .unwrap();
+    let mut b = Qube::from_ascii(
+        r#"root
+└── class=od
+    └── param=2/3"#,
+    )
+    .unwrap();
+    let a_root = a.root();
+    a.set_metadata(a_root, "site", MetadataValues::single_string("lumi")).unwrap();
+    let b_root = b.root();
+    b.set_metadata(b_root, "site", MetadataValues::single_string("mn5")).unwrap();
+
+    let result = a.symmetric_difference(&b);
+
+    assert_eq!(
+        provenance_at(&result, "site", &[("class", "od"), ("param", "1")]).as_deref(),
+        Some("lumi")
+    );
+    assert_eq!(
+        provenance_at(&result, "site", &[("class", "od"), ("param", "3")]).as_deref(),
+        Some("mn5")
+    );
+}
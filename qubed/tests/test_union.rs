@@ -140,3 +140,127 @@ fn append_to_empty_qube_produces_other() {
     );
     assert!(other.is_empty(), "other should be empty after append");
 }
+
+#[test]
+fn union_leaves_both_operands_untouched() {
+    let a = Qube::from_ascii(
+        r#"root
+└── class=1
+    └── param=1/2"#,
+    )
+    .unwrap();
+    let b = Qube::from_ascii(
+        r#"root
+├── class=1
+│   └── param=3
+└── class=2
+    └── param=1"#,
+    )
+    .unwrap();
+
+    let a_before = a.to_ascii();
+    let b_before = b.to_ascii();
+
+    let result = a.union(&b);
+
+    assert_eq!(a.to_ascii(), a_before, "self must be unchanged");
+    assert_eq!(b.to_ascii(), b_before, "other must be unchanged");
+
+    let mut expected = a.clone();
+    let mut b_copy = b.clone();
+    expected.append(&mut b_copy);
+    assert_eq!(result.to_ascii(), expected.to_ascii(), "union should match append");
+}
+
+#[test]
+fn union_in_place_and_operators_match_union() {
+    let a = Qube::from_ascii(
+        r#"root
+└── class=1
+    └── param=1/2"#,
+    )
+    .unwrap();
+    let b = Qube::from_ascii(
+        r#"root
+└── class=2
+    └── param=1/2"#,
+    )
+    .unwrap();
+
+    let by_method = a.union(&b);
+    let by_operator = &a | &b;
+
+    let mut in_place = a.clone();
+    in_place.union_in_place(&b);
+
+    let mut or_assign = a.clone();
+    or_assign |= &b;
+
+    let expected = Qube::from_ascii(
+        r#"root
+└── class=1/2
+    └── param=1/2"#,
+    )
+    .unwrap();
+
+    for (name, q) in [
+        ("union", &by_method),
+        ("|", &by_operator),
+        ("union_in_place", &in_place),
+        ("|=", &or_assign),
+    ] {
+        assert_eq!(q.to_ascii(), expected.to_ascii(), "{name} gave the wrong result");
+    }
+    assert!(!b.is_empty(), "other must not be cleared");
+}
+
+#[test]
+fn union_keeps_other_and_its_metadata_untouched() {
+    use qubed::metadata::MetadataValues;
+
+    let mut a = Qube::from_ascii(
+        r#"root
+└── class=1
+    └── param=1/2"#,
+    )
+    .unwrap();
+    let leaf = a.leaf_node_ids_paths()[0][2];
+    a.set_metadata(leaf, "src", MetadataValues::single_string("a")).unwrap();
+
+    // `param=2/3` overlaps `param=1/2`, so the merge splits it, and the root
+    // metadata differs, so the merge pushes it down.
+    let mut b = Qube::from_ascii(
+        r#"root
+├── class=1
+│   └── param=2/3
+└── class=2
+    └── param=1"#,
+    )
+    .unwrap();
+    let root = b.root();
+    b.set_metadata(root, "src", MetadataValues::single_string("b")).unwrap();
+
+    let b_before = b.to_arena_json();
+    let result = a.union(&b);
+    assert_eq!(b.to_arena_json(), b_before, "other must be unchanged, metadata included");
+
+    let mut appended = a.clone();
+    let mut b_copy = b.clone();
+    appended.append(&mut b_copy);
+    assert_eq!(result.to_arena_json(), appended.to_arena_json());
+
+    // The three params stay apart because their `src` metadata differs.
+    let expected = Qube::from_ascii(
+        r#"root
+├── class=1
+│   ├── param=1
+│   ├── param=2
+│   └── param=3
+└── class=2
+    └── param=1"#,
+    )
+    .unwrap();
+    assert_eq!(result.to_ascii(), expected.to_ascii());
+    let arena = result.to_arena_json().to_string();
+    assert!(arena.contains("\"a\"") && arena.contains("\"b\""), "{arena}");
+}